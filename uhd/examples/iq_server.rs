@@ -0,0 +1,104 @@
+//!
+//! Streams samples from a USRP over the network
+//!
+//! Usage: iq_server [--args DEVICE_ARGS] [--channel N] [--mode rtl_tcp|tcp|udp] [--address ADDRESS]
+//!     [--frequency HZ] [--rate SAMPLES_PER_SECOND] [--gain DB]
+//!
+//! In rtl_tcp and tcp modes, the address is the address to listen on (default 0.0.0.0:1234).
+//! In udp mode, it is the address to send packets to.
+//!
+
+extern crate uhd;
+
+use std::error::Error;
+use std::net::{TcpListener, UdpSocket};
+
+use uhd::server::{Radio, RawServer, RawTransport, RtlTcpServer, UsrpRadio};
+use uhd::Usrp;
+
+#[derive(Debug)]
+struct Options {
+    args: String,
+    channel: usize,
+    mode: String,
+    address: String,
+    frequency: Option<f64>,
+    rate: Option<f64>,
+    gain: Option<f64>,
+}
+
+fn parse_options() -> Result<Options, Box<dyn Error>> {
+    let mut options = Options {
+        args: String::new(),
+        channel: 0,
+        mode: "rtl_tcp".to_string(),
+        address: "0.0.0.0:1234".to_string(),
+        frequency: None,
+        rate: None,
+        gain: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(name) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", name))?;
+        match name.as_str() {
+            "--args" => options.args = value,
+            "--channel" => options.channel = value.parse()?,
+            "--mode" => options.mode = value,
+            "--address" => options.address = value,
+            "--frequency" => options.frequency = Some(value.parse()?),
+            "--rate" => options.rate = Some(value.parse()?),
+            "--gain" => options.gain = Some(value.parse()?),
+            _ => return Err(format!("Unknown option {}", name).into()),
+        }
+    }
+    Ok(options)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options = parse_options()?;
+    let usrp = Usrp::open(&options.args)?;
+    let mut radio = UsrpRadio::new(&usrp, options.channel)?;
+    if let Some(rate) = options.rate {
+        radio.set_sample_rate(rate)?;
+    }
+    if let Some(frequency) = options.frequency {
+        radio.set_frequency(frequency)?;
+    }
+    if let Some(gain) = options.gain {
+        radio.set_gain(gain)?;
+    }
+
+    match options.mode.as_str() {
+        "rtl_tcp" => {
+            let server = RtlTcpServer::bind(&options.address)?;
+            println!("rtl_tcp server listening on {}", server.local_addr()?);
+            loop {
+                let session = server.serve_one(&mut radio)?;
+                println!("Client disconnected after {} bytes", session.bytes_sent);
+                for (command, error) in session.failed_commands {
+                    eprintln!("Command {:?} failed: {}", command, error);
+                }
+            }
+        }
+        "tcp" => {
+            let listener = TcpListener::bind(&options.address)?;
+            println!("Raw TCP server listening on {}", listener.local_addr()?);
+            loop {
+                let (stream, client) = listener.accept()?;
+                println!("Streaming to {}", client);
+                let packets = RawServer::new(RawTransport::Tcp(stream)).run(&mut radio, || true)?;
+                println!("Client disconnected after {} packets", packets);
+            }
+        }
+        "udp" => {
+            let socket = UdpSocket::bind("0.0.0.0:0")?;
+            socket.connect(&options.address)?;
+            println!("Sending UDP packets to {}", options.address);
+            RawServer::new(RawTransport::Udp(socket)).run(&mut radio, || true)?;
+            Ok(())
+        }
+        other => Err(format!("Unknown mode {}", other).into()),
+    }
+}
@@ -3,14 +3,19 @@ extern crate uhd;
 use std::error::Error;
 
 use num_complex::Complex32;
-use uhd::{Usrp, TuneRequest, ReceiveStreamer, StreamArgs};
+use uhd::{DeviceArgs, Usrp, TuneRequest, ReceiveStreamer, StreamArgs};
 
 fn main() -> Result<(), Box<dyn Error>> {
     let found_usrps = Usrp::find("")?;
 
     for address in found_usrps {
-        println!("Opening {}", address);
-        match probe_one_usrp(&address) {
+        println!(
+            "Opening {} (product {}, serial {})",
+            address,
+            address.product().unwrap_or("unknown"),
+            address.serial().unwrap_or("unknown")
+        );
+        match probe_one_usrp(address.args()) {
             Ok(_) => {}
             Err(e) => eprintln!("{}", e),
         }
@@ -19,8 +24,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn probe_one_usrp(address: &str) -> Result<(), Box<dyn Error>> {
-    let usrp = Usrp::open(address)?;
+fn probe_one_usrp(address: &DeviceArgs) -> Result<(), Box<dyn Error>> {
+    let usrp = Usrp::open_with_args(address)?;
     let num_mboards = usrp.get_num_motherboards()?;
     for board in 0..num_mboards {
         println!(
@@ -0,0 +1,95 @@
+use ndarray::{ArrayBase, Data, DataMut, Ix2};
+
+use crate::error::{Error, ErrorKind};
+use crate::receive_metadata::ReceiveMetadata;
+use crate::receive_streamer::ReceiveStreamer;
+use crate::transmit_metadata::TransmitMetadata;
+use crate::transmit_streamer::TransmitStreamer;
+
+impl<I> ReceiveStreamer<'_, I> {
+    /// Receives samples into a two-dimensional array with one row per channel
+    /// (channels × samples)
+    ///
+    /// timeout and one_packet have the same meaning as in [`receive`](#method.receive).
+    ///
+    /// The number of samples received (per channel) is available from the returned metadata.
+    ///
+    /// This function returns an error of kind `ErrorKind::BufferLength` if the array is not in
+    /// standard (row-major, contiguous) layout, or an error if the number of rows is not equal
+    /// to self.num_channels().
+    pub fn receive_array<S>(
+        &mut self,
+        array: &mut ArrayBase<S, Ix2>,
+        timeout: f64,
+        one_packet: bool,
+    ) -> Result<ReceiveMetadata, Error>
+    where
+        S: DataMut<Elem = I>,
+    {
+        let channels = array.nrows();
+        let buffer = array.as_slice_mut().ok_or_else(layout_error)?;
+        self.receive_planar(buffer, channels, timeout, one_packet)
+    }
+}
+
+impl<I> TransmitStreamer<'_, I> {
+    /// Sends samples from a two-dimensional array with one row per channel (channels × samples),
+    /// using the provided metadata to control bursts and timing
+    ///
+    /// metadata and timeout have the same meaning as in
+    /// [`send_with_metadata`](#method.send_with_metadata).
+    ///
+    /// On success, this function returns the number of samples (per channel) that were sent.
+    ///
+    /// This function returns an error of kind `ErrorKind::BufferLength` if the array is not in
+    /// standard (row-major, contiguous) layout, or an error if the number of rows is not equal
+    /// to self.num_channels().
+    pub fn send_array<S>(
+        &mut self,
+        array: &ArrayBase<S, Ix2>,
+        metadata: &mut TransmitMetadata,
+        timeout: f64,
+    ) -> Result<usize, Error>
+    where
+        S: Data<Elem = I>,
+    {
+        let buffer = array.as_slice().ok_or_else(layout_error)?;
+        self.send_planar(buffer, array.nrows(), metadata, timeout)
+    }
+}
+
+/// Returns an error for an array that is not in standard layout
+fn layout_error() -> Error {
+    Error::with_message(
+        ErrorKind::BufferLength,
+        "Array is not in standard (row-major, contiguous) layout".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{s, Array2, ShapeBuilder};
+
+    #[test]
+    fn non_standard_layout() {
+        // Neither function reaches the streamer handle, so a null handle is enough
+        let mut receiver = ReceiveStreamer::<i16>::new();
+        let mut fortran = Array2::<i16>::zeros((2, 8).f());
+        let error = receiver
+            .receive_array(&mut fortran, 0.1, false)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::BufferLength, error.kind());
+
+        let mut transmitter = TransmitStreamer::<i16>::new();
+        let mut metadata = TransmitMetadata::try_new().unwrap();
+        let array = Array2::<i16>::zeros((2, 8));
+        let sliced = array.slice(s![.., ..4]);
+        let error = transmitter
+            .send_array(&sliced, &mut metadata, 0.1)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::BufferLength, error.kind());
+    }
+}
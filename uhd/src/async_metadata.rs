@@ -0,0 +1,152 @@
+use std::ptr;
+
+use crate::error::{check_status, Error};
+use crate::TimeSpec;
+
+/// An asynchronous message from a USRP about a transmit stream
+pub struct AsyncMetadata {
+    /// Handle to C++ object
+    handle: uhd_sys::uhd_async_metadata_handle,
+}
+
+impl AsyncMetadata {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a metadata object, returning an error if UHD cannot allocate it
+    pub fn try_new() -> Result<Self, Error> {
+        let mut handle: uhd_sys::uhd_async_metadata_handle = ptr::null_mut();
+        check_status(unsafe { uhd_sys::uhd_async_metadata_make(&mut handle) })?;
+        Ok(AsyncMetadata { handle })
+    }
+
+    /// Returns the index of the channel that this message relates to
+    pub fn channel(&self) -> usize {
+        self.try_channel().unwrap()
+    }
+
+    /// Returns the index of the channel that this message relates to, or an error if it could
+    /// not be read
+    pub fn try_channel(&self) -> Result<usize, Error> {
+        let mut channel = 0usize;
+        check_status(unsafe {
+            uhd_sys::uhd_async_metadata_channel(self.handle, &mut channel as *mut usize as *mut _)
+        })?;
+        Ok(channel)
+    }
+
+    /// Returns the time when the event occurred, according to the USRP's internal clock
+    pub fn time_spec(&self) -> Option<TimeSpec> {
+        self.try_time_spec().unwrap()
+    }
+
+    /// Returns the time when the event occurred, or an error if it could not be read
+    pub fn try_time_spec(&self) -> Result<Option<TimeSpec>, Error> {
+        if self.has_time_spec()? {
+            let mut time = TimeSpec::default();
+            check_status(unsafe {
+                uhd_sys::uhd_async_metadata_time_spec(
+                    self.handle,
+                    &mut time.seconds,
+                    &mut time.fraction,
+                )
+            })?;
+            Ok(Some(time))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns true if this metadata object has a time
+    fn has_time_spec(&self) -> Result<bool, Error> {
+        let mut has = false;
+        check_status(unsafe { uhd_sys::uhd_async_metadata_has_time_spec(self.handle, &mut has) })?;
+        Ok(has)
+    }
+
+    /// Returns the event that this message reports
+    pub fn event(&self) -> AsyncEvent {
+        self.try_event().unwrap()
+    }
+
+    /// Returns the event that this message reports, or an error if it could not be read
+    pub fn try_event(&self) -> Result<AsyncEvent, Error> {
+        let mut code = 0;
+        check_status(unsafe { uhd_sys::uhd_async_metadata_event_code(self.handle, &mut code) })?;
+        Ok(AsyncEvent::from_code(code))
+    }
+
+    pub(crate) fn handle_mut(&mut self) -> &mut uhd_sys::uhd_async_metadata_handle {
+        &mut self.handle
+    }
+}
+
+// Thread safety: The uhd_async_metadata struct just stores data. All exposed functions read fields.
+unsafe impl Send for AsyncMetadata {}
+unsafe impl Sync for AsyncMetadata {}
+
+impl Default for AsyncMetadata {
+    fn default() -> Self {
+        AsyncMetadata::try_new().unwrap()
+    }
+}
+
+impl Drop for AsyncMetadata {
+    fn drop(&mut self) {
+        let _ = unsafe { uhd_sys::uhd_async_metadata_free(&mut self.handle) };
+    }
+}
+
+/// Events that a USRP can report about a transmit stream
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsyncEvent {
+    /// A burst was transmitted successfully
+    BurstAck,
+    /// An internal send buffer became empty
+    Underflow,
+    /// Packets were lost between the host and the device
+    SequenceError,
+    /// A packet had a time that was in the past
+    TimeError,
+    /// An underflow occurred inside a packet
+    UnderflowInPacket,
+    /// Packets were lost within a burst
+    SequenceErrorInBurst,
+    /// A custom payload from the FPGA
+    UserPayload,
+    /// An event code that this library does not recognize
+    Other(u32),
+}
+
+impl AsyncEvent {
+    fn from_code(code: uhd_sys::uhd_async_metadata_event_code_t::Type) -> Self {
+        use uhd_sys::uhd_async_metadata_event_code_t::*;
+        match code {
+            UHD_ASYNC_METADATA_EVENT_CODE_BURST_ACK => AsyncEvent::BurstAck,
+            UHD_ASYNC_METADATA_EVENT_CODE_UNDERFLOW => AsyncEvent::Underflow,
+            UHD_ASYNC_METADATA_EVENT_CODE_SEQ_ERROR => AsyncEvent::SequenceError,
+            UHD_ASYNC_METADATA_EVENT_CODE_TIME_ERROR => AsyncEvent::TimeError,
+            UHD_ASYNC_METADATA_EVENT_CODE_UNDERFLOW_IN_PACKET => AsyncEvent::UnderflowInPacket,
+            UHD_ASYNC_METADATA_EVENT_CODE_SEQ_ERROR_IN_BURST => AsyncEvent::SequenceErrorInBurst,
+            UHD_ASYNC_METADATA_EVENT_CODE_USER_PAYLOAD => AsyncEvent::UserPayload,
+            other => AsyncEvent::Other(other),
+        }
+    }
+}
+
+mod fmt {
+    use super::AsyncMetadata;
+    use std::fmt::{Debug, Formatter, Result};
+
+    impl Debug for AsyncMetadata {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            f.debug_struct("AsyncMetadata")
+                .field("channel", &self.try_channel())
+                .field("time_spec", &self.try_time_spec())
+                .field("event", &self.try_event())
+                .finish()
+        }
+    }
+}
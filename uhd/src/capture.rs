@@ -0,0 +1,181 @@
+use crate::error::{Error, ErrorKind};
+use crate::receive_metadata::{ReceiveErrorKind, ReceiveMetadataSnapshot};
+use crate::stream::{Item, StreamArgs, StreamCommand, StreamCommandType, StreamTime};
+use crate::usrp::Usrp;
+use crate::TimeSpec;
+
+/// Extra time to wait for samples after the scheduled start time, in seconds
+const START_MARGIN: f64 = 0.1;
+/// Timeout for each receive operation after the first samples arrive, in seconds
+const RECEIVE_TIMEOUT: f64 = 0.1;
+
+/// Samples received by `Usrp::capture_at`
+#[derive(Debug, Clone)]
+pub struct Capture<I> {
+    /// The received samples, one vector per channel in the order of the stream arguments
+    pub samples: Vec<Vec<I>>,
+    /// The device time of the first sample
+    pub time_spec: TimeSpec,
+    /// The sample rate of the first channel, in samples/second
+    pub sample_rate: f64,
+    /// Metadata from each receive operation, in order
+    pub metadata: Vec<ReceiveMetadataSnapshot>,
+}
+
+impl<I> Capture<I> {
+    /// Returns the number of samples per channel
+    pub fn len(&self) -> usize {
+        self.samples.first().map(Vec::len).unwrap_or(0)
+    }
+
+    /// Returns true if this capture contains no samples
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the samples received on a channel
+    ///
+    /// The channel index is relative to the channels in the stream arguments, not the USRP.
+    pub fn channel(&self, channel: usize) -> &[I] {
+        &self.samples[channel]
+    }
+}
+
+impl Usrp {
+    /// Receives exactly num_samples samples (per channel) starting at a device time
+    ///
+    /// This opens a stream with the provided arguments, sends a `CountAndDone` stream command
+    /// scheduled for the start time, and receives until the end of the burst.
+    ///
+    /// The returned capture is validated: the first sample must have the requested start time
+    /// and the burst must contain exactly num_samples samples. This function returns an error
+    /// of kind `ErrorKind::Receive` if the USRP reports an error (`ReceiveErrorKind::LateCommand`
+    /// if the start time had already passed when the command arrived), `ErrorKind::Timestamp`
+    /// if the first sample has a different time, or `ErrorKind::IncompleteCapture` if the burst
+    /// ended early or the samples stopped arriving.
+    ///
+    /// Example:
+    ///
+    /// ```no_run
+    /// use num_complex::Complex32;
+    /// use uhd::{StreamArgs, TimeSpec, Usrp};
+    ///
+    /// # fn main() -> Result<(), uhd::Error> {
+    /// let usrp = Usrp::open("")?;
+    /// let args = StreamArgs::<Complex32>::builder()
+    ///     .channels(vec![0, 1, 2, 3])
+    ///     .build();
+    /// let start = usrp.get_current_time(0)? + TimeSpec::from_secs_f64(0.5);
+    /// let capture = usrp.capture_at(&args, start, 100_000)?;
+    /// assert_eq!(4, capture.samples.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn capture_at<I>(
+        &self,
+        args: &StreamArgs<I>,
+        start: TimeSpec,
+        num_samples: usize,
+    ) -> Result<Capture<I>, Error>
+    where
+        I: Item + Copy + Default,
+    {
+        let first_channel = args.channels().first().copied().unwrap_or(0);
+        let sample_rate = self.get_rx_sample_rate(first_channel)?;
+        let mut streamer = self.get_rx_stream(args)?;
+        let mut samples = vec![vec![I::default(); num_samples]; streamer.try_num_channels()?];
+
+        streamer.send_command(&StreamCommand {
+            command_type: StreamCommandType::CountAndDone(num_samples as u64),
+            time: StreamTime::Later(start),
+        })?;
+
+        // Wait until the start time for the first samples
+        let now = self.get_current_time(0)?;
+        let mut timeout = (start - now).as_secs_f64().max(0.0) + START_MARGIN;
+
+        let mut received = 0;
+        let mut snapshots = Vec::new();
+        let mut buffers: Vec<&mut [I]> = samples.iter_mut().map(Vec::as_mut_slice).collect();
+        while received < num_samples {
+            let mut remaining: Vec<&mut [I]> = buffers
+                .iter_mut()
+                .map(|buffer| &mut buffer[received..])
+                .collect();
+            let metadata = streamer.receive(&mut remaining, timeout, false)?;
+            let snapshot = metadata.snapshot()?;
+            if let Some(kind) = snapshot.error {
+                if kind == ReceiveErrorKind::Timeout {
+                    return Err(incomplete(received, num_samples));
+                }
+                let message = metadata
+                    .try_last_error()?
+                    .map(|error| error.to_string())
+                    .unwrap_or_default();
+                return Err(Error::with_message(ErrorKind::Receive(kind), message));
+            }
+            if received == 0 && snapshot.samples != 0 {
+                check_start_time(start, snapshot.time_spec, sample_rate)?;
+            }
+            received += snapshot.samples;
+            snapshots.push(snapshot);
+            timeout = RECEIVE_TIMEOUT;
+            if snapshot.end_of_burst && received < num_samples {
+                return Err(incomplete(received, num_samples));
+            }
+        }
+        drop(buffers);
+
+        Ok(Capture {
+            samples,
+            time_spec: start,
+            sample_rate,
+            metadata: snapshots,
+        })
+    }
+}
+
+/// Checks that the time of the first received sample is within half a sample of the requested
+/// start time
+fn check_start_time(
+    start: TimeSpec,
+    actual: Option<TimeSpec>,
+    sample_rate: f64,
+) -> Result<(), Error> {
+    match actual {
+        Some(actual) if (actual - start).to_ticks(sample_rate) == 0 => Ok(()),
+        Some(actual) => Err(Error::with_message(
+            ErrorKind::Timestamp,
+            format!(
+                "First sample has time {}, but the capture was scheduled for {}",
+                actual, start
+            ),
+        )),
+        None => Err(Error::with_message(
+            ErrorKind::Timestamp,
+            "First sample has no timestamp".to_string(),
+        )),
+    }
+}
+
+fn incomplete(received: usize, expected: usize) -> Error {
+    Error::with_message(
+        ErrorKind::IncompleteCapture,
+        format!("Received {} of {} samples", received, expected),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_time() {
+        let start = TimeSpec::new(10, 0.5);
+        assert!(check_start_time(start, Some(TimeSpec::new(10, 0.5000004)), 1e6).is_ok());
+        let error = check_start_time(start, Some(TimeSpec::new(10, 0.500001)), 1e6).unwrap_err();
+        assert_eq!(ErrorKind::Timestamp, error.kind());
+        let error = check_start_time(start, None, 1e6).unwrap_err();
+        assert_eq!(ErrorKind::Timestamp, error.kind());
+    }
+}
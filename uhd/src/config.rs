@@ -0,0 +1,453 @@
+//!
+//! Device configuration profiles
+//!
+//! A `UsrpConfig` describes the settings of the motherboards and channels of a USRP. It can be
+//! loaded from TOML or YAML, applied to a `Usrp`, and captured from a `Usrp`.
+//!
+//! Example TOML configuration:
+//!
+//! ```toml
+//! [[mboards]]
+//! clock_source = "external"
+//! time_source = "external"
+//! rx_subdev_spec = "A:0 B:0"
+//!
+//! [[rx]]
+//! antenna = "RX2"
+//! frequency = 2.45e9
+//! sample_rate = 1e6
+//! gain = 30.0
+//!
+//! [[rx]]
+//! antenna = "RX2"
+//! frequency = 2.45e9
+//! sample_rate = 1e6
+//! gain = 30.0
+//! ```
+//!
+//! Settings that are not present in a configuration are not changed when the configuration is
+//! applied.
+//!
+//! This module is available when the `config` feature is enabled.
+//!
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, ErrorKind};
+use crate::tune_request::TuneRequest;
+use crate::usrp::Usrp;
+
+/// Settings for a USRP
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsrpConfig {
+    /// Settings for each motherboard, in order of motherboard index
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mboards: Vec<MboardConfig>,
+    /// Settings for each receive channel, in order of channel index
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rx: Vec<ChannelConfig>,
+    /// Settings for each transmit channel, in order of channel index
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tx: Vec<ChannelConfig>,
+}
+
+/// Settings for one motherboard
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MboardConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_source: Option<String>,
+    /// The receive subdevice specification, in markup form (for example, `A:0 B:0`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rx_subdev_spec: Option<String>,
+    /// The transmit subdevice specification, in markup form
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_subdev_spec: Option<String>,
+}
+
+/// Settings for one receive or transmit channel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub antenna: Option<String>,
+    /// Center frequency, hertz
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<f64>,
+    /// Sample rate, samples/second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+    /// Overall gain, decibels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f64>,
+    /// Analog bandwidth, hertz
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<f64>,
+}
+
+impl UsrpConfig {
+    /// Parses a configuration from TOML
+    pub fn from_toml(text: &str) -> Result<Self, Error> {
+        toml::from_str(text).map_err(format_error)
+    }
+
+    /// Converts this configuration into TOML
+    pub fn to_toml(&self) -> Result<String, Error> {
+        toml::to_string(self).map_err(format_error)
+    }
+
+    /// Parses a configuration from YAML
+    pub fn from_yaml(text: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(text).map_err(format_error)
+    }
+
+    /// Converts this configuration into YAML
+    pub fn to_yaml(&self) -> Result<String, Error> {
+        serde_yaml::to_string(self).map_err(format_error)
+    }
+
+    /// Loads a configuration from a file
+    ///
+    /// Files with the extension `yaml` or `yml` are parsed as YAML. All other files are parsed as
+    /// TOML.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        if is_yaml(path) {
+            Self::from_yaml(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    /// Saves this configuration to a file, choosing the format from the extension like `load`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let text = if is_yaml(path) {
+            self.to_yaml()?
+        } else {
+            self.to_toml()?
+        };
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Applies this configuration to a USRP
+    ///
+    /// Motherboard settings are applied first, so that subdevice specifications take effect
+    /// before channel settings. For each channel, the sample rate is set before the frequency.
+    ///
+    /// A failure to apply one setting does not stop the others from being applied. The returned
+    /// report lists every setting with the value that the USRP actually uses or the error that
+    /// occurred.
+    pub fn apply(&self, usrp: &Usrp) -> ApplyReport {
+        let mut report = ApplyReport::default();
+        for (mboard, config) in self.mboards.iter().enumerate() {
+            let target = Target::Mboard(mboard);
+            if let Some(source) = &config.clock_source {
+                report.text(target, Setting::ClockSource, source, || {
+                    usrp.set_clock_source(source, mboard)?;
+                    usrp.get_clock_source(mboard)
+                });
+            }
+            if let Some(source) = &config.time_source {
+                report.text(target, Setting::TimeSource, source, || {
+                    usrp.set_time_source(source, mboard)?;
+                    usrp.get_time_source(mboard)
+                });
+            }
+            if let Some(spec) = &config.rx_subdev_spec {
+                report.text(target, Setting::RxSubdevSpec, spec, || {
+                    usrp.set_rx_subdev_spec(spec, mboard)?;
+                    usrp.get_rx_subdev_spec(mboard)
+                });
+            }
+            if let Some(spec) = &config.tx_subdev_spec {
+                report.text(target, Setting::TxSubdevSpec, spec, || {
+                    usrp.set_tx_subdev_spec(spec, mboard)?;
+                    usrp.get_tx_subdev_spec(mboard)
+                });
+            }
+        }
+
+        for (channel, config) in self.rx.iter().enumerate() {
+            let target = Target::Rx(channel);
+            if let Some(rate) = config.sample_rate {
+                report.number(target, Setting::SampleRate, rate, || {
+                    usrp.set_rx_sample_rate(rate, channel)?;
+                    usrp.get_rx_sample_rate(channel)
+                });
+            }
+            if let Some(antenna) = &config.antenna {
+                report.text(target, Setting::Antenna, antenna, || {
+                    usrp.set_rx_antenna(antenna, channel)?;
+                    usrp.get_rx_antenna(channel)
+                });
+            }
+            if let Some(frequency) = config.frequency {
+                report.number(target, Setting::Frequency, frequency, || {
+                    usrp.set_rx_frequency(&TuneRequest::with_frequency(frequency), channel)?;
+                    usrp.get_rx_frequency(channel)
+                });
+            }
+            if let Some(gain) = config.gain {
+                report.number(target, Setting::Gain, gain, || {
+                    usrp.set_rx_gain(gain, channel, "")?;
+                    usrp.get_rx_gain(channel, "")
+                });
+            }
+            if let Some(bandwidth) = config.bandwidth {
+                report.number(target, Setting::Bandwidth, bandwidth, || {
+                    usrp.set_rx_bandwidth(bandwidth, channel)?;
+                    usrp.get_rx_bandwidth(channel)
+                });
+            }
+        }
+
+        for (channel, config) in self.tx.iter().enumerate() {
+            let target = Target::Tx(channel);
+            if let Some(rate) = config.sample_rate {
+                report.number(target, Setting::SampleRate, rate, || {
+                    usrp.set_tx_sample_rate(rate, channel)?;
+                    usrp.get_tx_sample_rate(channel)
+                });
+            }
+            if let Some(antenna) = &config.antenna {
+                report.text(target, Setting::Antenna, antenna, || {
+                    usrp.set_tx_antenna(antenna, channel)?;
+                    usrp.get_tx_antenna(channel)
+                });
+            }
+            if let Some(frequency) = config.frequency {
+                report.number(target, Setting::Frequency, frequency, || {
+                    usrp.set_tx_frequency(&TuneRequest::with_frequency(frequency), channel)?;
+                    usrp.get_tx_frequency(channel)
+                });
+            }
+            if let Some(gain) = config.gain {
+                report.number(target, Setting::Gain, gain, || {
+                    usrp.set_tx_gain(gain, channel, "")?;
+                    usrp.get_tx_gain(channel, "")
+                });
+            }
+            if let Some(bandwidth) = config.bandwidth {
+                report.number(target, Setting::Bandwidth, bandwidth, || {
+                    usrp.set_tx_bandwidth(bandwidth, channel)?;
+                    usrp.get_tx_bandwidth(channel)
+                });
+            }
+        }
+        report
+    }
+
+    /// Reads the current settings of all motherboards and channels of a USRP
+    ///
+    /// This returns an error if a basic setting (such as the frequency or antenna of a channel)
+    /// cannot be read. Settings that some devices do not support (bandwidth and subdevice
+    /// specifications) are left as None if they cannot be read.
+    pub fn capture(usrp: &Usrp) -> Result<Self, Error> {
+        let mboards = (0..usrp.get_num_motherboards()?)
+            .map(|mboard| {
+                Ok(MboardConfig {
+                    clock_source: Some(usrp.get_clock_source(mboard)?),
+                    time_source: Some(usrp.get_time_source(mboard)?),
+                    rx_subdev_spec: usrp.get_rx_subdev_spec(mboard).ok(),
+                    tx_subdev_spec: usrp.get_tx_subdev_spec(mboard).ok(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        let rx = (0..usrp.get_num_rx_channels()?)
+            .map(|channel| {
+                Ok(ChannelConfig {
+                    antenna: Some(usrp.get_rx_antenna(channel)?),
+                    frequency: Some(usrp.get_rx_frequency(channel)?),
+                    sample_rate: Some(usrp.get_rx_sample_rate(channel)?),
+                    gain: Some(usrp.get_rx_gain(channel, "")?),
+                    bandwidth: usrp.get_rx_bandwidth(channel).ok(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        let tx = (0..usrp.get_num_tx_channels()?)
+            .map(|channel| {
+                Ok(ChannelConfig {
+                    antenna: Some(usrp.get_tx_antenna(channel)?),
+                    frequency: Some(usrp.get_tx_frequency(channel)?),
+                    sample_rate: Some(usrp.get_tx_sample_rate(channel)?),
+                    gain: Some(usrp.get_tx_gain(channel, "")?),
+                    bandwidth: usrp.get_tx_bandwidth(channel).ok(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(UsrpConfig { mboards, rx, tx })
+    }
+}
+
+/// Returns true if a path has a YAML file extension
+fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("yaml") | Some("yml")
+    )
+}
+
+/// Converts a TOML or YAML error into an Error
+fn format_error<E: fmt::Display>(error: E) -> Error {
+    Error::with_message(ErrorKind::Value, error.to_string())
+}
+
+/// The motherboard or channel that a setting applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Mboard(usize),
+    Rx(usize),
+    Tx(usize),
+}
+
+/// A setting in a configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    ClockSource,
+    TimeSource,
+    RxSubdevSpec,
+    TxSubdevSpec,
+    Antenna,
+    Frequency,
+    SampleRate,
+    Gain,
+    Bandwidth,
+}
+
+/// The value of a setting
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingValue {
+    Text(String),
+    Number(f64),
+}
+
+/// The result of applying one setting
+#[derive(Debug)]
+pub struct AppliedSetting {
+    pub target: Target,
+    pub setting: Setting,
+    /// The value in the configuration
+    pub requested: SettingValue,
+    /// The value that the USRP reported after the setting was applied, or the error that
+    /// occurred
+    pub actual: Result<SettingValue, Error>,
+}
+
+/// The results of applying a configuration
+#[derive(Debug, Default)]
+pub struct ApplyReport {
+    pub settings: Vec<AppliedSetting>,
+}
+
+impl ApplyReport {
+    /// Returns true if all settings were applied without errors
+    pub fn is_ok(&self) -> bool {
+        self.settings.iter().all(|setting| setting.actual.is_ok())
+    }
+
+    /// Returns the settings that could not be applied
+    pub fn errors(&self) -> impl Iterator<Item = &AppliedSetting> {
+        self.settings
+            .iter()
+            .filter(|setting| setting.actual.is_err())
+    }
+
+    /// Returns the result of applying a setting, if the configuration contained it
+    pub fn get(&self, target: Target, setting: Setting) -> Option<&AppliedSetting> {
+        self.settings
+            .iter()
+            .find(|applied| applied.target == target && applied.setting == setting)
+    }
+
+    fn text<F>(&mut self, target: Target, setting: Setting, requested: &str, operation: F)
+    where
+        F: FnOnce() -> Result<String, Error>,
+    {
+        self.settings.push(AppliedSetting {
+            target,
+            setting,
+            requested: SettingValue::Text(requested.to_string()),
+            actual: operation().map(SettingValue::Text),
+        });
+    }
+
+    fn number<F>(&mut self, target: Target, setting: Setting, requested: f64, operation: F)
+    where
+        F: FnOnce() -> Result<f64, Error>,
+    {
+        self.settings.push(AppliedSetting {
+            target,
+            setting,
+            requested: SettingValue::Number(requested),
+            actual: operation().map(SettingValue::Number),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> UsrpConfig {
+        UsrpConfig {
+            mboards: vec![MboardConfig {
+                clock_source: Some("external".to_string()),
+                rx_subdev_spec: Some("A:0 B:0".to_string()),
+                ..MboardConfig::default()
+            }],
+            rx: vec![
+                ChannelConfig {
+                    antenna: Some("RX2".to_string()),
+                    frequency: Some(2.45e9),
+                    sample_rate: Some(1e6),
+                    gain: Some(30.0),
+                    bandwidth: None,
+                },
+                ChannelConfig::default(),
+            ],
+            tx: vec![],
+        }
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let config = example();
+        let text = config.to_toml().unwrap();
+        assert!(!text.contains("time_source"));
+        assert_eq!(config, UsrpConfig::from_toml(&text).unwrap());
+
+        let parsed = UsrpConfig::from_toml(
+            "[[mboards]]\nclock_source = \"external\"\nrx_subdev_spec = \"A:0 B:0\"\n\n\
+             [[rx]]\nantenna = \"RX2\"\nfrequency = 2.45e9\nsample_rate = 1e6\ngain = 30.0\n\n\
+             [[rx]]\n",
+        )
+        .unwrap();
+        assert_eq!(config, parsed);
+        assert!(UsrpConfig::from_toml("[[rx]]\ngain = \"high\"").is_err());
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let config = example();
+        let text = config.to_yaml().unwrap();
+        assert_eq!(config, UsrpConfig::from_yaml(&text).unwrap());
+
+        let parsed = UsrpConfig::from_yaml(
+            "mboards:\n  - clock_source: external\n    rx_subdev_spec: \"A:0 B:0\"\n\
+             rx:\n  - antenna: RX2\n    frequency: 2.45e9\n    sample_rate: 1.0e6\n    gain: 30\n\
+             \x20 - {}\n",
+        )
+        .unwrap();
+        assert_eq!(config, parsed);
+    }
+}
@@ -0,0 +1,327 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, ErrorKind};
+
+/// Arguments that identify and configure a USRP, in UHD's `key=value,key=value` syntax
+///
+/// Keys are kept in the order they were added. Setting a key that already exists replaces its
+/// value.
+///
+/// ```
+/// use uhd::DeviceArgs;
+/// let args: DeviceArgs = "type=b200, serial=30AD2C5".parse().unwrap();
+/// assert_eq!(Some("b200"), args.device_type());
+/// let args = args.with("master_clock_rate", "32e6");
+/// assert_eq!("type=b200,serial=30AD2C5,master_clock_rate=32e6", args.to_string());
+/// ```
+///
+/// Arguments for several motherboards used together have keys with an index suffix (for
+/// example, `addr0=192.168.10.2,addr1=192.168.10.3`). `split` separates them into arguments
+/// for each motherboard, and `combine` joins them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceArgs {
+    pairs: Vec<(String, String)>,
+}
+
+impl DeviceArgs {
+    /// Creates empty arguments
+    pub fn new() -> Self {
+        DeviceArgs::default()
+    }
+
+    /// Returns the value of a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(existing, _)| existing == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of a key
+    pub fn insert(&mut self, key: &str, value: &str) {
+        match self.pairs.iter_mut().find(|(existing, _)| existing == key) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.pairs.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// Sets the value of a key and returns the modified arguments
+    pub fn with(mut self, key: &str, value: &str) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// Removes a key and returns its value
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let index = self
+            .pairs
+            .iter()
+            .position(|(existing, _)| existing == key)?;
+        Some(self.pairs.remove(index).1)
+    }
+
+    /// Returns true if a key is present
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns an iterator over keys and values
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Returns the number of keys
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns true if there are no keys
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Returns the device type (the `type` key, for example `b200` or `x300`)
+    pub fn device_type(&self) -> Option<&str> {
+        self.get("type")
+    }
+
+    /// Returns the serial number (the `serial` key)
+    pub fn serial(&self) -> Option<&str> {
+        self.get("serial")
+    }
+
+    /// Returns the network address (the `addr` key)
+    pub fn addr(&self) -> Option<&str> {
+        self.get("addr")
+    }
+
+    /// Returns the user-assigned name (the `name` key)
+    pub fn name(&self) -> Option<&str> {
+        self.get("name")
+    }
+
+    /// Returns the product name (the `product` key, for example `B210`)
+    pub fn product(&self) -> Option<&str> {
+        self.get("product")
+    }
+
+    /// Returns the resource name of a PCIe device (the `resource` key)
+    pub fn resource(&self) -> Option<&str> {
+        self.get("resource")
+    }
+
+    /// Separates arguments for several motherboards
+    ///
+    /// A key with a numeric suffix (such as `addr1`) applies to the motherboard with that index.
+    /// Keys without a suffix apply to all motherboards. If no keys have suffixes, this returns
+    /// one copy of the arguments.
+    pub fn split(&self) -> Vec<DeviceArgs> {
+        let mut devices: Vec<DeviceArgs> = Vec::new();
+        let mut common = DeviceArgs::new();
+        for (key, value) in self.iter() {
+            let name_length = key.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+            match key[name_length..].parse::<usize>() {
+                Ok(index) if name_length != 0 => {
+                    if devices.len() <= index {
+                        devices.resize(index + 1, DeviceArgs::new());
+                    }
+                    devices[index].insert(&key[..name_length], value);
+                }
+                _ => common.insert(key, value),
+            }
+        }
+        if devices.is_empty() {
+            return vec![common];
+        }
+        for device in devices.iter_mut() {
+            for (key, value) in common.iter() {
+                if !device.contains_key(key) {
+                    device.insert(key, value);
+                }
+            }
+        }
+        devices
+    }
+
+    /// Combines arguments for several motherboards, adding index suffixes to the keys if
+    /// there is more than one
+    pub fn combine(devices: &[DeviceArgs]) -> DeviceArgs {
+        match devices {
+            [device] => device.clone(),
+            _ => {
+                let mut combined = DeviceArgs::new();
+                for (index, device) in devices.iter().enumerate() {
+                    for (key, value) in device.iter() {
+                        combined.insert(&format!("{}{}", key, index), value);
+                    }
+                }
+                combined
+            }
+        }
+    }
+}
+
+impl FromStr for DeviceArgs {
+    type Err = Error;
+
+    /// Parses arguments
+    ///
+    /// Whitespace around keys and values is ignored, and so are empty entries. A key without
+    /// `=` has an empty value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut args = DeviceArgs::new();
+        for pair in s.split(',') {
+            let pair = pair.trim();
+            if pair.is_empty() {
+                continue;
+            }
+            let mut parts = pair.splitn(2, '=');
+            let key = parts.next().unwrap_or_default().trim();
+            let value = parts.next().unwrap_or_default().trim();
+            if key.is_empty() || value.contains('=') {
+                return Err(Error::with_message(
+                    ErrorKind::Value,
+                    format!("Invalid device argument \"{}\"", pair),
+                ));
+            }
+            args.insert(key, value);
+        }
+        Ok(args)
+    }
+}
+
+impl fmt::Display for DeviceArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The address of a device found by `Usrp::find`
+///
+/// Display formats the address in the form that `Usrp::open` accepts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceAddr {
+    args: DeviceArgs,
+}
+
+impl DeviceAddr {
+    /// Returns all the arguments that describe this device
+    pub fn args(&self) -> &DeviceArgs {
+        &self.args
+    }
+
+    /// Returns the device type (for example, `b200` or `x300`)
+    pub fn device_type(&self) -> Option<&str> {
+        self.args.device_type()
+    }
+
+    /// Returns the serial number
+    pub fn serial(&self) -> Option<&str> {
+        self.args.serial()
+    }
+
+    /// Returns the network address of a networked device
+    pub fn addr(&self) -> Option<&str> {
+        self.args.addr()
+    }
+
+    /// Returns the user-assigned name
+    pub fn name(&self) -> Option<&str> {
+        self.args.name()
+    }
+
+    /// Returns the product name (for example, `B210` or `X310`)
+    pub fn product(&self) -> Option<&str> {
+        self.args.product()
+    }
+
+    /// Returns the resource name of a PCIe device
+    pub fn resource(&self) -> Option<&str> {
+        self.args.resource()
+    }
+}
+
+impl FromStr for DeviceAddr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(DeviceAddr::from(s.parse::<DeviceArgs>()?))
+    }
+}
+
+impl From<DeviceArgs> for DeviceAddr {
+    fn from(args: DeviceArgs) -> Self {
+        DeviceAddr { args }
+    }
+}
+
+impl From<DeviceAddr> for DeviceArgs {
+    fn from(addr: DeviceAddr) -> Self {
+        addr.args
+    }
+}
+
+impl fmt::Display for DeviceAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.args, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format() {
+        let args: DeviceArgs = " type = b200 ,serial=30AD2C5,, master_clock_rate=32e6,skip_ddc"
+            .parse()
+            .unwrap();
+        assert_eq!(4, args.len());
+        assert_eq!(Some("b200"), args.device_type());
+        assert_eq!(Some("30AD2C5"), args.serial());
+        assert_eq!(Some("32e6"), args.get("master_clock_rate"));
+        assert_eq!(Some(""), args.get("skip_ddc"));
+        assert_eq!(None, args.addr());
+        assert_eq!(
+            "type=b200,serial=30AD2C5,master_clock_rate=32e6,skip_ddc=",
+            args.to_string()
+        );
+        assert_eq!(args, args.to_string().parse().unwrap());
+
+        assert!("".parse::<DeviceArgs>().unwrap().is_empty());
+        assert!("=b200".parse::<DeviceArgs>().is_err());
+        assert!("type=b200=x".parse::<DeviceArgs>().is_err());
+    }
+
+    #[test]
+    fn split_and_combine() {
+        let args: DeviceArgs = "addr0=192.168.10.2,addr1=192.168.10.3,type=x300,name1=right"
+            .parse()
+            .unwrap();
+        let devices = args.split();
+        assert_eq!(2, devices.len());
+        assert_eq!("addr=192.168.10.2,type=x300", devices[0].to_string());
+        assert_eq!(
+            "addr=192.168.10.3,name=right,type=x300",
+            devices[1].to_string()
+        );
+        assert_eq!(
+            "addr0=192.168.10.2,type0=x300,addr1=192.168.10.3,name1=right,type1=x300",
+            DeviceArgs::combine(&devices).to_string()
+        );
+        assert_eq!(DeviceArgs::combine(&devices).split(), devices);
+
+        let single: DeviceArgs = "type=b200".parse().unwrap();
+        assert_eq!(vec![single.clone()], single.split());
+        assert_eq!(single, DeviceArgs::combine(std::slice::from_ref(&single)));
+    }
+}
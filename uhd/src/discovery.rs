@@ -0,0 +1,377 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::device_args::{DeviceAddr, DeviceArgs};
+use crate::error::Error;
+use crate::usrp::Usrp;
+
+/// Default time between discovery attempts in `DeviceWatcher::next_events`
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Criteria for selecting devices found by `Usrp::find`
+///
+/// Products, serial numbers, and device types are compared without regard to case. Names are
+/// compared exactly. A filter with no criteria matches all devices.
+///
+/// ```no_run
+/// use uhd::DeviceFilter;
+/// # fn main() -> Result<(), uhd::Error> {
+/// let b210s = DeviceFilter::new().product("B210").find()?;
+/// for device in b210s {
+///     println!("B210 with serial {:?}", device.serial());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceFilter {
+    device_type: Option<String>,
+    product: Option<String>,
+    serial: Option<String>,
+    name: Option<String>,
+}
+
+impl DeviceFilter {
+    /// Creates a filter that matches all devices
+    pub fn new() -> Self {
+        DeviceFilter::default()
+    }
+
+    /// Selects devices of a type (for example, `b200` or `x300`)
+    pub fn device_type(self, device_type: &str) -> Self {
+        DeviceFilter {
+            device_type: Some(device_type.to_string()),
+            ..self
+        }
+    }
+
+    /// Selects devices with a product name (for example, `B210` or `X310`)
+    pub fn product(self, product: &str) -> Self {
+        DeviceFilter {
+            product: Some(product.to_string()),
+            ..self
+        }
+    }
+
+    /// Selects the device with a serial number
+    pub fn serial(self, serial: &str) -> Self {
+        DeviceFilter {
+            serial: Some(serial.to_string()),
+            ..self
+        }
+    }
+
+    /// Selects devices with a user-assigned name
+    pub fn name(self, name: &str) -> Self {
+        DeviceFilter {
+            name: Some(name.to_string()),
+            ..self
+        }
+    }
+
+    /// Returns true if a device meets all the criteria of this filter
+    pub fn matches(&self, device: &DeviceAddr) -> bool {
+        fn same(expected: &Option<String>, actual: Option<&str>) -> bool {
+            match expected {
+                Some(expected) => {
+                    matches!(actual, Some(actual) if actual.eq_ignore_ascii_case(expected))
+                }
+                None => true,
+            }
+        }
+        same(&self.device_type, device.device_type())
+            && same(&self.product, device.product())
+            && same(&self.serial, device.serial())
+            && (self.name.is_none() || device.name() == self.name.as_deref())
+    }
+
+    /// Returns the arguments that UHD can use to narrow its search
+    ///
+    /// UHD compares arguments exactly, so only the name is included. Products, serial numbers,
+    /// and device types are only checked by `matches`.
+    pub fn hint(&self) -> DeviceArgs {
+        let mut args = DeviceArgs::new();
+        if let Some(name) = &self.name {
+            args.insert("name", name);
+        }
+        args
+    }
+
+    /// Finds the devices that meet the criteria of this filter
+    pub fn find(&self) -> Result<Vec<DeviceAddr>, Error> {
+        let mut devices = Usrp::find(&self.hint().to_string())?;
+        devices.retain(|device| self.matches(device));
+        Ok(devices)
+    }
+
+    /// Finds the one device that meets the criteria of this filter, or returns None if no
+    /// devices or more than one device match
+    pub fn find_one(&self) -> Result<Option<DeviceAddr>, Error> {
+        let mut devices = self.find()?;
+        if devices.len() == 1 {
+            Ok(devices.pop())
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// A change in the devices that a `DeviceWatcher` can find
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+    /// A device was found that was not present during the previous discovery
+    Added(DeviceAddr),
+    /// A device that was present during the previous discovery was not found
+    Removed(DeviceAddr),
+}
+
+/// A function that finds devices
+type Finder = Box<dyn FnMut() -> Result<Vec<DeviceAddr>, Error> + Send>;
+
+/// Repeatedly finds devices and reports devices that were added or removed
+///
+/// Devices with serial numbers are identified by their type and serial number, so a USB device
+/// that is unplugged and plugged in again is reported as removed and then added. Devices without
+/// serial numbers are identified by all their arguments.
+///
+/// The first discovery reports all devices that are present as added.
+///
+/// ```no_run
+/// use uhd::{DeviceEvent, DeviceFilter, DeviceWatcher};
+/// # fn main() -> Result<(), uhd::Error> {
+/// let mut watcher = DeviceWatcher::new(DeviceFilter::new().serial("30AD2C5"));
+/// loop {
+///     for event in watcher.next_events()? {
+///         match event {
+///             DeviceEvent::Added(device) => println!("Connected: {}", device),
+///             DeviceEvent::Removed(device) => println!("Disconnected: {}", device),
+///         }
+///     }
+/// }
+/// # }
+/// ```
+pub struct DeviceWatcher {
+    finder: Finder,
+    filter: DeviceFilter,
+    interval: Duration,
+    /// The devices found during the last discovery, or None if discovery has not run
+    known: Option<Vec<DeviceAddr>>,
+}
+
+impl DeviceWatcher {
+    /// Creates a watcher that uses `Usrp::find` to find devices that match a filter
+    pub fn new(filter: DeviceFilter) -> Self {
+        let hint = filter.hint().to_string();
+        DeviceWatcher::with_finder(filter, move || Usrp::find(&hint))
+    }
+
+    /// Creates a watcher that uses a custom function to find devices
+    ///
+    /// The devices that the function returns are checked against the filter.
+    pub fn with_finder<F>(filter: DeviceFilter, finder: F) -> Self
+    where
+        F: FnMut() -> Result<Vec<DeviceAddr>, Error> + Send + 'static,
+    {
+        DeviceWatcher {
+            finder: Box::new(finder),
+            filter,
+            interval: DEFAULT_INTERVAL,
+            known: None,
+        }
+    }
+
+    /// Sets the time that `next_events` waits between discovery attempts (default 2 seconds)
+    pub fn interval(self, interval: Duration) -> Self {
+        DeviceWatcher { interval, ..self }
+    }
+
+    /// Returns the devices found during the last discovery
+    pub fn devices(&self) -> &[DeviceAddr] {
+        self.known.as_deref().unwrap_or(&[])
+    }
+
+    /// Finds devices once, and returns the devices that were added or removed since the last
+    /// discovery
+    pub fn poll(&mut self) -> Result<Vec<DeviceEvent>, Error> {
+        let mut found = (self.finder)()?;
+        found.retain(|device| self.filter.matches(device));
+
+        let previous = self.known.take().unwrap_or_default();
+        let mut events: Vec<DeviceEvent> = previous
+            .iter()
+            .filter(|device| !found.iter().any(|other| same_device(device, other)))
+            .cloned()
+            .map(DeviceEvent::Removed)
+            .collect();
+        events.extend(
+            found
+                .iter()
+                .filter(|device| !previous.iter().any(|other| same_device(device, other)))
+                .cloned()
+                .map(DeviceEvent::Added),
+        );
+        self.known = Some(found);
+        Ok(events)
+    }
+
+    /// Finds devices repeatedly, waiting for the interval between attempts, until at least one
+    /// device is added or removed
+    ///
+    /// The first call does not wait before the first attempt.
+    ///
+    /// If an attempt fails, this returns the error immediately. The watcher still remembers the
+    /// devices from the last successful discovery, so calling this function again continues
+    /// watching.
+    ///
+    /// A device that is removed and added again between two attempts is not reported.
+    pub fn next_events(&mut self) -> Result<Vec<DeviceEvent>, Error> {
+        if self.known.is_some() {
+            thread::sleep(self.interval);
+        }
+        loop {
+            let events = self.poll()?;
+            if !events.is_empty() {
+                return Ok(events);
+            }
+            thread::sleep(self.interval);
+        }
+    }
+}
+
+/// Returns true if two addresses refer to the same device
+fn same_device(first: &DeviceAddr, second: &DeviceAddr) -> bool {
+    match (first.serial(), second.serial()) {
+        (Some(first_serial), Some(second_serial)) => {
+            first_serial == second_serial && first.device_type() == second.device_type()
+        }
+        _ => first == second,
+    }
+}
+
+mod fmt {
+    use super::DeviceWatcher;
+    use std::fmt::{Debug, Formatter, Result};
+
+    impl Debug for DeviceWatcher {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            f.debug_struct("DeviceWatcher")
+                .field("filter", &self.filter)
+                .field("interval", &self.interval)
+                .field("known", &self.known)
+                .finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    fn device(text: &str) -> DeviceAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn filter() {
+        let b210 = device("type=b200,name=left,serial=30AD2C5,product=B210");
+        let x310 = device("type=x300,addr=192.168.40.2,name=rack,serial=31A0B1E,product=X310");
+
+        assert!(DeviceFilter::new().matches(&b210));
+        assert!(DeviceFilter::new().product("b210").matches(&b210));
+        assert!(!DeviceFilter::new().product("B210").matches(&x310));
+        assert!(DeviceFilter::new().serial("30ad2c5").matches(&b210));
+        assert!(!DeviceFilter::new().name("Left").matches(&b210));
+        assert!(DeviceFilter::new()
+            .device_type("x300")
+            .name("rack")
+            .matches(&x310));
+        assert!(!DeviceFilter::new()
+            .serial("30AD2C5")
+            .matches(&device("type=b200")));
+
+        assert_eq!(
+            "name=left",
+            DeviceFilter::new()
+                .product("B210")
+                .serial("30AD2C5")
+                .name("left")
+                .hint()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn watcher_events() {
+        let b210 = device("type=b200,serial=30AD2C5,product=B210");
+        let other_b210 = device("type=b200,serial=30AD2C6,product=B210");
+        let x310 = device("type=x300,addr=192.168.40.2,serial=31A0B1E,product=X310");
+        // The same B210 after it is plugged in again, with a different USB address
+        let replugged = device("type=b200,serial=30AD2C5,product=B210,name=");
+
+        let mut results: VecDeque<Vec<DeviceAddr>> = vec![
+            vec![b210.clone(), x310.clone()],
+            vec![b210.clone(), x310.clone()],
+            vec![x310.clone()],
+            vec![replugged.clone(), other_b210.clone(), x310],
+        ]
+        .into_iter()
+        .collect();
+        let mut watcher =
+            DeviceWatcher::with_finder(DeviceFilter::new().product("B210"), move || {
+                Ok(results.pop_front().unwrap_or_default())
+            })
+            .interval(Duration::from_millis(1));
+
+        assert_eq!(
+            vec![DeviceEvent::Added(b210.clone())],
+            watcher.next_events().unwrap()
+        );
+        assert_eq!(std::slice::from_ref(&b210), watcher.devices());
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(vec![DeviceEvent::Removed(b210)], watcher.poll().unwrap());
+        assert!(watcher.devices().is_empty());
+        assert_eq!(
+            vec![
+                DeviceEvent::Added(replugged.clone()),
+                DeviceEvent::Added(other_b210.clone())
+            ],
+            watcher.next_events().unwrap()
+        );
+        assert_eq!(
+            vec![
+                DeviceEvent::Removed(replugged),
+                DeviceEvent::Removed(other_b210)
+            ],
+            watcher.next_events().unwrap()
+        );
+    }
+
+    #[test]
+    fn watcher_error() {
+        let b210 = device("type=b200,serial=30AD2C5,product=B210");
+        let mut results: VecDeque<Result<Vec<DeviceAddr>, Error>> = vec![
+            Err(Error::new(crate::ErrorKind::Io)),
+            Ok(vec![b210.clone()]),
+            Err(Error::new(crate::ErrorKind::Io)),
+            Ok(vec![b210.clone()]),
+        ]
+        .into_iter()
+        .collect();
+        let mut watcher = DeviceWatcher::with_finder(DeviceFilter::new(), move || {
+            results.pop_front().unwrap_or_else(|| Ok(vec![]))
+        })
+        .interval(Duration::from_millis(1));
+
+        assert!(watcher.next_events().is_err());
+        assert!(watcher.devices().is_empty());
+        assert_eq!(
+            vec![DeviceEvent::Added(b210.clone())],
+            watcher.next_events().unwrap()
+        );
+        // A failed discovery does not forget the known devices
+        assert!(watcher.poll().is_err());
+        assert_eq!(std::slice::from_ref(&b210), watcher.devices());
+        assert!(watcher.poll().unwrap().is_empty());
+    }
+}
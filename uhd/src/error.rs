@@ -1,3 +1,4 @@
+use crate::receive_metadata::ReceiveErrorKind;
 use crate::utils::copy_string;
 use std::ffi::NulError;
 use std::str::Utf8Error;
@@ -20,6 +21,13 @@ impl Error {
             message: None,
         }
     }
+    /// Creates an error with the provided kind and message
+    pub(crate) fn with_message(kind: ErrorKind, message: String) -> Self {
+        Error {
+            kind,
+            message: Some(message),
+        }
+    }
     /// Creates an error with the provided kind, and a message from uhd_get_last_error()
     ///
     /// Returns None if code is UHD_ERROR_NONE
@@ -83,6 +91,22 @@ pub enum ErrorKind {
     StringLength,
     /// A string from C FFI contained invalid UTF-8
     Utf8,
+    /// The thread of a receive or transmit pipeline has stopped
+    PipelineStopped,
+    /// The number of buffers or channels provided is not equal to the number of channels in a
+    /// stream
+    ChannelCount,
+    /// Buffers provided for a stream do not have equal lengths, or a buffer cannot be split into
+    /// equal-length channel planes
+    BufferLength,
+    /// A USRP reported an error while receiving samples
+    Receive(ReceiveErrorKind),
+    /// A received sample had a different timestamp than requested
+    Timestamp,
+    /// A capture ended before all requested samples were received
+    IncompleteCapture,
+    /// An operation did not complete before its timeout expired
+    Timeout,
     Unknown,
 }
 
@@ -119,6 +143,12 @@ impl From<NulError> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::with_message(ErrorKind::Io, error.to_string())
+    }
+}
+
 impl From<Utf8Error> for Error {
     fn from(_: Utf8Error) -> Self {
         Error::new(ErrorKind::Utf8)
@@ -162,6 +192,20 @@ mod fmt {
                     ErrorKind::NullByte => write!(f, "Null byte in input string"),
                     ErrorKind::StringLength => write!(f, "String from FFI is too long"),
                     ErrorKind::Utf8 => write!(f, "String from FFI contains invalid UTF-8"),
+                    ErrorKind::PipelineStopped => write!(f, "Pipeline thread has stopped"),
+                    ErrorKind::ChannelCount => {
+                        write!(
+                            f,
+                            "Number of buffers is not equal to the number of channels"
+                        )
+                    }
+                    ErrorKind::BufferLength => write!(f, "Buffer lengths are not equal"),
+                    ErrorKind::Receive(kind) => write!(f, "Receive error: {:?}", kind),
+                    ErrorKind::Timestamp => write!(f, "Unexpected sample timestamp"),
+                    ErrorKind::IncompleteCapture => {
+                        write!(f, "Capture ended before all samples were received")
+                    }
+                    ErrorKind::Timeout => write!(f, "Operation timed out"),
                     ErrorKind::Unknown => write!(f, "Unknown error"),
                 }
             }
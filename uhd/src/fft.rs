@@ -0,0 +1,87 @@
+use std::f64::consts::PI;
+
+use num_complex::Complex64;
+
+/// Replaces the contents of a buffer with its discrete Fourier transform
+///
+/// This uses an iterative radix-2 algorithm. The result is not scaled.
+///
+/// # Panics
+///
+/// This function panics if the buffer length is not a power of two.
+pub(crate) fn fft(buffer: &mut [Complex64]) {
+    let length = buffer.len();
+    assert!(
+        length.is_power_of_two(),
+        "FFT length {} is not a power of two",
+        length
+    );
+    if length == 1 {
+        return;
+    }
+
+    // Put the samples in bit-reversed order
+    let shift = usize::BITS - length.trailing_zeros();
+    for i in 0..length {
+        let j = i.reverse_bits() >> shift;
+        if j > i {
+            buffer.swap(i, j);
+        }
+    }
+
+    // Combine pairs of smaller transforms
+    let mut size = 2;
+    while size <= length {
+        let half = size / 2;
+        for chunk in buffer.chunks_mut(size) {
+            for k in 0..half {
+                let twiddle = Complex64::from_polar(1.0, -2.0 * PI * k as f64 / size as f64);
+                let even = chunk[k];
+                let odd = chunk[k + half] * twiddle;
+                chunk[k] = even + odd;
+                chunk[k + half] = even - odd;
+            }
+        }
+        size *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dft(input: &[Complex64]) -> Vec<Complex64> {
+        let length = input.len();
+        (0..length)
+            .map(|k| {
+                input
+                    .iter()
+                    .enumerate()
+                    .map(|(n, &x)| {
+                        x * Complex64::from_polar(1.0, -2.0 * PI * (k * n) as f64 / length as f64)
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_dft() {
+        for &length in &[1, 2, 8, 64] {
+            let input: Vec<Complex64> = (0..length)
+                .map(|i| Complex64::new((i as f64 * 0.7).sin(), (i as f64 * 1.3).cos() - 0.2))
+                .collect();
+            let mut output = input.clone();
+            fft(&mut output);
+            for (actual, expected) in output.iter().zip(dft(&input)) {
+                assert!((actual - expected).norm() < 1e-9, "length {}", length);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn not_power_of_two() {
+        fft(&mut [Complex64::default(); 12]);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::error::{Error, ErrorKind};
+use crate::tune_request::TuneRequest;
+use crate::tune_result::TuneResult;
+use crate::usrp::Usrp;
+use crate::TimeSpec;
+
+/// One retune point in a `HopSchedule`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hop {
+    /// The device time when the new frequency (and gain) takes effect
+    pub time: TimeSpec,
+    /// The center frequency, in hertz
+    pub frequency: f64,
+    /// The overall gain, in decibels, or None to leave the gain unchanged
+    pub gain: Option<f64>,
+}
+
+impl Hop {
+    /// Creates a hop that changes the frequency at a device time
+    pub fn new(time: TimeSpec, frequency: f64) -> Self {
+        Hop {
+            time,
+            frequency,
+            gain: None,
+        }
+    }
+
+    /// Sets the gain that this hop applies
+    pub fn gain(self, gain: f64) -> Self {
+        Hop {
+            gain: Some(gain),
+            ..self
+        }
+    }
+}
+
+/// The result of applying one hop
+#[derive(Debug, Clone)]
+pub struct HopResult {
+    /// The device time of the hop
+    pub time: TimeSpec,
+    /// The tune results for the receive channels, in the order of the schedule's channels
+    pub rx: Vec<TuneResult>,
+    /// The tune results for the transmit channels, in the order of the schedule's channels
+    pub tx: Vec<TuneResult>,
+    /// True if the device time had already passed the hop time when the commands were sent
+    ///
+    /// Late commands take effect as soon as the USRP receives them, not at the hop time.
+    pub late: bool,
+}
+
+/// A list of frequency (and optional gain) changes that take effect at specific device times
+///
+/// Each hop is applied with timed commands: the command time is set to the hop time, the
+/// frequency and gain of every configured channel are changed, and the command time is cleared.
+/// The USRP executes the commands when its clock reaches the hop time, so retune points do not
+/// depend on when the host sends them.
+///
+/// The USRP can only queue a limited number of timed commands. If the queue is full, `apply`
+/// blocks until earlier commands have executed, so a long schedule may take until shortly before
+/// its last hop to apply.
+///
+/// Example:
+///
+/// ```no_run
+/// use uhd::{Hop, HopSchedule, TimeSpec, Usrp};
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let usrp = Usrp::open("")?;
+/// let start = usrp.get_current_time(0)? + TimeSpec::from_secs_f64(0.5);
+/// let hops = (0..10)
+///     .map(|i| {
+///         let time = start + TimeSpec::from_secs_f64(0.01 * i as f64);
+///         Hop::new(time, 2.4e9 + 5e6 * i as f64)
+///     })
+///     .collect();
+/// let results = HopSchedule::new(hops)
+///     .rx_channels(vec![0])
+///     .tx_channels(vec![0])
+///     .apply(&usrp)?;
+/// for result in results.iter().filter(|result| result.late) {
+///     println!("Hop at {} was late", result.time);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HopSchedule {
+    /// The hops, in time order
+    hops: Vec<Hop>,
+    /// Receive channels to retune
+    rx_channels: Vec<usize>,
+    /// Transmit channels to retune
+    tx_channels: Vec<usize>,
+    /// The motherboard whose clock the hop times refer to
+    mboard: usize,
+}
+
+impl HopSchedule {
+    /// Creates a schedule with the provided hops, which must be in time order
+    ///
+    /// By default, the schedule does not retune any channels.
+    pub fn new(hops: Vec<Hop>) -> Self {
+        HopSchedule {
+            hops,
+            rx_channels: Vec::new(),
+            tx_channels: Vec::new(),
+            mboard: 0,
+        }
+    }
+
+    /// Sets the receive channels that the hops retune
+    pub fn rx_channels(self, rx_channels: Vec<usize>) -> Self {
+        HopSchedule {
+            rx_channels,
+            ..self
+        }
+    }
+
+    /// Sets the transmit channels that the hops retune
+    pub fn tx_channels(self, tx_channels: Vec<usize>) -> Self {
+        HopSchedule {
+            tx_channels,
+            ..self
+        }
+    }
+
+    /// Sets the motherboard that the timed commands are sent to (default 0)
+    pub fn mboard(self, mboard: usize) -> Self {
+        HopSchedule { mboard, ..self }
+    }
+
+    /// Returns the hops in this schedule
+    pub fn hops(&self) -> &[Hop] {
+        &self.hops
+    }
+
+    /// Checks that the hops are in time order and that every frequency is in the supported
+    /// range of every configured channel
+    ///
+    /// This returns an error of kind `ErrorKind::Value` if the schedule is not valid.
+    pub fn validate(&self, usrp: &Usrp) -> Result<(), Error> {
+        check_order(&self.hops)?;
+        for &channel in &self.rx_channels {
+            let range = usrp.get_rx_frequency_range(channel)?;
+            let (start, stop) = (range.start()?, range.stop()?);
+            for hop in &self.hops {
+                check_frequency("receive", channel, hop.frequency, start, stop)?;
+            }
+        }
+        for &channel in &self.tx_channels {
+            let range = usrp.get_tx_frequency_range(channel)?;
+            let (start, stop) = (range.start()?, range.stop()?);
+            for hop in &self.hops {
+                check_frequency("transmit", channel, hop.frequency, start, stop)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates this schedule and sends the timed commands for every hop
+    ///
+    /// This returns one result for each hop, in order.
+    pub fn apply(&self, usrp: &Usrp) -> Result<Vec<HopResult>, Error> {
+        self.validate(usrp)?;
+        let mut results = Vec::with_capacity(self.hops.len());
+        for hop in &self.hops {
+            let late = hop.time <= usrp.get_current_time(self.mboard)?;
+            usrp.set_command_time(&hop.time, self.mboard)?;
+            let retuned = self.retune(usrp, hop);
+            // Clear the command time even if retuning failed, so that later commands are not timed
+            let cleared = usrp.clear_command_time(self.mboard);
+            let (rx, tx) = retuned?;
+            cleared?;
+            results.push(HopResult {
+                time: hop.time,
+                rx,
+                tx,
+                late,
+            });
+        }
+        Ok(results)
+    }
+
+    /// Sets the frequency and gain of all configured channels
+    fn retune(&self, usrp: &Usrp, hop: &Hop) -> Result<(Vec<TuneResult>, Vec<TuneResult>), Error> {
+        let request = TuneRequest::with_frequency(hop.frequency);
+        let mut rx = Vec::with_capacity(self.rx_channels.len());
+        for &channel in &self.rx_channels {
+            rx.push(usrp.set_rx_frequency(&request, channel)?);
+            if let Some(gain) = hop.gain {
+                usrp.set_rx_gain(gain, channel, "")?;
+            }
+        }
+        let mut tx = Vec::with_capacity(self.tx_channels.len());
+        for &channel in &self.tx_channels {
+            tx.push(usrp.set_tx_frequency(&request, channel)?);
+            if let Some(gain) = hop.gain {
+                usrp.set_tx_gain(gain, channel, "")?;
+            }
+        }
+        Ok((rx, tx))
+    }
+}
+
+/// Checks that hop times do not decrease
+fn check_order(hops: &[Hop]) -> Result<(), Error> {
+    for (index, pair) in hops.windows(2).enumerate() {
+        if pair[1].time < pair[0].time {
+            return Err(Error::with_message(
+                ErrorKind::Value,
+                format!(
+                    "Hop {} at {} is before hop {} at {}",
+                    index + 1,
+                    pair[1].time,
+                    index,
+                    pair[0].time
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a frequency is in the range [start, stop]
+fn check_frequency(
+    direction: &str,
+    channel: usize,
+    frequency: f64,
+    start: f64,
+    stop: f64,
+) -> Result<(), Error> {
+    if frequency >= start && frequency <= stop {
+        Ok(())
+    } else {
+        Err(Error::with_message(
+            ErrorKind::Value,
+            format!(
+                "Frequency {} Hz is outside the {} range of channel {} ({} to {} Hz)",
+                frequency, direction, channel, start, stop
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order() {
+        let hops = vec![
+            Hop::new(TimeSpec::new(1, 0.0), 1e9),
+            Hop::new(TimeSpec::new(1, 0.0), 2e9).gain(10.0),
+            Hop::new(TimeSpec::new(1, 0.5), 1e9),
+        ];
+        assert!(check_order(&hops).is_ok());
+
+        let hops = vec![
+            Hop::new(TimeSpec::new(2, 0.0), 1e9),
+            Hop::new(TimeSpec::new(1, 0.5), 1e9),
+        ];
+        assert_eq!(ErrorKind::Value, check_order(&hops).unwrap_err().kind());
+    }
+
+    #[test]
+    fn frequency_range() {
+        assert!(check_frequency("receive", 0, 70e6, 70e6, 6e9).is_ok());
+        assert!(check_frequency("receive", 0, 6e9, 70e6, 6e9).is_ok());
+        let error = check_frequency("transmit", 1, 6.1e9, 70e6, 6e9).unwrap_err();
+        assert_eq!(ErrorKind::Value, error.kind());
+    }
+}
@@ -0,0 +1,420 @@
+//!
+//! Reading and writing samples in files
+//!
+//! The `Sample` trait defines the little-endian byte encoding used for each host sample type.
+//! Interleaved (`[T; 2]`) samples use the same encoding as the matching complex samples.
+//!
+//! The `raw` module reads and writes headerless sample files like the ones used by the UHD
+//! `rx_samples_to_file` and `tx_samples_from_file` examples.
+//!
+//! With the `sigmf` feature enabled, the `sigmf` module records receive streams in the
+//! [SigMF](https://sigmf.org) format and plays recordings through transmit streams.
+//!
+
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+
+use num_complex::{Complex, Complex64};
+
+use crate::stream::Item;
+
+pub mod raw;
+#[cfg(feature = "sigmf")]
+pub mod sigmf;
+
+/// A host sample type that can be stored in a file as little-endian bytes
+pub trait Sample: Item + Copy + Default {
+    /// The number of bytes in one encoded sample
+    const SIZE: usize;
+    /// The SigMF name of this type (for example, `cf32_le`)
+    const DATATYPE: &'static str;
+
+    /// Writes this sample into bytes, which has length `Self::SIZE`
+    fn encode(&self, bytes: &mut [u8]);
+    /// Reads a sample from bytes, which has length `Self::SIZE`
+    fn decode(bytes: &[u8]) -> Self;
+
+    /// Converts this sample into a complex number where full scale is 1.0
+    ///
+    /// Integer samples are divided by 2^(bits - 1), the same scaling that UHD uses to convert
+    /// between integer and floating-point formats. Real samples have an imaginary part of 0.
+    fn to_normalized(&self) -> Complex64;
+    /// Converts a complex number where full scale is 1.0 into a sample
+    ///
+    /// Integer values are rounded and saturated. For real sample types, the imaginary part is
+    /// discarded.
+    fn from_normalized(value: Complex64) -> Self;
+}
+
+/// A number that makes up a sample
+trait Component: Copy {
+    /// Converts this number into a value where full scale is 1.0
+    fn to_normalized(self) -> f64;
+    /// Converts a value where full scale is 1.0 into a number
+    fn from_normalized(value: f64) -> Self;
+}
+
+impl Component for f64 {
+    fn to_normalized(self) -> f64 {
+        self
+    }
+    fn from_normalized(value: f64) -> Self {
+        value
+    }
+}
+
+impl Component for f32 {
+    fn to_normalized(self) -> f64 {
+        f64::from(self)
+    }
+    fn from_normalized(value: f64) -> Self {
+        value as f32
+    }
+}
+
+macro_rules! integer_component {
+    ($type:ty) => {
+        impl Component for $type {
+            fn to_normalized(self) -> f64 {
+                f64::from(self) / -f64::from(<$type>::MIN)
+            }
+            fn from_normalized(value: f64) -> Self {
+                // Float to integer casts saturate
+                (value * -f64::from(<$type>::MIN)).round() as $type
+            }
+        }
+    };
+}
+
+integer_component!(i16);
+integer_component!(i8);
+
+macro_rules! real_sample {
+    ($type:ty, $datatype:expr) => {
+        impl Sample for $type {
+            const SIZE: usize = std::mem::size_of::<$type>();
+            const DATATYPE: &'static str = $datatype;
+
+            fn encode(&self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes());
+            }
+            fn decode(bytes: &[u8]) -> Self {
+                <$type>::from_le_bytes(bytes.try_into().expect("Incorrect sample length"))
+            }
+            fn to_normalized(&self) -> Complex64 {
+                Complex64::new(Component::to_normalized(*self), 0.0)
+            }
+            fn from_normalized(value: Complex64) -> Self {
+                Component::from_normalized(value.re)
+            }
+        }
+    };
+}
+
+macro_rules! complex_sample {
+    ($type:ty, $datatype:expr) => {
+        impl Sample for Complex<$type> {
+            const SIZE: usize = 2 * std::mem::size_of::<$type>();
+            const DATATYPE: &'static str = $datatype;
+
+            fn encode(&self, bytes: &mut [u8]) {
+                let (re, im) = bytes.split_at_mut(Self::SIZE / 2);
+                re.copy_from_slice(&self.re.to_le_bytes());
+                im.copy_from_slice(&self.im.to_le_bytes());
+            }
+            fn decode(bytes: &[u8]) -> Self {
+                let (re, im) = bytes.split_at(Self::SIZE / 2);
+                Complex::new(
+                    <$type>::from_le_bytes(re.try_into().expect("Incorrect sample length")),
+                    <$type>::from_le_bytes(im.try_into().expect("Incorrect sample length")),
+                )
+            }
+            fn to_normalized(&self) -> Complex64 {
+                Complex64::new(self.re.to_normalized(), self.im.to_normalized())
+            }
+            fn from_normalized(value: Complex64) -> Self {
+                Complex::new(
+                    Component::from_normalized(value.re),
+                    Component::from_normalized(value.im),
+                )
+            }
+        }
+    };
+}
+
+macro_rules! interleaved_sample {
+    ($type:ty, $datatype:expr) => {
+        impl Sample for [$type; 2] {
+            const SIZE: usize = 2 * std::mem::size_of::<$type>();
+            const DATATYPE: &'static str = $datatype;
+
+            fn encode(&self, bytes: &mut [u8]) {
+                Complex::new(self[0], self[1]).encode(bytes);
+            }
+            fn decode(bytes: &[u8]) -> Self {
+                let value = Complex::<$type>::decode(bytes);
+                [value.re, value.im]
+            }
+            fn to_normalized(&self) -> Complex64 {
+                Complex::new(self[0], self[1]).to_normalized()
+            }
+            fn from_normalized(value: Complex64) -> Self {
+                let value = Complex::<$type>::from_normalized(value);
+                [value.re, value.im]
+            }
+        }
+    };
+}
+
+real_sample!(f32, "rf32_le");
+real_sample!(i16, "ri16_le");
+real_sample!(i8, "ri8");
+complex_sample!(f64, "cf64_le");
+complex_sample!(f32, "cf32_le");
+complex_sample!(i16, "ci16_le");
+complex_sample!(i8, "ci8");
+interleaved_sample!(f64, "cf64_le");
+interleaved_sample!(f32, "cf32_le");
+interleaved_sample!(i16, "ci16_le");
+interleaved_sample!(i8, "ci8");
+
+/// A sample format identified at runtime, for reading files whose format may not match the host
+/// sample type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Datatype {
+    Cf64,
+    Cf32,
+    Ci16,
+    Ci8,
+    Rf32,
+    Ri16,
+    Ri8,
+}
+
+impl Datatype {
+    /// Returns the datatype with the provided SigMF name (for example, `cf32_le`), if it is
+    /// supported
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_DATATYPES
+            .iter()
+            .copied()
+            .find(|datatype| datatype.name() == name)
+    }
+
+    /// Returns the datatype of a host sample type
+    pub fn of<S: Sample>() -> Self {
+        Datatype::from_name(S::DATATYPE).expect("Sample type has no matching Datatype")
+    }
+
+    /// Returns the SigMF name of this datatype
+    pub fn name(&self) -> &'static str {
+        match self {
+            Datatype::Cf64 => <Complex<f64> as Sample>::DATATYPE,
+            Datatype::Cf32 => <Complex<f32> as Sample>::DATATYPE,
+            Datatype::Ci16 => <Complex<i16> as Sample>::DATATYPE,
+            Datatype::Ci8 => <Complex<i8> as Sample>::DATATYPE,
+            Datatype::Rf32 => <f32 as Sample>::DATATYPE,
+            Datatype::Ri16 => <i16 as Sample>::DATATYPE,
+            Datatype::Ri8 => <i8 as Sample>::DATATYPE,
+        }
+    }
+
+    /// Returns the number of bytes in one sample
+    pub fn size(&self) -> usize {
+        match self {
+            Datatype::Cf64 => <Complex<f64> as Sample>::SIZE,
+            Datatype::Cf32 => <Complex<f32> as Sample>::SIZE,
+            Datatype::Ci16 => <Complex<i16> as Sample>::SIZE,
+            Datatype::Ci8 => <Complex<i8> as Sample>::SIZE,
+            Datatype::Rf32 => <f32 as Sample>::SIZE,
+            Datatype::Ri16 => <i16 as Sample>::SIZE,
+            Datatype::Ri8 => <i8 as Sample>::SIZE,
+        }
+    }
+
+    /// Returns true if samples of this datatype have real and imaginary parts
+    pub fn is_complex(&self) -> bool {
+        match self {
+            Datatype::Cf64 | Datatype::Cf32 | Datatype::Ci16 | Datatype::Ci8 => true,
+            Datatype::Rf32 | Datatype::Ri16 | Datatype::Ri8 => false,
+        }
+    }
+
+    /// Decodes a sample of this datatype and converts it into the host sample type S
+    ///
+    /// If this datatype is the datatype of S, the sample is decoded without conversion.
+    /// Otherwise, it is converted through a normalized complex value (see
+    /// `Sample::to_normalized`).
+    pub fn decode<S: Sample>(&self, bytes: &[u8]) -> S {
+        if self.name() == S::DATATYPE {
+            return S::decode(bytes);
+        }
+        let normalized = match self {
+            Datatype::Cf64 => decode_normalized::<Complex<f64>>(bytes),
+            Datatype::Cf32 => decode_normalized::<Complex<f32>>(bytes),
+            Datatype::Ci16 => decode_normalized::<Complex<i16>>(bytes),
+            Datatype::Ci8 => decode_normalized::<Complex<i8>>(bytes),
+            Datatype::Rf32 => decode_normalized::<f32>(bytes),
+            Datatype::Ri16 => decode_normalized::<i16>(bytes),
+            Datatype::Ri8 => decode_normalized::<i8>(bytes),
+        };
+        S::from_normalized(normalized)
+    }
+}
+
+/// Decodes a sample of type S and returns its normalized value
+fn decode_normalized<S: Sample>(bytes: &[u8]) -> Complex64 {
+    S::decode(bytes).to_normalized()
+}
+
+/// All supported datatypes
+const ALL_DATATYPES: [Datatype; 7] = [
+    Datatype::Cf64,
+    Datatype::Cf32,
+    Datatype::Ci16,
+    Datatype::Ci8,
+    Datatype::Rf32,
+    Datatype::Ri16,
+    Datatype::Ri8,
+];
+
+/// Writes samples from one or more channels, interleaved so that sample i of every channel is
+/// written before sample i + 1 of any channel
+///
+/// Only the first `samples` samples of each channel are written.
+///
+/// # Panics
+///
+/// This function panics if any channel has fewer than `samples` samples.
+pub fn write_interleaved<S, B, W>(writer: &mut W, channels: &[B], samples: usize) -> io::Result<()>
+where
+    S: Sample,
+    B: AsRef<[S]>,
+    W: Write,
+{
+    let mut bytes = vec![0u8; S::SIZE * channels.len() * samples];
+    let mut chunks = bytes.chunks_exact_mut(S::SIZE);
+    for i in 0..samples {
+        for channel in channels {
+            channel.as_ref()[i].encode(chunks.next().expect("Byte buffer too short"));
+        }
+    }
+    writer.write_all(&bytes)
+}
+
+/// Reads interleaved samples into one or more channel buffers, overwriting their contents
+///
+/// This reads until every buffer is full or the reader reaches its end, and returns the number
+/// of samples read into each buffer. All buffers must have the same length.
+///
+/// If the reader ends partway through a group of samples (one for each channel), the incomplete
+/// group is discarded.
+pub fn read_interleaved<S, R>(reader: &mut R, channels: &mut [&mut [S]]) -> io::Result<usize>
+where
+    S: Sample,
+    R: Read,
+{
+    read_interleaved_as(reader, Datatype::of::<S>(), channels)
+}
+
+/// Reads interleaved samples stored in the provided datatype, converts them into the host
+/// sample type S, and writes them into one or more channel buffers
+///
+/// This function behaves like `read_interleaved`, except that the file may contain a different
+/// datatype. See `Datatype::decode` for details about the conversion.
+pub fn read_interleaved_as<S, R>(
+    reader: &mut R,
+    datatype: Datatype,
+    channels: &mut [&mut [S]],
+) -> io::Result<usize>
+where
+    S: Sample,
+    R: Read,
+{
+    let capacity = channels.first().map(|channel| channel.len()).unwrap_or(0);
+    let size = datatype.size();
+    let group_size = size * channels.len();
+    let mut bytes = vec![0u8; group_size * capacity];
+    let mut length = 0;
+    while length < bytes.len() {
+        match reader.read(&mut bytes[length..]) {
+            Ok(0) => break,
+            Ok(count) => length += count,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let samples = length.checked_div(group_size).unwrap_or(0);
+    for (i, group) in bytes[..samples * group_size]
+        .chunks_exact(group_size)
+        .enumerate()
+    {
+        for (channel, sample_bytes) in channels.iter_mut().zip(group.chunks_exact(size)) {
+            channel[i] = datatype.decode(sample_bytes);
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_little_endian() {
+        let mut bytes = [0u8; 4];
+        Complex::<i16>::new(0x0102, -2).encode(&mut bytes);
+        assert_eq!([0x02, 0x01, 0xfe, 0xff], bytes);
+        assert_eq!(Complex::new(0x0102, -2), Complex::<i16>::decode(&bytes));
+        assert_eq!("ci8", <Complex<i8> as Sample>::DATATYPE);
+        assert_eq!(8, <Complex<f32> as Sample>::SIZE);
+    }
+
+    #[test]
+    fn interleaved_round_trip() {
+        let a = vec![
+            Complex::new(1.0f32, 2.0),
+            Complex::new(3.0, 4.0),
+            Complex::new(9.0, 9.0),
+        ];
+        let b = vec![
+            Complex::new(-1.0f32, -2.0),
+            Complex::new(-3.0, -4.0),
+            Complex::new(9.0, 9.0),
+        ];
+        let mut file = Vec::new();
+        write_interleaved(&mut file, &[&a, &b], 2).unwrap();
+        assert_eq!(2 * 2 * 8, file.len());
+        // Add part of another group, which is ignored
+        file.extend_from_slice(&[0; 5]);
+
+        let mut read_a = [Complex::default(); 4];
+        let mut read_b = [Complex::default(); 4];
+        let samples =
+            read_interleaved(&mut &file[..], &mut [&mut read_a[..], &mut read_b[..]]).unwrap();
+        assert_eq!(2, samples);
+        assert_eq!(a[..2], read_a[..2]);
+        assert_eq!(b[..2], read_b[..2]);
+    }
+
+    #[test]
+    fn convert() {
+        assert_eq!(Some(Datatype::Ci16), Datatype::from_name("ci16_le"));
+        assert_eq!(None, Datatype::from_name("cu8"));
+
+        let mut bytes = [0u8; 2];
+        Complex::<i8>::new(-128, 64).encode(&mut bytes);
+        let converted: Complex<f32> = Datatype::Ci8.decode(&bytes);
+        assert_eq!(Complex::new(-1.0, 0.5), converted);
+        let converted: Complex<i16> = Datatype::Ci8.decode(&bytes);
+        assert_eq!(Complex::new(-32768, 16384), converted);
+        let converted: i16 = Datatype::Ci8.decode(&bytes);
+        assert_eq!(-32768, converted);
+
+        // Out-of-range values saturate
+        assert_eq!(
+            Complex::new(i8::MAX, i8::MIN),
+            Complex::<i8>::from_normalized(Complex64::new(1.5, -2.0))
+        );
+    }
+}
@@ -0,0 +1,720 @@
+//!
+//! Raw sample files
+//!
+//! A raw file contains only samples, encoded as little-endian numbers with no header. This is
+//! the format that the UHD `rx_samples_to_file` example writes and `tx_samples_from_file` reads.
+//! Its `short`, `float`, and `double` types correspond to `Complex<i16>`, `Complex<f32>`, and
+//! `Complex<f64>`.
+//!
+//! Samples from several channels can be stored in one file, interleaved so that sample i of
+//! every channel comes before sample i + 1 of any channel, or in a separate file for each
+//! channel. Per-channel files are named like the ones from `rx_samples_to_file`: channel 1 of
+//! `samples.dat` is stored in `samples.01.dat`.
+//!
+//! Because raw files have no metadata, `RawWriter` also writes a small text header next to the
+//! samples (see `header_path`) with the datatype, channel layout, sample rate, center frequency,
+//! and time of the first sample. `RawReader` can also read files without a header, if the
+//! format is provided.
+//!
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, ErrorKind};
+use crate::io::{read_interleaved_as, write_interleaved, Datatype, Sample};
+use crate::receive_metadata::{ReceiveMetadata, ReceiveMetadataSnapshot};
+use crate::usrp::Usrp;
+use crate::utils::check_buffers;
+use crate::TimeSpec;
+
+/// Extension appended to a sample file path to get the path of its header
+pub const HEADER_EXTENSION: &str = "hdr";
+
+/// Returns the path of the header for a sample file (for example, `samples.dat.hdr` for
+/// `samples.dat`)
+pub fn header_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut path = OsString::from(path.as_ref());
+    path.push(".");
+    path.push(HEADER_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Returns the path of the file for one channel when each channel has its own file
+///
+/// Like `rx_samples_to_file`, this inserts the two-digit channel number before the extension
+/// (for example, `samples.01.dat` for channel 1 of `samples.dat`).
+pub fn channel_path<P: AsRef<Path>>(path: P, channel: usize) -> PathBuf {
+    let path = path.as_ref();
+    let mut extension = OsString::from(format!("{:02}", channel));
+    if let Some(original) = path.extension() {
+        extension.push(".");
+        extension.push(original);
+    }
+    path.with_extension(extension)
+}
+
+/// Describes the samples in a raw file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    /// Format of the samples
+    pub datatype: Datatype,
+    /// Number of channels
+    pub num_channels: usize,
+    /// True if each channel is stored in its own file, false if all channels are interleaved
+    /// in one file
+    pub per_channel_files: bool,
+    /// Sample rate, in samples per second
+    pub sample_rate: Option<f64>,
+    /// Center frequency, in hertz
+    pub frequency: Option<f64>,
+    /// Device time of the first sample
+    pub start_time: Option<TimeSpec>,
+    /// Number of samples (per channel) in the file or files
+    pub num_samples: Option<u64>,
+}
+
+impl Header {
+    /// Creates a header for samples of type S with interleaved channels and no other
+    /// information
+    pub fn new<S: Sample>(num_channels: usize) -> Self {
+        Header {
+            datatype: Datatype::of::<S>(),
+            num_channels,
+            per_channel_files: false,
+            sample_rate: None,
+            frequency: None,
+            start_time: None,
+            num_samples: None,
+        }
+    }
+
+    /// Creates a header for a file written by the UHD examples
+    ///
+    /// type_name: The value of the `--type` option (`short`, `float`, or `double`)
+    ///
+    /// This returns an error of kind `ErrorKind::Value` if the type is not one of those.
+    pub fn from_example_type(type_name: &str, num_channels: usize) -> Result<Self, Error> {
+        let datatype = match type_name {
+            "short" => Datatype::Ci16,
+            "float" => Datatype::Cf32,
+            "double" => Datatype::Cf64,
+            _ => {
+                return Err(Error::with_message(
+                    ErrorKind::Value,
+                    format!("Unknown sample type {}", type_name),
+                ))
+            }
+        };
+        Ok(Header {
+            datatype,
+            num_channels,
+            per_channel_files: false,
+            sample_rate: None,
+            frequency: None,
+            start_time: None,
+            num_samples: None,
+        })
+    }
+
+    /// Returns the paths of the sample files
+    ///
+    /// path: The path of the recording. This is the only file unless channels are stored in
+    /// separate files.
+    pub fn data_paths<P: AsRef<Path>>(&self, path: P) -> Vec<PathBuf> {
+        if self.per_channel_files && self.num_channels > 1 {
+            (0..self.num_channels)
+                .map(|channel| channel_path(&path, channel))
+                .collect()
+        } else {
+            vec![path.as_ref().to_path_buf()]
+        }
+    }
+
+    /// Reads a header file
+    ///
+    /// This returns an error of kind `ErrorKind::Value` if the file is not a valid header.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Writes this header into a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+mod fmt {
+    use super::Header;
+    use std::fmt::{Display, Formatter, Result};
+
+    impl Display for Header {
+        /// Formats this header as text, with one `key = value` line for each field that has a
+        /// value
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            writeln!(f, "datatype = {}", self.datatype.name())?;
+            writeln!(f, "channels = {}", self.num_channels)?;
+            writeln!(f, "per_channel_files = {}", self.per_channel_files)?;
+            if let Some(sample_rate) = self.sample_rate {
+                writeln!(f, "sample_rate = {}", sample_rate)?;
+            }
+            if let Some(frequency) = self.frequency {
+                writeln!(f, "frequency = {}", frequency)?;
+            }
+            if let Some(start_time) = self.start_time {
+                // Whole and fractional seconds are stored separately to keep full precision
+                writeln!(
+                    f,
+                    "start_time = {} {}",
+                    start_time.seconds, start_time.fraction
+                )?;
+            }
+            if let Some(num_samples) = self.num_samples {
+                writeln!(f, "samples = {}", num_samples)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+impl std::str::FromStr for Header {
+    type Err = Error;
+
+    /// Parses a header in the format produced by `Display`
+    ///
+    /// Empty lines, lines that start with `#`, and unknown keys are ignored.
+    fn from_str(text: &str) -> Result<Self, Error> {
+        let mut datatype = None;
+        let mut num_channels = None;
+        let mut per_channel_files = false;
+        let mut sample_rate = None;
+        let mut frequency = None;
+        let mut start_time = None;
+        let mut num_samples = None;
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| header_error(line))?;
+            match key {
+                "datatype" => {
+                    datatype = Some(Datatype::from_name(value).ok_or_else(|| header_error(line))?)
+                }
+                "channels" => num_channels = Some(parse_value(line, value)?),
+                "per_channel_files" => per_channel_files = parse_value(line, value)?,
+                "sample_rate" => sample_rate = Some(parse_value(line, value)?),
+                "frequency" => frequency = Some(parse_value(line, value)?),
+                "start_time" => {
+                    let mut parts = value.split_whitespace();
+                    let seconds = parse_value(line, parts.next().unwrap_or(""))?;
+                    let fraction = parse_value(line, parts.next().unwrap_or("0"))?;
+                    start_time = Some(TimeSpec::new(seconds, fraction));
+                }
+                "samples" => num_samples = Some(parse_value(line, value)?),
+                _ => {}
+            }
+        }
+        match (datatype, num_channels) {
+            (Some(datatype), Some(num_channels)) => Ok(Header {
+                datatype,
+                num_channels,
+                per_channel_files,
+                sample_rate,
+                frequency,
+                start_time,
+                num_samples,
+            }),
+            _ => Err(Error::with_message(
+                ErrorKind::Value,
+                "Raw file header has no datatype or channel count".to_string(),
+            )),
+        }
+    }
+}
+
+/// Parses a header value, returning an error that includes the line if it is not valid
+fn parse_value<T: std::str::FromStr>(line: &str, value: &str) -> Result<T, Error> {
+    value.parse().map_err(|_| header_error(line))
+}
+
+/// Returns an error that describes an invalid header line
+fn header_error(line: &str) -> Error {
+    Error::with_message(
+        ErrorKind::Value,
+        format!("Invalid raw file header line {:?}", line),
+    )
+}
+
+/// Writes samples into raw files, with a header
+///
+/// The writer can stop writing after a maximum number of bytes or a maximum duration. When a
+/// limit is reached, the remaining samples are discarded and `is_full` returns true.
+///
+/// The header is written when `finish` is called.
+///
+/// Example:
+///
+/// ```no_run
+/// use uhd::io::raw::RawWriter;
+/// use uhd::{StreamArgs, StreamCommand, StreamCommandType, StreamTime, Usrp};
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let usrp = Usrp::open("")?;
+/// let mut streamer = usrp.get_rx_stream(&StreamArgs::<[i16; 2]>::builder().build())?;
+/// let mut writer = RawWriter::<[i16; 2]>::create("samples.dat", &usrp, &[0])?
+///     .max_duration(10.0);
+/// streamer.send_command(&StreamCommand {
+///     command_type: StreamCommandType::StartContinuous,
+///     time: StreamTime::Now,
+/// })?;
+/// let mut buffer = vec![[0i16; 2]; 4096];
+/// while !writer.is_full() {
+///     let metadata = streamer.receive_simple(&mut buffer)?;
+///     writer.write(&[&buffer], &metadata)?;
+/// }
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RawWriter<S> {
+    /// The sample files (one for each channel, or one for all channels)
+    files: Vec<BufWriter<File>>,
+    /// Path of the header file
+    header_path: PathBuf,
+    /// Header written by finish()
+    header: Header,
+    /// Maximum number of bytes in all files
+    max_bytes: Option<u64>,
+    /// Maximum duration, in seconds
+    max_duration: Option<f64>,
+    /// Number of samples (per channel) written
+    samples_written: u64,
+    sample_phantom: PhantomData<S>,
+}
+
+impl<S: Sample> RawWriter<S> {
+    /// Creates a file (or one file per channel) with the sample rate and center frequency of
+    /// the first receive channel
+    ///
+    /// channels: The receive channels in the stream
+    pub fn create<P: AsRef<Path>>(path: P, usrp: &Usrp, channels: &[usize]) -> Result<Self, Error> {
+        let first_channel = channels.first().copied().unwrap_or(0);
+        let header = Header {
+            sample_rate: Some(usrp.get_rx_sample_rate(first_channel)?),
+            frequency: Some(usrp.get_rx_frequency(first_channel)?),
+            ..Header::new::<S>(channels.len().max(1))
+        };
+        Self::with_header(path, header)
+    }
+
+    /// Creates a file (or one file per channel) described by the provided header
+    ///
+    /// The datatype of the header is replaced with the datatype of S.
+    pub fn with_header<P: AsRef<Path>>(path: P, header: Header) -> Result<Self, Error> {
+        let header = Header {
+            datatype: Datatype::of::<S>(),
+            num_samples: None,
+            ..header
+        };
+        let files = header
+            .data_paths(&path)
+            .iter()
+            .map(|path| File::create(path).map(BufWriter::new))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(RawWriter {
+            files,
+            header_path: header_path(&path),
+            header,
+            max_bytes: None,
+            max_duration: None,
+            samples_written: 0,
+            sample_phantom: PhantomData,
+        })
+    }
+
+    /// Limits the total size of all sample files, in bytes
+    ///
+    /// Only complete samples (one for every channel) are written, so the files may be slightly
+    /// smaller than the limit.
+    pub fn max_bytes(self, max_bytes: u64) -> Self {
+        RawWriter {
+            max_bytes: Some(max_bytes),
+            ..self
+        }
+    }
+
+    /// Limits the duration of the recording, in seconds
+    ///
+    /// This limit is ignored if the header has no sample rate.
+    pub fn max_duration(self, max_duration: f64) -> Self {
+        RawWriter {
+            max_duration: Some(max_duration),
+            ..self
+        }
+    }
+
+    /// Returns the header, which can be modified before the recording is finished
+    pub fn header_mut(&mut self) -> &mut Header {
+        &mut self.header
+    }
+
+    /// Returns the number of samples (per channel) written
+    pub fn samples_written(&self) -> u64 {
+        self.samples_written
+    }
+
+    /// Returns true if a size or duration limit has been reached
+    pub fn is_full(&self) -> bool {
+        self.remaining() == Some(0)
+    }
+
+    /// Returns the number of samples (per channel) that can be written before a limit is
+    /// reached, or None if there is no limit
+    fn remaining(&self) -> Option<u64> {
+        let group_size = (S::SIZE * self.header.num_channels) as u64;
+        let byte_limit = self
+            .max_bytes
+            .and_then(|max_bytes| max_bytes.checked_div(group_size));
+        let duration_limit = self
+            .max_duration
+            .zip(self.header.sample_rate)
+            .map(|(duration, rate)| (duration * rate).max(0.0) as u64);
+        let limit = match (byte_limit, duration_limit) {
+            (Some(bytes), Some(duration)) => Some(bytes.min(duration)),
+            (limit, None) | (None, limit) => limit,
+        };
+        limit.map(|limit| limit.saturating_sub(self.samples_written))
+    }
+
+    /// Writes the samples from a receive operation
+    ///
+    /// buffers: The buffers passed to the receive function, one per channel. Only the number of
+    /// samples in the metadata are written.
+    ///
+    /// metadata: The metadata returned from the receive function. If the header has no start
+    /// time, the time of the first samples written becomes the start time.
+    ///
+    /// On success, this returns the number of samples (per channel) written, which is less
+    /// than the number received if a limit was reached.
+    pub fn write<B: AsRef<[S]>>(
+        &mut self,
+        buffers: &[B],
+        metadata: &ReceiveMetadata,
+    ) -> Result<usize, Error> {
+        self.write_snapshot(buffers, &metadata.snapshot()?)
+    }
+
+    /// Writes the samples from a receive operation, using a metadata snapshot
+    pub fn write_snapshot<B: AsRef<[S]>>(
+        &mut self,
+        buffers: &[B],
+        metadata: &ReceiveMetadataSnapshot,
+    ) -> Result<usize, Error> {
+        if self.samples_written == 0 && metadata.samples != 0 && self.header.start_time.is_none() {
+            self.header.start_time = metadata.time_spec;
+        }
+        self.write_samples(buffers, metadata.samples)
+    }
+
+    /// Writes the first `samples` samples from each buffer
+    ///
+    /// On success, this returns the number of samples (per channel) written, which is less
+    /// than `samples` if a limit was reached.
+    pub fn write_samples<B: AsRef<[S]>>(
+        &mut self,
+        buffers: &[B],
+        samples: usize,
+    ) -> Result<usize, Error> {
+        let length = check_buffers(buffers, self.header.num_channels)?;
+        if samples > length {
+            return Err(Error::with_message(
+                ErrorKind::BufferLength,
+                format!(
+                    "Cannot write {} samples from buffers with length {}",
+                    samples, length
+                ),
+            ));
+        }
+        let samples = match self.remaining() {
+            Some(remaining) if remaining < samples as u64 => remaining as usize,
+            _ => samples,
+        };
+        if self.files.len() == 1 {
+            write_interleaved(&mut self.files[0], buffers, samples)?;
+        } else {
+            for (file, buffer) in self.files.iter_mut().zip(buffers) {
+                write_interleaved(file, &[buffer], samples)?;
+            }
+        }
+        self.samples_written += samples as u64;
+        Ok(samples)
+    }
+
+    /// Flushes the sample files, writes the header, and returns the header
+    pub fn finish(mut self) -> Result<Header, Error> {
+        for file in &mut self.files {
+            file.flush()?;
+        }
+        self.header.num_samples = Some(self.samples_written);
+        self.header.save(&self.header_path)?;
+        Ok(self.header)
+    }
+}
+
+/// Reads samples from raw files
+///
+/// If the file format is not the host sample type S, samples are converted (see
+/// `Datatype::decode`).
+///
+/// Example (reading a file from `rx_samples_to_file --type short`):
+///
+/// ```no_run
+/// use num_complex::Complex32;
+/// use uhd::io::raw::{Header, RawReader};
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let header = Header::from_example_type("short", 1)?;
+/// let mut reader = RawReader::<Complex32>::with_header("usrp_samples.dat", header)?;
+/// let mut buffer = vec![Complex32::default(); 4096];
+/// loop {
+///     let samples = reader.read(&mut [&mut buffer])?;
+///     if samples == 0 {
+///         break;
+///     }
+///     // Process buffer[..samples]
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RawReader<S> {
+    /// The sample files (one for each channel, or one for all channels)
+    files: Vec<BufReader<File>>,
+    /// Describes the files
+    header: Header,
+    sample_phantom: PhantomData<S>,
+}
+
+impl<S: Sample> RawReader<S> {
+    /// Opens a file (or one file per channel) described by the header next to it
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let header = Header::load(header_path(&path))?;
+        Self::with_header(path, header)
+    }
+
+    /// Opens a file (or one file per channel) described by the provided header
+    pub fn with_header<P: AsRef<Path>>(path: P, header: Header) -> Result<Self, Error> {
+        let files = header
+            .data_paths(&path)
+            .iter()
+            .map(|path| File::open(path).map(BufReader::new))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(RawReader {
+            files,
+            header,
+            sample_phantom: PhantomData,
+        })
+    }
+
+    /// Returns the header that describes the files
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Reads samples into one buffer per channel, and returns the number of samples (per
+    /// channel) read
+    ///
+    /// This reads until every buffer is full or the end of the files is reached. A return value
+    /// of 0 means that there are no more samples. If per-channel files have different lengths,
+    /// reading stops at the end of the shortest file, and the extra samples in the other files
+    /// are not returned.
+    pub fn read(&mut self, channels: &mut [&mut [S]]) -> Result<usize, Error> {
+        check_buffers(channels, self.header.num_channels)?;
+        let datatype = self.header.datatype;
+        if self.files.len() == 1 {
+            return Ok(read_interleaved_as(&mut self.files[0], datatype, channels)?);
+        }
+        let mut counts = Vec::with_capacity(self.files.len());
+        for (file, channel) in self.files.iter_mut().zip(channels.iter_mut()) {
+            counts.push(read_interleaved_as(file, datatype, &mut [&mut **channel])?);
+        }
+        let samples = counts.iter().copied().min().unwrap_or(0);
+        // Go back to the first unused sample in longer files, so that the channels stay aligned
+        for (file, &count) in self.files.iter_mut().zip(counts.iter()) {
+            let excess = (count - samples) * datatype.size();
+            if excess != 0 {
+                file.seek(SeekFrom::Current(-(excess as i64)))?;
+            }
+        }
+        Ok(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_complex::Complex;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("uhd-raw-{}-{}.dat", name, std::process::id()))
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(
+            PathBuf::from("dir/samples.01.dat"),
+            channel_path("dir/samples.dat", 1)
+        );
+        assert_eq!(PathBuf::from("samples.12"), channel_path("samples", 12));
+        assert_eq!(PathBuf::from("samples.dat.hdr"), header_path("samples.dat"));
+    }
+
+    #[test]
+    fn header_text() {
+        let header = Header {
+            per_channel_files: true,
+            sample_rate: Some(1e6),
+            frequency: Some(2.45e9),
+            start_time: Some(TimeSpec::new(12, 0.000_000_1)),
+            num_samples: Some(100),
+            ..Header::new::<Complex<i16>>(2)
+        };
+        let text = header.to_string();
+        assert!(text.contains("datatype = ci16_le\n"));
+        assert_eq!(header, text.parse().unwrap());
+        assert_eq!(
+            Header::new::<[f32; 2]>(1),
+            "# comment\ndatatype = cf32_le\nchannels = 1\nunknown = 3\n"
+                .parse()
+                .unwrap()
+        );
+        assert!("channels = 1".parse::<Header>().is_err());
+        assert!("datatype = cf32_le\nchannels = x"
+            .parse::<Header>()
+            .is_err());
+    }
+
+    #[test]
+    fn interleaved_limit() {
+        let path = temp_path("interleaved");
+        let header = Header {
+            sample_rate: Some(1000.0),
+            ..Header::new::<Complex<i16>>(2)
+        };
+        // 0.005 seconds at 1000 samples per second
+        let mut writer = RawWriter::<Complex<i16>>::with_header(&path, header)
+            .unwrap()
+            .max_duration(0.005);
+        let a: Vec<_> = (0..4).map(|i| Complex::new(i, -i)).collect();
+        let b: Vec<_> = (0..4).map(|i| Complex::new(100 + i, 0)).collect();
+        let metadata = ReceiveMetadataSnapshot {
+            samples: 4,
+            time_spec: Some(TimeSpec::new(3, 0.5)),
+            ..ReceiveMetadataSnapshot::default()
+        };
+        assert_eq!(4, writer.write_snapshot(&[&a, &b], &metadata).unwrap());
+        assert!(!writer.is_full());
+        assert_eq!(1, writer.write_snapshot(&[&a, &b], &metadata).unwrap());
+        assert!(writer.is_full());
+        let header = writer.finish().unwrap();
+        assert_eq!(Some(5), header.num_samples);
+        assert_eq!(Some(TimeSpec::new(3, 0.5)), header.start_time);
+        assert_eq!(5 * 2 * 4, fs::metadata(&path).unwrap().len());
+
+        let mut reader = RawReader::<Complex<f32>>::open(&path).unwrap();
+        let mut read_a = [Complex::default(); 8];
+        let mut read_b = [Complex::default(); 8];
+        let samples = reader.read(&mut [&mut read_a, &mut read_b]).unwrap();
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(header_path(&path)).unwrap();
+
+        assert_eq!(5, samples);
+        assert_eq!(Complex::new(3.0 / 32768.0, -3.0 / 32768.0), read_a[3]);
+        assert_eq!(Complex::new(100.0 / 32768.0, 0.0), read_b[4]);
+    }
+
+    #[test]
+    fn per_channel_files() {
+        let path = temp_path("channels");
+        let header = Header {
+            per_channel_files: true,
+            ..Header::new::<[f32; 2]>(2)
+        };
+        // 3 samples per channel, each 8 bytes
+        let mut writer = RawWriter::<[f32; 2]>::with_header(&path, header)
+            .unwrap()
+            .max_bytes(50);
+        let a = [[1.0f32, 2.0]; 4];
+        let b = [[3.0f32, 4.0]; 4];
+        assert_eq!(3, writer.write_samples(&[&a, &b], 4).unwrap());
+        let header = writer.finish().unwrap();
+        let paths = header.data_paths(&path);
+        assert_eq!(channel_path(&path, 1), paths[1]);
+        assert_eq!(24, fs::metadata(&paths[0]).unwrap().len());
+        assert!(!path.exists());
+
+        let mut reader = RawReader::<[f32; 2]>::open(&path).unwrap();
+        let mut read_a = [[0.0; 2]; 4];
+        let mut read_b = [[0.0; 2]; 4];
+        let samples = reader.read(&mut [&mut read_a, &mut read_b]).unwrap();
+        assert_eq!(0, reader.read(&mut [&mut read_a, &mut read_b]).unwrap());
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+        fs::remove_file(header_path(&path)).unwrap();
+
+        assert_eq!(3, samples);
+        assert_eq!(a[..3], read_a[..3]);
+        assert_eq!(b[..3], read_b[..3]);
+    }
+
+    #[test]
+    fn per_channel_files_different_lengths() {
+        let path = temp_path("lengths");
+        let header = Header {
+            per_channel_files: true,
+            ..Header::new::<[i16; 2]>(2)
+        };
+        let paths = header.data_paths(&path);
+        // Channel 0 has 5 samples and channel 1 has 3
+        let encode = |values: &[i16]| -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|&value| vec![value, -value])
+                .flat_map(i16::to_le_bytes)
+                .collect()
+        };
+        fs::write(&paths[0], encode(&[0, 1, 2, 3, 4])).unwrap();
+        fs::write(&paths[1], encode(&[10, 11, 12])).unwrap();
+
+        let mut reader = RawReader::<[i16; 2]>::with_header(&path, header).unwrap();
+        let mut read_a = [[0; 2]; 2];
+        let mut read_b = [[0; 2]; 2];
+        let mut counts = Vec::new();
+        let mut samples_a = Vec::new();
+        let mut samples_b = Vec::new();
+        loop {
+            let samples = reader.read(&mut [&mut read_a, &mut read_b]).unwrap();
+            if samples == 0 {
+                break;
+            }
+            counts.push(samples);
+            samples_a.extend_from_slice(&read_a[..samples]);
+            samples_b.extend_from_slice(&read_b[..samples]);
+        }
+        for path in paths {
+            fs::remove_file(path).unwrap();
+        }
+
+        assert_eq!(vec![2, 1], counts);
+        assert_eq!(vec![[0, 0], [1, -1], [2, -2]], samples_a);
+        assert_eq!(vec![[10, -10], [11, -11], [12, -12]], samples_b);
+    }
+}
@@ -0,0 +1,939 @@
+//!
+//! Recording receive streams in the [SigMF](https://sigmf.org) format, and playing recordings
+//! through transmit streams
+//!
+//! A SigMF recording has two files with the same base name: `.sigmf-data` contains the samples
+//! and `.sigmf-meta` contains JSON metadata that describes them.
+//!
+//! This module is available with the `sigmf` feature.
+//!
+
+use std::convert::TryFrom;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, ErrorKind};
+use crate::io::{read_interleaved_as, write_interleaved, Datatype, Sample};
+use crate::receive_metadata::{ReceiveErrorKind, ReceiveMetadata, ReceiveMetadataSnapshot};
+use crate::sample_clock::SampleClock;
+use crate::transmit_metadata::TransmitMetadata;
+use crate::transmit_streamer::TransmitStreamer;
+use crate::tune_request::TuneRequest;
+use crate::usrp::Usrp;
+use crate::utils::check_buffers;
+use crate::TimeSpec;
+
+/// The version of the SigMF specification that this module writes
+pub const SIGMF_VERSION: &str = "1.0.0";
+/// The extension of SigMF data files
+pub const DATA_EXTENSION: &str = "sigmf-data";
+/// The extension of SigMF metadata files
+pub const META_EXTENSION: &str = "sigmf-meta";
+/// The name of the extension namespace used for USRP information
+const UHD_EXTENSION: &str = "uhd";
+/// Label of annotations that mark discontinuities
+pub const DISCONTINUITY_LABEL: &str = "discontinuity";
+/// Default number of samples (per channel) that a player sends at a time
+const DEFAULT_BLOCK_SIZE: usize = 8192;
+
+/// The contents of a `.sigmf-meta` file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Meta {
+    /// Information about the whole recording
+    pub global: Global,
+    /// Segments of samples that were captured continuously, in order
+    #[serde(default)]
+    pub captures: Vec<CaptureSegment>,
+    /// Notes about ranges of samples
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+/// Information about a whole recording
+///
+/// Fields in the `uhd` extension namespace describe the USRP that made the recording.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Global {
+    /// The format of the samples (for example, `cf32_le`)
+    #[serde(rename = "core:datatype")]
+    pub datatype: String,
+    /// The version of the SigMF specification
+    #[serde(rename = "core:version")]
+    pub version: String,
+    /// The sample rate, in samples/second
+    #[serde(
+        rename = "core:sample_rate",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sample_rate: Option<f64>,
+    /// The number of interleaved channels
+    #[serde(
+        rename = "core:num_channels",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub num_channels: Option<u64>,
+    /// A description of the hardware
+    #[serde(rename = "core:hw", default, skip_serializing_if = "Option::is_none")]
+    pub hw: Option<String>,
+    /// The software that made the recording
+    #[serde(
+        rename = "core:recorder",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recorder: Option<String>,
+    /// A description of the recording
+    #[serde(
+        rename = "core:description",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<String>,
+    /// Extension namespaces used in this metadata
+    #[serde(
+        rename = "core:extensions",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub extensions: Vec<Extension>,
+    /// The UHD version string
+    #[serde(
+        rename = "uhd:version",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub uhd_version: Option<String>,
+    #[serde(
+        rename = "uhd:mboard_id",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub motherboard_id: Option<String>,
+    #[serde(
+        rename = "uhd:mboard_name",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub motherboard_name: Option<String>,
+    #[serde(
+        rename = "uhd:mboard_serial",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub motherboard_serial: Option<String>,
+    #[serde(rename = "uhd:rx_id", default, skip_serializing_if = "Option::is_none")]
+    pub daughterboard_id: Option<String>,
+    #[serde(
+        rename = "uhd:rx_serial",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub daughterboard_serial: Option<String>,
+    #[serde(
+        rename = "uhd:rx_subdev_name",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub subdev_name: Option<String>,
+    #[serde(
+        rename = "uhd:rx_subdev_spec",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub subdev_spec: Option<String>,
+    /// The receive antenna
+    #[serde(
+        rename = "uhd:antenna",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub antenna: Option<String>,
+    /// The overall receive gain, in decibels
+    #[serde(rename = "uhd:gain", default, skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f64>,
+}
+
+impl Global {
+    /// Creates global metadata for samples of type S, with no other information
+    pub fn new<S: Sample>() -> Self {
+        Global {
+            datatype: S::DATATYPE.to_string(),
+            version: SIGMF_VERSION.to_string(),
+            recorder: Some(format!("uhd-rust {}", env!("CARGO_PKG_VERSION"))),
+            ..Global::default()
+        }
+    }
+
+    /// Creates global metadata for samples of type S, with information read from a USRP
+    /// receive channel
+    pub fn from_usrp<S: Sample>(usrp: &Usrp, channel: usize) -> Result<Self, Error> {
+        let info = usrp.get_rx_info(channel)?;
+        Ok(Global {
+            sample_rate: Some(usrp.get_rx_sample_rate(channel)?),
+            hw: Some(
+                format!("{} {}", info.motherboard_name(), info.daughterboard_id())
+                    .trim()
+                    .to_string(),
+            ),
+            extensions: vec![Extension {
+                name: UHD_EXTENSION.to_string(),
+                version: "1.0.0".to_string(),
+                optional: true,
+            }],
+            uhd_version: Some(crate::version()?),
+            motherboard_id: Some(info.motherboard_id().to_string()),
+            motherboard_name: Some(info.motherboard_name().to_string()),
+            motherboard_serial: Some(info.motherboard_serial().to_string()),
+            daughterboard_id: Some(info.daughterboard_id().to_string()),
+            daughterboard_serial: Some(info.daughterboard_serial().to_string()),
+            subdev_name: Some(info.subdev_name().to_string()),
+            subdev_spec: Some(info.subdev_spec().to_string()),
+            antenna: Some(usrp.get_rx_antenna(channel)?),
+            gain: Some(usrp.get_rx_gain(channel, "")?),
+            ..Global::new::<S>()
+        })
+    }
+}
+
+/// An extension namespace used in a recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Extension {
+    pub name: String,
+    pub version: String,
+    pub optional: bool,
+}
+
+/// A segment of samples that were captured continuously
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CaptureSegment {
+    /// The index in the data file of the first sample in this segment (counting one sample per
+    /// channel)
+    #[serde(rename = "core:sample_start")]
+    pub sample_start: u64,
+    /// The device time of the first sample in this segment, in samples
+    #[serde(
+        rename = "core:global_index",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub global_index: Option<u64>,
+    /// The center frequency, in hertz
+    #[serde(
+        rename = "core:frequency",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub frequency: Option<f64>,
+    /// The wall clock time of the first sample in this segment, in ISO 8601 format
+    #[serde(
+        rename = "core:datetime",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub datetime: Option<String>,
+}
+
+/// A note about a range of samples
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// The index of the first sample that this annotation applies to
+    #[serde(rename = "core:sample_start")]
+    pub sample_start: u64,
+    /// The number of samples that this annotation applies to
+    #[serde(
+        rename = "core:sample_count",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sample_count: Option<u64>,
+    /// A short label
+    #[serde(
+        rename = "core:label",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub label: Option<String>,
+    /// A longer description
+    #[serde(
+        rename = "core:comment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub comment: Option<String>,
+}
+
+/// Returns the path of the data file of a recording
+///
+/// The base path may have no extension, or the `.sigmf-data`, `.sigmf-meta`, or `.sigmf`
+/// extension.
+pub fn data_path<P: AsRef<Path>>(base: P) -> PathBuf {
+    with_extension(base.as_ref(), DATA_EXTENSION)
+}
+
+/// Returns the path of the metadata file of a recording
+///
+/// The base path may have no extension, or the `.sigmf-data`, `.sigmf-meta`, or `.sigmf`
+/// extension.
+pub fn meta_path<P: AsRef<Path>>(base: P) -> PathBuf {
+    with_extension(base.as_ref(), META_EXTENSION)
+}
+
+/// Removes any SigMF extension from a base path and appends an extension
+fn with_extension(base: &Path, extension: &str) -> PathBuf {
+    let base = match base.extension().and_then(OsStr::to_str) {
+        Some(DATA_EXTENSION) | Some(META_EXTENSION) | Some("sigmf") => base.with_extension(""),
+        _ => base.to_path_buf(),
+    };
+    // Path::with_extension would replace any other extension, like the .v2 in capture.v2
+    let mut path = base.into_os_string();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+/// Converts a JSON error into an Error
+pub(crate) fn json_error(error: serde_json::Error) -> Error {
+    Error::with_message(ErrorKind::Io, error.to_string())
+}
+
+/// Writes samples from a receive stream into a SigMF recording
+///
+/// The first capture segment starts at the first sample. Whenever the samples from a receive
+/// operation do not continue from the previous samples (usually after an overflow), the writer
+/// starts a new capture segment and adds an annotation that describes the discontinuity.
+///
+/// The metadata file is written when `finish` is called.
+///
+/// Example:
+///
+/// ```no_run
+/// use num_complex::Complex32;
+/// use uhd::io::sigmf::SigmfWriter;
+/// use uhd::{StreamArgs, StreamCommand, StreamCommandType, StreamTime, Usrp};
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let usrp = Usrp::open("")?;
+/// let mut streamer = usrp.get_rx_stream(&StreamArgs::<Complex32>::builder().build())?;
+/// let mut writer = SigmfWriter::<Complex32>::create("capture", &usrp, &[0])?;
+/// streamer.send_command(&StreamCommand {
+///     command_type: StreamCommandType::CountAndDone(1_000_000),
+///     time: StreamTime::Now,
+/// })?;
+/// let mut buffer = vec![Complex32::default(); 4096];
+/// loop {
+///     let metadata = streamer.receive_simple(&mut buffer)?;
+///     writer.write(&[&buffer], &metadata)?;
+///     if metadata.end_of_burst() {
+///         break;
+///     }
+/// }
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SigmfWriter<S> {
+    /// The data file
+    data: BufWriter<File>,
+    /// Path of the metadata file
+    meta_path: PathBuf,
+    /// Metadata written so far
+    meta: Meta,
+    /// Number of channels
+    num_channels: usize,
+    /// Center frequency for capture segments
+    frequency: Option<f64>,
+    /// Tracks sample times to find discontinuities
+    clock: SampleClock,
+    /// Number of samples (per channel) written
+    samples_written: u64,
+    /// An error reported since the last samples were written
+    pending_error: Option<ReceiveErrorKind>,
+    sample_phantom: PhantomData<S>,
+}
+
+impl<S: Sample> SigmfWriter<S> {
+    /// Creates a recording with metadata from a USRP
+    ///
+    /// base: The path of the recording, with or without a SigMF extension
+    ///
+    /// channels: The receive channels in the stream. Hardware information is read from the
+    /// first channel.
+    pub fn create<P: AsRef<Path>>(base: P, usrp: &Usrp, channels: &[usize]) -> Result<Self, Error> {
+        let first_channel = channels.first().copied().unwrap_or(0);
+        let global = Global {
+            num_channels: Some(channels.len().max(1) as u64),
+            ..Global::from_usrp::<S>(usrp, first_channel)?
+        };
+        let frequency = usrp.get_rx_frequency(first_channel)?;
+        Self::with_global(base, global, Some(frequency))
+    }
+
+    /// Creates a recording with the provided global metadata and center frequency
+    ///
+    /// The global metadata must have a sample rate, and its datatype is replaced with the
+    /// datatype of S.
+    pub fn with_global<P: AsRef<Path>>(
+        base: P,
+        global: Global,
+        frequency: Option<f64>,
+    ) -> Result<Self, Error> {
+        let sample_rate = global.sample_rate.ok_or_else(|| {
+            Error::with_message(
+                ErrorKind::Value,
+                "SigMF global metadata has no sample rate".to_string(),
+            )
+        })?;
+        let global = Global {
+            datatype: S::DATATYPE.to_string(),
+            ..global
+        };
+        let num_channels = global.num_channels.unwrap_or(1) as usize;
+        let data = BufWriter::new(File::create(data_path(&base))?);
+        Ok(SigmfWriter {
+            data,
+            meta_path: meta_path(&base),
+            meta: Meta {
+                global,
+                ..Meta::default()
+            },
+            num_channels,
+            frequency,
+            clock: SampleClock::new(sample_rate),
+            samples_written: 0,
+            pending_error: None,
+            sample_phantom: PhantomData,
+        })
+    }
+
+    /// Returns the metadata, which can be modified before the recording is finished
+    pub fn meta_mut(&mut self) -> &mut Meta {
+        &mut self.meta
+    }
+
+    /// Returns the number of samples (per channel) written
+    pub fn samples_written(&self) -> u64 {
+        self.samples_written
+    }
+
+    /// Writes the samples from a receive operation
+    ///
+    /// buffers: The buffers passed to the receive function, one per channel. Only the number of
+    /// samples in the metadata are written.
+    ///
+    /// metadata: The metadata returned from the receive function
+    pub fn write<B: AsRef<[S]>>(
+        &mut self,
+        buffers: &[B],
+        metadata: &ReceiveMetadata,
+    ) -> Result<(), Error> {
+        self.write_snapshot(buffers, &metadata.snapshot()?)
+    }
+
+    /// Writes the samples from a receive operation, using a metadata snapshot
+    pub fn write_snapshot<B: AsRef<[S]>>(
+        &mut self,
+        buffers: &[B],
+        metadata: &ReceiveMetadataSnapshot,
+    ) -> Result<(), Error> {
+        let length = check_buffers(buffers, self.num_channels)?;
+        if metadata.samples > length {
+            return Err(Error::with_message(
+                ErrorKind::BufferLength,
+                format!(
+                    "Metadata reports {} samples, but the buffers have only {}",
+                    metadata.samples, length
+                ),
+            ));
+        }
+        match metadata.error {
+            Some(ReceiveErrorKind::Timeout) | None => {}
+            Some(error) => self.pending_error = Some(error),
+        }
+        if metadata.samples == 0 {
+            return Ok(());
+        }
+
+        let jump = self.clock.update_snapshot(metadata);
+        if self.meta.captures.is_empty() || jump.is_some() {
+            let global_index = metadata
+                .time_spec
+                .and_then(|time| u64::try_from(time.to_ticks(self.clock.sample_rate())).ok());
+            self.meta.captures.push(CaptureSegment {
+                sample_start: self.samples_written,
+                global_index,
+                frequency: self.frequency,
+                datetime: None,
+            });
+        }
+        let pending_error = self.pending_error.take();
+        if jump.is_some() || (pending_error.is_some() && self.samples_written != 0) {
+            let mut comment = match jump {
+                Some(jump) => format!("{} samples lost", jump.samples),
+                None => "Samples may be missing".to_string(),
+            };
+            if let Some(error) = pending_error {
+                comment.push_str(&format!(" after {:?}", error));
+            }
+            self.meta.annotations.push(Annotation {
+                sample_start: self.samples_written,
+                sample_count: None,
+                label: Some(DISCONTINUITY_LABEL.to_string()),
+                comment: Some(comment),
+            });
+        }
+
+        write_interleaved(&mut self.data, buffers, metadata.samples)?;
+        self.samples_written += metadata.samples as u64;
+        Ok(())
+    }
+
+    /// Flushes the data file, writes the metadata file, and returns the metadata
+    pub fn finish(mut self) -> Result<Meta, Error> {
+        self.data.flush()?;
+        let mut meta_file = BufWriter::new(File::create(&self.meta_path)?);
+        serde_json::to_writer_pretty(&mut meta_file, &self.meta).map_err(json_error)?;
+        meta_file.flush()?;
+        Ok(self.meta)
+    }
+}
+
+/// How many times a recording is played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    /// Play the recording once
+    Once,
+    /// Play the recording the provided number of times, without gaps
+    Times(u64),
+    /// Play the recording repeatedly until playback is stopped
+    Forever,
+}
+
+/// Plays a SigMF recording through a transmit stream
+///
+/// All samples are sent as one burst. The first send operation has the start of burst flag and
+/// the optional start time, and the last one has the end of burst flag. When the recording is
+/// repeated, the start of the recording follows the end of the recording without a gap.
+///
+/// If the datatype of the recording is not the host sample type of the streamer, samples are
+/// converted (see `Datatype::decode`).
+///
+/// Example:
+///
+/// ```no_run
+/// use num_complex::Complex32;
+/// use uhd::io::sigmf::{Repeat, SigmfPlayer};
+/// use uhd::{StreamArgs, TimeSpec, Usrp};
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let usrp = Usrp::open("")?;
+/// let start = usrp.get_current_time(0)? + TimeSpec::from_secs_f64(1.0);
+/// let player = SigmfPlayer::open("capture")?
+///     .repeat(Repeat::Times(3))
+///     .start_time(start);
+/// player.configure(&usrp, &[0])?;
+/// let mut streamer = usrp.get_tx_stream(&StreamArgs::<Complex32>::builder().build())?;
+/// let samples = player.play(&mut streamer, 2.0)?;
+/// println!("Sent {} samples", samples);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SigmfPlayer {
+    /// The metadata of the recording
+    meta: Meta,
+    /// Path of the data file
+    data_path: PathBuf,
+    /// Format of the samples in the data file
+    datatype: Datatype,
+    /// Number of interleaved channels in the data file
+    num_channels: usize,
+    /// How many times to play the recording
+    repeat: Repeat,
+    /// Device time of the first sample
+    start_time: Option<TimeSpec>,
+    /// Number of samples (per channel) read and sent at a time
+    block_size: usize,
+}
+
+impl SigmfPlayer {
+    /// Opens a recording
+    ///
+    /// base: The path of the recording, with or without a SigMF extension
+    ///
+    /// This returns an error of kind `ErrorKind::Value` if the datatype of the recording is not
+    /// supported.
+    pub fn open<P: AsRef<Path>>(base: P) -> Result<Self, Error> {
+        let meta_file = BufReader::new(File::open(meta_path(&base))?);
+        let meta: Meta = serde_json::from_reader(meta_file).map_err(json_error)?;
+        let datatype = Datatype::from_name(&meta.global.datatype).ok_or_else(|| {
+            Error::with_message(
+                ErrorKind::Value,
+                format!("Unsupported SigMF datatype {}", meta.global.datatype),
+            )
+        })?;
+        let num_channels = meta.global.num_channels.unwrap_or(1) as usize;
+        Ok(SigmfPlayer {
+            meta,
+            data_path: data_path(&base),
+            datatype,
+            num_channels,
+            repeat: Repeat::Once,
+            start_time: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+        })
+    }
+
+    /// Returns the metadata of the recording
+    pub fn meta(&self) -> &Meta {
+        &self.meta
+    }
+
+    /// Sets how many times the recording is played (default once)
+    pub fn repeat(self, repeat: Repeat) -> Self {
+        SigmfPlayer { repeat, ..self }
+    }
+
+    /// Sets the device time when the first sample is transmitted
+    ///
+    /// By default, the first sample is transmitted as soon as possible.
+    pub fn start_time(self, start_time: TimeSpec) -> Self {
+        SigmfPlayer {
+            start_time: Some(start_time),
+            ..self
+        }
+    }
+
+    /// Sets the number of samples (per channel) that are read from the file and sent at a time
+    pub fn block_size(self, block_size: usize) -> Self {
+        SigmfPlayer {
+            block_size: block_size.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the transmit sample rate and center frequency of the provided channels from the
+    /// metadata
+    ///
+    /// The frequency is taken from the first capture segment. Settings that are not in the
+    /// metadata are not changed.
+    pub fn configure(&self, usrp: &Usrp, channels: &[usize]) -> Result<(), Error> {
+        let frequency = self
+            .meta
+            .captures
+            .first()
+            .and_then(|capture| capture.frequency);
+        for &channel in channels {
+            if let Some(sample_rate) = self.meta.global.sample_rate {
+                usrp.set_tx_sample_rate(sample_rate, channel)?;
+            }
+            if let Some(frequency) = frequency {
+                usrp.set_tx_frequency(&TuneRequest::with_frequency(frequency), channel)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends all samples of the recording (repeated as configured), and returns the number of
+    /// samples (per channel) sent
+    ///
+    /// timeout: The timeout for each send operation, in seconds. If a start time is set, this
+    /// must be long enough for the first send operation to wait until the start time.
+    ///
+    /// This returns an error of kind `ErrorKind::ChannelCount` if the streamer does not have the
+    /// same number of channels as the recording, or `ErrorKind::Timeout` if a send operation
+    /// times out.
+    pub fn play<I: Sample>(
+        &self,
+        streamer: &mut TransmitStreamer<'_, I>,
+        timeout: f64,
+    ) -> Result<u64, Error> {
+        self.play_while(streamer, timeout, || true)
+    }
+
+    /// Sends samples of the recording until all have been sent or keep_going returns false
+    ///
+    /// keep_going is called before each block of samples is read. When it returns false, the
+    /// previous block is sent as the end of the burst.
+    pub fn play_while<I, F>(
+        &self,
+        streamer: &mut TransmitStreamer<'_, I>,
+        timeout: f64,
+        mut keep_going: F,
+    ) -> Result<u64, Error>
+    where
+        I: Sample,
+        F: FnMut() -> bool,
+    {
+        let streamer_channels = streamer.try_num_channels()?;
+        if streamer_channels != self.num_channels {
+            return Err(Error::with_message(
+                ErrorKind::ChannelCount,
+                format!(
+                    "Recording has {} channels, but the streamer has {}",
+                    self.num_channels, streamer_channels
+                ),
+            ));
+        }
+        let mut reader = BlockReader::open(&self.data_path, self.datatype, self.repeat)?;
+        let mut current = vec![vec![I::default(); self.block_size]; self.num_channels];
+        let mut next = current.clone();
+        let mut current_length = reader.read(&mut current)?;
+        let mut first = true;
+        let mut sent = 0;
+        while current_length != 0 {
+            let next_length = if keep_going() {
+                reader.read(&mut next)?
+            } else {
+                0
+            };
+            let last = next_length == 0;
+            self.send_block(streamer, &current, current_length, first, last, timeout)?;
+            sent += current_length as u64;
+            first = false;
+            mem::swap(&mut current, &mut next);
+            current_length = next_length;
+        }
+        Ok(sent)
+    }
+
+    /// Sends the first length samples of each channel buffer
+    fn send_block<I: Sample>(
+        &self,
+        streamer: &mut TransmitStreamer<'_, I>,
+        channels: &[Vec<I>],
+        length: usize,
+        first: bool,
+        last: bool,
+        timeout: f64,
+    ) -> Result<(), Error> {
+        let start_time = if first {
+            self.start_time.as_ref()
+        } else {
+            None
+        };
+        let mut metadata = TransmitMetadata::try_with_options(first, last, start_time)?;
+        let mut sent = 0;
+        loop {
+            let buffers: Vec<&[I]> = channels
+                .iter()
+                .map(|channel| &channel[sent..length])
+                .collect();
+            let samples = streamer.send_with_metadata(&buffers, &mut metadata, timeout)?;
+            sent += samples;
+            if sent == length {
+                return Ok(());
+            }
+            if samples == 0 {
+                return Err(Error::with_message(
+                    ErrorKind::Timeout,
+                    format!("Sent {} of {} samples in a block", sent, length),
+                ));
+            }
+            // The rest of the block continues the burst
+            metadata = TransmitMetadata::try_with_options(false, last, None)?;
+        }
+    }
+}
+
+/// Reads blocks of samples from a data file, starting again at the beginning of the file when
+/// the recording is repeated
+struct BlockReader {
+    file: BufReader<File>,
+    datatype: Datatype,
+    /// Number of times the file still needs to be read to the end, or None to repeat forever
+    passes_left: Option<u64>,
+    /// True if any samples have been read since the file was last rewound
+    read_this_pass: bool,
+}
+
+impl BlockReader {
+    fn open(path: &Path, datatype: Datatype, repeat: Repeat) -> Result<Self, Error> {
+        let passes_left = match repeat {
+            Repeat::Once => Some(1),
+            Repeat::Times(times) => Some(times),
+            Repeat::Forever => None,
+        };
+        Ok(BlockReader {
+            file: BufReader::new(File::open(path)?),
+            datatype,
+            passes_left,
+            read_this_pass: false,
+        })
+    }
+
+    /// Reads samples into the channel buffers, and returns the number of samples (per channel)
+    /// read
+    ///
+    /// A return value of 0 means that all passes through the file are complete.
+    fn read<I: Sample>(&mut self, channels: &mut [Vec<I>]) -> Result<usize, Error> {
+        loop {
+            if self.passes_left == Some(0) {
+                return Ok(0);
+            }
+            let mut buffers: Vec<&mut [I]> = channels.iter_mut().map(Vec::as_mut_slice).collect();
+            let samples = read_interleaved_as(&mut self.file, self.datatype, &mut buffers)?;
+            if samples != 0 {
+                self.read_this_pass = true;
+                return Ok(samples);
+            }
+            // End of file
+            if !self.read_this_pass {
+                // The file has no samples, so repeating it would never produce any
+                return Ok(0);
+            }
+            if let Some(passes_left) = self.passes_left.as_mut() {
+                *passes_left -= 1;
+            }
+            self.file.seek(SeekFrom::Start(0))?;
+            self.read_this_pass = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_complex::Complex;
+    use std::fs;
+
+    fn snapshot(samples: usize, seconds: i64, fraction: f64) -> ReceiveMetadataSnapshot {
+        ReceiveMetadataSnapshot {
+            samples,
+            time_spec: Some(TimeSpec::new(seconds, fraction)),
+            ..ReceiveMetadataSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(PathBuf::from("a/rec.sigmf-data"), data_path("a/rec"));
+        assert_eq!(PathBuf::from("rec.sigmf-meta"), meta_path("rec.sigmf-data"));
+        assert_eq!(PathBuf::from("rec.sigmf-data"), data_path("rec.sigmf"));
+        assert_eq!(PathBuf::from("rec.v2.sigmf-meta"), meta_path("rec.v2"));
+    }
+
+    #[test]
+    fn discontinuity() {
+        let base = std::env::temp_dir().join(format!("uhd-sigmf-writer-{}", std::process::id()));
+        let global = Global {
+            sample_rate: Some(1000.0),
+            ..Global::new::<Complex<i16>>()
+        };
+        let mut writer =
+            SigmfWriter::<Complex<i16>>::with_global(&base, global, Some(2.4e9)).unwrap();
+        let buffer = vec![Complex::new(1i16, -1); 8];
+        writer
+            .write_snapshot(&[&buffer], &snapshot(4, 1, 0.0))
+            .unwrap();
+        writer
+            .write_snapshot(&[&buffer], &snapshot(8, 1, 0.004))
+            .unwrap();
+        let overflow = ReceiveMetadataSnapshot {
+            error: Some(ReceiveErrorKind::Overflow),
+            ..ReceiveMetadataSnapshot::default()
+        };
+        writer.write_snapshot(&[&buffer], &overflow).unwrap();
+        writer
+            .write_snapshot(&[&buffer], &snapshot(2, 1, 0.022))
+            .unwrap();
+        let meta = writer.finish().unwrap();
+
+        let data = fs::read(data_path(&base)).unwrap();
+        let written: Meta = serde_json::from_slice(&fs::read(meta_path(&base)).unwrap()).unwrap();
+        fs::remove_file(data_path(&base)).unwrap();
+        fs::remove_file(meta_path(&base)).unwrap();
+
+        assert_eq!(14 * 4, data.len());
+        assert_eq!(meta, written);
+        assert_eq!("ci16_le", meta.global.datatype);
+        assert_eq!(
+            vec![
+                CaptureSegment {
+                    sample_start: 0,
+                    global_index: Some(1000),
+                    frequency: Some(2.4e9),
+                    datetime: None
+                },
+                CaptureSegment {
+                    sample_start: 12,
+                    global_index: Some(1022),
+                    frequency: Some(2.4e9),
+                    datetime: None
+                },
+            ],
+            meta.captures
+        );
+        assert_eq!(1, meta.annotations.len());
+        assert_eq!(12, meta.annotations[0].sample_start);
+        assert_eq!(
+            Some("10 samples lost after Overflow"),
+            meta.annotations[0].comment.as_deref()
+        );
+    }
+
+    #[test]
+    fn repeat() {
+        let path = std::env::temp_dir().join(format!("uhd-sigmf-player-{}", std::process::id()));
+        // Two channels, three samples each, stored as ci16
+        let mut data = Vec::new();
+        let channels = [[1i16, 2, 3], [-4, -5, -6]];
+        write_interleaved(
+            &mut data,
+            &[
+                channels[0]
+                    .iter()
+                    .map(|&i| Complex::new(i, 0))
+                    .collect::<Vec<_>>(),
+                channels[1]
+                    .iter()
+                    .map(|&i| Complex::new(i, 0))
+                    .collect::<Vec<_>>(),
+            ],
+            3,
+        )
+        .unwrap();
+        fs::write(&path, data).unwrap();
+
+        let read_all = |repeat| {
+            let mut reader = BlockReader::open(&path, Datatype::Ci16, repeat).unwrap();
+            let mut blocks = vec![vec![Complex::<i16>::default(); 2]; 2];
+            let mut lengths = Vec::new();
+            let mut first = Vec::new();
+            loop {
+                let length = reader.read(&mut blocks).unwrap();
+                if length == 0 || lengths.len() == 6 {
+                    break;
+                }
+                lengths.push(length);
+                first.push((blocks[0][0].re, blocks[1][0].re));
+            }
+            (lengths, first)
+        };
+        let (lengths, first) = read_all(Repeat::Once);
+        assert_eq!(vec![2, 1], lengths);
+        assert_eq!(vec![(1, -4), (3, -6)], first);
+        assert_eq!(vec![2, 1, 2, 1], read_all(Repeat::Times(2)).0);
+        assert!(read_all(Repeat::Times(0)).0.is_empty());
+        assert_eq!(6, read_all(Repeat::Forever).0.len());
+
+        // Conversion to another sample type
+        let mut reader = BlockReader::open(&path, Datatype::Ci16, Repeat::Once).unwrap();
+        let mut blocks = vec![vec![Complex::<f32>::default(); 3]; 2];
+        assert_eq!(3, reader.read(&mut blocks).unwrap());
+        assert!((blocks[1][2].re - -6.0 / 32768.0).abs() < 1e-9);
+
+        // An empty file produces no samples, even when repeated forever
+        fs::write(&path, []).unwrap();
+        assert!(read_all(Repeat::Forever).0.is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
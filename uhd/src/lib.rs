@@ -16,47 +16,99 @@ extern crate libc;
 extern crate num_complex;
 extern crate uhd_sys;
 
+#[cfg(feature = "ndarray")]
+mod array;
+mod async_metadata;
+mod capture;
+#[cfg(feature = "config")]
+pub mod config;
 mod daughter_board_eeprom;
+mod device_args;
+mod discovery;
 mod error;
+mod fft;
+mod hop;
+pub mod io;
 mod motherboard_eeprom;
 pub mod range;
 mod receive_info;
 mod receive_metadata;
+mod receive_pipeline;
 mod receive_streamer;
-mod transmit_streamer;
+mod recovery;
+mod ring_buffer;
+mod sensor_value;
+pub mod server;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+mod sample_clock;
+mod transmit_info;
 mod transmit_metadata;
+mod transmit_pipeline;
+mod transmit_streamer;
 mod stream;
 mod string_vector;
+mod sweep;
+mod time_spec;
+mod timed_send;
 mod tune_request;
 mod tune_result;
 mod usrp;
 mod utils;
+pub mod vrt;
+#[cfg(feature = "zmq")]
+pub mod zmq;
 
 // Re-export many public items at the root
+pub use crate::async_metadata::{AsyncEvent, AsyncMetadata};
+pub use crate::capture::Capture;
 pub use crate::daughter_board_eeprom::DaughterBoardEeprom;
+pub use crate::device_args::{DeviceAddr, DeviceArgs};
+pub use crate::discovery::{DeviceEvent, DeviceFilter, DeviceWatcher};
 pub use crate::error::*;
+pub use crate::hop::{Hop, HopResult, HopSchedule};
 pub use crate::motherboard_eeprom::MotherboardEeprom;
 pub use crate::receive_info::ReceiveInfo;
 pub use crate::receive_metadata::*;
-pub use crate::receive_streamer::ReceiveStreamer;
-pub use crate::transmit_streamer::TransmitStreamer;
+pub use crate::receive_pipeline::{
+    ReadGuard, ReceiveBlock, ReceivePipeline, ReceivePipelineBuilder, ReceivePipelineStats,
+};
+pub use crate::receive_streamer::{OwnedReceiveStreamer, ReceiveStreamer};
+pub use crate::recovery::{ReceiveEvent, RecoveredReceive, Recovery};
+pub use crate::sensor_value::{SensorType, SensorValue};
+pub use crate::sample_clock::{SampleClock, TimestampJump};
+pub use crate::transmit_info::TransmitInfo;
 pub use crate::transmit_metadata::*;
+pub use crate::transmit_pipeline::{
+    TransmitBlock, TransmitPipeline, TransmitPipelineBuilder, TransmitPipelineStats, TransmitSender,
+};
+pub use crate::transmit_streamer::{OwnedTransmitStreamer, TransmitStreamer};
 pub use crate::stream::*;
+pub use crate::sweep::{Spectrum, Sweep, Window};
+pub use crate::time_spec::TimeSpec;
+pub use crate::timed_send::BurstStatus;
 pub use crate::tune_request::*;
 pub use crate::tune_result::TuneResult;
 pub use crate::usrp::Usrp;
 
 // Common definitions
 
-/// A time value, represented as an integer number of seconds and a floating-point fraction of
-/// a second
-#[derive(Debug, Clone, Default, PartialOrd, PartialEq)]
-pub struct TimeSpec {
-    // In some versions of UHD, the corresponding field of uhd::time_spec_t is a time_t.
-    // In other versions, it's a int64_t. The Rust code does conversion to keep this
-    // an i64.
-    pub seconds: i64,
-    pub fraction: f64,
+/// Sets the scheduling priority of the current thread
+///
+/// priority: The priority, in the range [0, 1]
+///
+/// realtime: If this is true, a real-time scheduling policy is requested
+///
+/// This commonly fails if the process does not have permission to change its scheduling policy.
+pub fn set_thread_priority(priority: f32, realtime: bool) -> Result<()> {
+    check_status(unsafe { uhd_sys::uhd_set_thread_priority(priority, realtime) })
+}
+
+/// Returns the version of the UHD library (a dotted version number and build information)
+pub fn version() -> Result<String> {
+    utils::copy_string(|buffer, length| unsafe {
+        uhd_sys::uhd_get_version_string(buffer, length as _)
+    })
 }
 
 
@@ -1,6 +1,6 @@
 use std::ptr;
 
-use crate::error::check_status;
+use crate::error::{check_status, Error};
 use crate::utils::copy_string;
 use crate::TimeSpec;
 
@@ -17,52 +17,63 @@ impl ReceiveMetadata {
         Default::default()
     }
 
+    /// Creates a metadata object, returning an error if UHD cannot allocate it
+    pub fn try_new() -> Result<Self, Error> {
+        let mut handle: uhd_sys::uhd_rx_metadata_handle = ptr::null_mut();
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_make(&mut handle) })?;
+        Ok(ReceiveMetadata { handle, samples: 0 })
+    }
+
     /// Returns the timestamp of (the first?) of the received samples, according to the USRP's
     /// internal clock
     pub fn time_spec(&self) -> Option<TimeSpec> {
-        if self.has_time_spec() {
-            let mut time = TimeSpec::default();
-            let mut seconds_time_t: libc::time_t = Default::default();
-            let mut seconds_time_t_i64: i64 = seconds_time_t as i64;
+        self.try_time_spec().unwrap()
+    }
 
+    /// Returns the timestamp of the received samples, or an error if it could not be read
+    pub fn try_time_spec(&self) -> Result<Option<TimeSpec>, Error> {
+        if self.has_time_spec()? {
+            let mut time = TimeSpec::default();
             check_status(unsafe {
-                uhd_sys::uhd_rx_metadata_time_spec(
-                    self.handle,
-                    &mut seconds_time_t_i64 as *mut i64,
-                    &mut time.fraction,
-                )
-            })
-            .unwrap();
-            // Convert seconds from time_t to i64
-            time.seconds = seconds_time_t.into();
-            Some(time)
+                uhd_sys::uhd_rx_metadata_time_spec(self.handle, &mut time.seconds, &mut time.fraction)
+            })?;
+            Ok(Some(time))
         } else {
-            None
+            Ok(None)
         }
     }
 
     /// Returns true if this metadata object has a time
-    fn has_time_spec(&self) -> bool {
+    fn has_time_spec(&self) -> Result<bool, Error> {
         let mut has = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_has_time_spec(self.handle, &mut has) })
-            .unwrap();
-        has
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_has_time_spec(self.handle, &mut has) })?;
+        Ok(has)
     }
 
     /// Returns true if the received samples are at the beginning of a burst
     pub fn start_of_burst(&self) -> bool {
+        self.try_start_of_burst().unwrap()
+    }
+
+    /// Returns true if the received samples are at the beginning of a burst, or an error if the
+    /// flag could not be read
+    pub fn try_start_of_burst(&self) -> Result<bool, Error> {
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_start_of_burst(self.handle, &mut value) })
-            .unwrap();
-        value
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_start_of_burst(self.handle, &mut value) })?;
+        Ok(value)
     }
 
     /// Returns true if the received samples are at the end of a burst
     pub fn end_of_burst(&self) -> bool {
+        self.try_end_of_burst().unwrap()
+    }
+
+    /// Returns true if the received samples are at the end of a burst, or an error if the flag
+    /// could not be read
+    pub fn try_end_of_burst(&self) -> Result<bool, Error> {
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_end_of_burst(self.handle, &mut value) })
-            .unwrap();
-        value
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_end_of_burst(self.handle, &mut value) })?;
+        Ok(value)
     }
 
     /// Returns true if the provided receive buffer was not large enough to hold a full packet
@@ -70,32 +81,46 @@ impl ReceiveMetadata {
     /// If this is the case, the fragment_offset() function returns the offset from the beginning
     /// of the packet to the first sample received
     pub fn more_fragments(&self) -> bool {
+        self.try_more_fragments().unwrap()
+    }
+
+    /// Returns true if the provided receive buffer was not large enough to hold a full packet, or
+    /// an error if the flag could not be read
+    pub fn try_more_fragments(&self) -> Result<bool, Error> {
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_more_fragments(self.handle, &mut value) })
-            .unwrap();
-        value
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_more_fragments(self.handle, &mut value) })?;
+        Ok(value)
     }
 
     /// If more_fragments() returned true, this function returns the offset from the beginning
     /// of the packet to the first sample received
     pub fn fragment_offset(&self) -> usize {
+        self.try_fragment_offset().unwrap()
+    }
+
+    /// Returns the fragment offset, or an error if it could not be read
+    pub fn try_fragment_offset(&self) -> Result<usize, Error> {
         let mut value = 0usize;
         check_status(unsafe {
             uhd_sys::uhd_rx_metadata_fragment_offset(
                 self.handle,
                 &mut value as *mut usize as *mut _,
             )
-        })
-        .unwrap();
-        value
+        })?;
+        Ok(value)
     }
 
     /// Returns true if a packet was dropped or received out of order
     pub fn out_of_sequence(&self) -> bool {
+        self.try_out_of_sequence().unwrap()
+    }
+
+    /// Returns true if a packet was dropped or received out of order, or an error if the flag
+    /// could not be read
+    pub fn try_out_of_sequence(&self) -> Result<bool, Error> {
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_out_of_sequence(self.handle, &mut value) })
-            .unwrap();
-        value
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_out_of_sequence(self.handle, &mut value) })?;
+        Ok(value)
     }
 
     /// Returns the number of samples received
@@ -109,18 +134,17 @@ impl ReceiveMetadata {
     }
 
     /// Returns the error code associated with the receive operation
-    fn error_code(&self) -> uhd_sys::uhd_rx_metadata_error_code_t::Type {
+    fn error_code(&self) -> Result<uhd_sys::uhd_rx_metadata_error_code_t::Type, Error> {
         let mut code = uhd_sys::uhd_rx_metadata_error_code_t::UHD_RX_METADATA_ERROR_CODE_NONE;
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_error_code(self.handle, &mut code) })
-            .unwrap();
-        code
+        check_status(unsafe { uhd_sys::uhd_rx_metadata_error_code(self.handle, &mut code) })?;
+        Ok(code)
     }
 
-    /// Returns the error associated with the receive operation, if any
-    pub fn last_error(&self) -> Option<ReceiveError> {
-        let out_of_sequence = self.out_of_sequence();
+    /// Returns the kind of error associated with the receive operation, if any
+    fn error_kind(&self) -> Result<Option<ReceiveErrorKind>, Error> {
+        let out_of_sequence = self.try_out_of_sequence()?;
         use uhd_sys::uhd_rx_metadata_error_code_t::*;
-        let kind = match self.error_code() {
+        let kind = match self.error_code()? {
             UHD_RX_METADATA_ERROR_CODE_TIMEOUT => ReceiveErrorKind::Timeout,
             UHD_RX_METADATA_ERROR_CODE_LATE_COMMAND => ReceiveErrorKind::LateCommand,
             UHD_RX_METADATA_ERROR_CODE_BROKEN_CHAIN => ReceiveErrorKind::BrokenChain,
@@ -132,19 +156,50 @@ impl ReceiveMetadata {
             UHD_RX_METADATA_ERROR_CODE_BAD_PACKET => ReceiveErrorKind::BadPacket,
             UHD_RX_METADATA_ERROR_CODE_NONE => {
                 // Not actually an error
-                return None;
+                return Ok(None);
             }
             _ => {
                 // Some other error
                 ReceiveErrorKind::Other
             }
         };
+        Ok(Some(kind))
+    }
+
+    /// Returns the error associated with the receive operation, if any
+    pub fn last_error(&self) -> Option<ReceiveError> {
+        self.try_last_error().unwrap()
+    }
+
+    /// Returns the error associated with the receive operation, if any, or an error if the
+    /// metadata could not be read
+    pub fn try_last_error(&self) -> Result<Option<ReceiveError>, Error> {
+        let kind = match self.error_kind()? {
+            Some(kind) => kind,
+            None => return Ok(None),
+        };
         let message = copy_string(|buffer, length| unsafe {
             uhd_sys::uhd_rx_metadata_strerror(self.handle, buffer, length as _)
         })
         .ok();
 
-        Some(ReceiveError { kind, message })
+        Ok(Some(ReceiveError { kind, message }))
+    }
+
+    /// Copies all fields of this metadata into a plain Rust value
+    ///
+    /// The snapshot does not include the error message, which would require an allocation.
+    pub fn snapshot(&self) -> Result<ReceiveMetadataSnapshot, Error> {
+        Ok(ReceiveMetadataSnapshot {
+            samples: self.samples,
+            time_spec: self.try_time_spec()?,
+            start_of_burst: self.try_start_of_burst()?,
+            end_of_burst: self.try_end_of_burst()?,
+            more_fragments: self.try_more_fragments()?,
+            fragment_offset: self.try_fragment_offset()?,
+            out_of_sequence: self.try_out_of_sequence()?,
+            error: self.error_kind()?,
+        })
     }
 
     pub(crate) fn handle_mut(&mut self) -> &mut uhd_sys::uhd_rx_metadata_handle {
@@ -152,15 +207,39 @@ impl ReceiveMetadata {
     }
 }
 
+/// A copy of the information in a `ReceiveMetadata`, without a handle to a C++ object
+///
+/// Snapshots are created using `ReceiveMetadata::snapshot`. With the `serde` feature, they can
+/// be serialized and deserialized.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReceiveMetadataSnapshot {
+    /// The number of samples received
+    pub samples: usize,
+    /// The timestamp of the first received sample, if available
+    pub time_spec: Option<TimeSpec>,
+    /// True if the received samples are at the beginning of a burst
+    pub start_of_burst: bool,
+    /// True if the received samples are at the end of a burst
+    pub end_of_burst: bool,
+    /// True if the receive buffer was not large enough to hold a full packet
+    pub more_fragments: bool,
+    /// The offset from the beginning of the packet to the first sample received, if
+    /// more_fragments is true
+    pub fragment_offset: usize,
+    /// True if a packet was dropped or received out of order
+    pub out_of_sequence: bool,
+    /// The kind of error associated with the receive operation, if any
+    pub error: Option<ReceiveErrorKind>,
+}
+
 // Thread safety: The uhd_rx_metadata struct just stores data. All exposed functions read fields.
 unsafe impl Send for ReceiveMetadata {}
 unsafe impl Sync for ReceiveMetadata {}
 
 impl Default for ReceiveMetadata {
     fn default() -> Self {
-        let mut handle: uhd_sys::uhd_rx_metadata_handle = ptr::null_mut();
-        check_status(unsafe { uhd_sys::uhd_rx_metadata_make(&mut handle) }).unwrap();
-        ReceiveMetadata { handle, samples: 0 }
+        ReceiveMetadata::try_new().unwrap()
     }
 }
 
@@ -178,11 +257,11 @@ mod fmt {
     impl Debug for ReceiveMetadata {
         fn fmt(&self, f: &mut Formatter<'_>) -> Result {
             f.debug_struct("ReceiveMetadata")
-                .field("time_spec", &self.time_spec())
-                .field("more_fragments", &self.more_fragments())
-                .field("fragment_offset", &self.fragment_offset())
-                .field("start_of_burst", &self.start_of_burst())
-                .field("end_of_burst", &self.end_of_burst())
+                .field("time_spec", &self.try_time_spec())
+                .field("more_fragments", &self.try_more_fragments())
+                .field("fragment_offset", &self.try_fragment_offset())
+                .field("start_of_burst", &self.try_start_of_burst())
+                .field("end_of_burst", &self.try_end_of_burst())
                 .finish()
         }
     }
@@ -218,7 +297,7 @@ pub struct ReceiveError {
 
 impl ReceiveError {
     pub fn kind(&self) -> ReceiveErrorKind {
-        self.kind.clone()
+        self.kind
     }
     pub fn message(&self) -> Option<&str> {
         self.message.as_deref()
@@ -228,7 +307,8 @@ impl ReceiveError {
 impl std::error::Error for ReceiveError {}
 
 #[non_exhaustive]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReceiveErrorKind {
     Timeout,
     LateCommand,
@@ -242,7 +322,7 @@ pub enum ReceiveErrorKind {
 
 #[cfg(test)]
 mod test {
-    use super::ReceiveMetadata;
+    use super::{ReceiveMetadata, ReceiveMetadataSnapshot};
 
     #[test]
     fn default_rx_metadata() {
@@ -255,4 +335,10 @@ mod test {
         assert_eq!(0, metadata.fragment_offset());
         assert!(metadata.last_error().is_none());
     }
+
+    #[test]
+    fn default_rx_metadata_snapshot() {
+        let snapshot = ReceiveMetadata::try_new().unwrap().snapshot().unwrap();
+        assert_eq!(ReceiveMetadataSnapshot::default(), snapshot);
+    }
 }
@@ -0,0 +1,497 @@
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::receive_metadata::{ReceiveErrorKind, ReceiveMetadata, ReceiveMetadataSnapshot};
+use crate::receive_streamer::{OwnedReceiveStreamer, ReceiveStreamer};
+use crate::ring_buffer::{ring_buffer, Consumer, Producer};
+use crate::stream::{StreamCommand, StreamCommandType, StreamTime};
+use crate::{set_thread_priority, TimeSpec};
+
+/// Receives samples on a dedicated thread into a ring of preallocated blocks
+///
+/// A pipeline is created using a `ReceivePipelineBuilder`. The pipeline thread calls
+/// `ReceiveStreamer::receive` in a loop, writing each call's samples directly into the next free
+/// block. Consumers read blocks in place using `read()` or `try_read()`.
+///
+/// If the consumer falls behind and all blocks are full, the pipeline thread keeps receiving
+/// (so that the USRP does not overflow) but discards the samples. Discarded blocks are counted in
+/// the pipeline statistics.
+///
+/// Example:
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use num_complex::Complex32;
+/// use uhd::{
+///     ReceivePipelineBuilder, StreamArgs, StreamCommand, StreamCommandType, StreamTime, Usrp,
+///     WireFormat,
+/// };
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let usrp = Arc::new(Usrp::open("")?);
+/// let streamer = usrp.get_rx_stream_owned(&StreamArgs::<Complex32>::new(WireFormat::Sc16)?)?;
+/// let start = StreamCommand {
+///     command_type: StreamCommandType::StartContinuous,
+///     time: StreamTime::Now,
+/// };
+/// let mut pipeline = ReceivePipelineBuilder::new()
+///     .block_size(65536)
+///     .num_blocks(64)
+///     .start(streamer, start)?;
+///
+/// while let Some(block) = pipeline.read(Duration::from_secs(1)) {
+///     let samples: &[Complex32] = block.channel(0);
+///     // Process samples
+/// #   let _ = samples;
+/// }
+/// println!("{:?}", pipeline.stats());
+/// # Ok(())
+/// # }
+/// ```
+pub struct ReceivePipeline<I> {
+    consumer: Consumer<ReceiveBlock<I>>,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<OwnedReceiveStreamer<I>>>,
+}
+
+impl<I> ReceivePipeline<I>
+where
+    I: Copy + Default + Send + 'static,
+{
+    /// Returns the oldest block of received samples, if one is available
+    ///
+    /// This function does not block. The block is returned to the pipeline when the guard is
+    /// dropped.
+    pub fn try_read(&mut self) -> Option<ReadGuard<'_, I>> {
+        if self.consumer.front().is_some() {
+            Some(ReadGuard {
+                consumer: &mut self.consumer,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Waits up to `timeout` for a block of received samples
+    ///
+    /// This function returns None if the timeout expires, or if the pipeline has stopped and all
+    /// received blocks have been read.
+    pub fn read(&mut self, timeout: Duration) -> Option<ReadGuard<'_, I>> {
+        let deadline = Instant::now() + timeout;
+        let mut spins = 0u32;
+        while self.consumer.front().is_none() {
+            if !self.shared.running.load(Ordering::Acquire) && self.consumer.front().is_none() {
+                return None;
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            // Spin briefly, then back off to avoid burning a core while waiting
+            if spins < 64 {
+                spins += 1;
+                std::hint::spin_loop();
+            } else if spins < 128 {
+                spins += 1;
+                thread::yield_now();
+            } else {
+                thread::sleep(Duration::from_micros(50));
+            }
+        }
+        self.try_read()
+    }
+
+    /// Returns the number of received blocks that are waiting to be read
+    pub fn available(&self) -> usize {
+        self.consumer.len()
+    }
+
+    /// Returns true if the pipeline thread is still receiving
+    pub fn is_running(&self) -> bool {
+        self.shared.running.load(Ordering::Acquire)
+    }
+
+    /// Returns a snapshot of the pipeline statistics
+    pub fn stats(&self) -> ReceivePipelineStats {
+        self.shared.stats.snapshot()
+    }
+
+    /// Stops the pipeline thread, stops streaming, and returns the streamer
+    ///
+    /// If the pipeline thread stopped because of an error, that error is returned instead.
+    /// Blocks that were received but not read are discarded.
+    pub fn stop(mut self) -> Result<OwnedReceiveStreamer<I>, Error> {
+        let streamer = self.stop_thread();
+        match self.shared.error.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(streamer.expect("Receive pipeline thread already stopped")),
+        }
+    }
+
+    fn stop_thread(&mut self) -> Option<OwnedReceiveStreamer<I>> {
+        self.shared.stop.store(true, Ordering::Release);
+        let thread = self.thread.take()?;
+        match thread.join() {
+            Ok(streamer) => Some(streamer),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl<I> Drop for ReceivePipeline<I> {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A builder for a receive pipeline
+#[derive(Debug, Clone)]
+pub struct ReceivePipelineBuilder {
+    block_size: usize,
+    num_blocks: usize,
+    timeout: f64,
+    priority: Option<f32>,
+}
+
+impl Default for ReceivePipelineBuilder {
+    fn default() -> Self {
+        ReceivePipelineBuilder {
+            block_size: 16384,
+            num_blocks: 32,
+            timeout: 0.1,
+            priority: Some(0.5),
+        }
+    }
+}
+
+impl ReceivePipelineBuilder {
+    /// Creates a builder with default settings
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the maximum number of samples per channel in each block
+    pub fn block_size(self, block_size: usize) -> Self {
+        ReceivePipelineBuilder { block_size, ..self }
+    }
+
+    /// Sets the number of blocks in the ring buffer
+    pub fn num_blocks(self, num_blocks: usize) -> Self {
+        ReceivePipelineBuilder { num_blocks, ..self }
+    }
+
+    /// Sets the timeout for each receive operation, in seconds
+    ///
+    /// This also limits how long stopping the pipeline can take.
+    pub fn timeout(self, timeout: f64) -> Self {
+        ReceivePipelineBuilder { timeout, ..self }
+    }
+
+    /// Sets the real-time scheduling priority of the pipeline thread, in the range [0, 1]
+    ///
+    /// If this is None, the thread runs at normal priority. Failing to set the priority (for
+    /// example, because the process does not have permission) is not an error.
+    pub fn priority(self, priority: Option<f32>) -> Self {
+        ReceivePipelineBuilder { priority, ..self }
+    }
+
+    /// Allocates the blocks, starts the pipeline thread, and sends a stream command to start
+    /// streaming
+    ///
+    /// The streamer is moved into the pipeline thread, so it must be an owned streamer (created
+    /// with `Usrp::get_rx_stream_owned`).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the block size or number of blocks is zero.
+    pub fn start<I>(
+        self,
+        streamer: OwnedReceiveStreamer<I>,
+        command: StreamCommand,
+    ) -> Result<ReceivePipeline<I>, Error>
+    where
+        I: Copy + Default + Send + 'static,
+    {
+        assert_ne!(self.block_size, 0, "Block size must not be zero");
+        let num_channels = streamer.try_num_channels()?;
+        let blocks = (0..self.num_blocks)
+            .map(|_| ReceiveBlock::new(num_channels, self.block_size))
+            .collect();
+        let (producer, consumer) = ring_buffer(blocks);
+        let scratch = ReceiveBlock::new(num_channels, self.block_size);
+
+        streamer.send_command(&command)?;
+
+        let shared = Arc::new(Shared {
+            running: AtomicBool::new(true),
+            stop: AtomicBool::new(false),
+            stats: Counters::default(),
+            error: Mutex::new(None),
+        });
+        let thread_shared = Arc::clone(&shared);
+        let thread = thread::Builder::new()
+            .name("uhd-rx-pipeline".into())
+            .spawn(move || {
+                run_receive(
+                    streamer,
+                    producer,
+                    scratch,
+                    &thread_shared,
+                    self.timeout,
+                    self.priority,
+                )
+            })
+            .expect("Failed to spawn receive pipeline thread");
+
+        Ok(ReceivePipeline {
+            consumer,
+            shared,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// The body of the pipeline thread
+fn run_receive<I>(
+    mut streamer: OwnedReceiveStreamer<I>,
+    mut producer: Producer<ReceiveBlock<I>>,
+    mut scratch: ReceiveBlock<I>,
+    shared: &Shared,
+    timeout: f64,
+    priority: Option<f32>,
+) -> OwnedReceiveStreamer<I>
+where
+    I: Copy,
+{
+    if let Some(priority) = priority {
+        let _ = set_thread_priority(priority, true);
+    }
+    let stats = &shared.stats;
+    let mut metadata = match ReceiveMetadata::try_new() {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            stats.errors.fetch_add(1, Ordering::Relaxed);
+            *shared.error.lock().unwrap() = Some(e);
+            shared.running.store(false, Ordering::Release);
+            return streamer;
+        }
+    };
+
+    while !shared.stop.load(Ordering::Acquire) {
+        let (block, dropped) = match producer.next_slot() {
+            Some(block) => (block, false),
+            None => (&mut scratch, true),
+        };
+
+        if let Err(e) = block.fill(&mut streamer, &mut metadata, timeout) {
+            stats.errors.fetch_add(1, Ordering::Relaxed);
+            *shared.error.lock().unwrap() = Some(e);
+            break;
+        }
+
+        if let Some(error) = block.metadata.error {
+            match error {
+                ReceiveErrorKind::Overflow => {
+                    stats.overflows.fetch_add(1, Ordering::Relaxed);
+                }
+                ReceiveErrorKind::Timeout => {
+                    stats.timeouts.fetch_add(1, Ordering::Relaxed);
+                }
+                _ => {
+                    stats.errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        let samples = block.samples();
+        if samples == 0 {
+            continue;
+        }
+
+        if dropped {
+            stats.dropped_blocks.fetch_add(1, Ordering::Relaxed);
+            stats
+                .dropped_samples
+                .fetch_add(samples as u64, Ordering::Relaxed);
+        } else {
+            stats.blocks.fetch_add(1, Ordering::Relaxed);
+            stats
+                .samples
+                .fetch_add(samples as u64, Ordering::Relaxed);
+            producer.commit();
+        }
+    }
+
+    let _ = streamer.send_command(&StreamCommand {
+        command_type: StreamCommandType::StopContinuous,
+        time: StreamTime::Now,
+    });
+    shared.running.store(false, Ordering::Release);
+    streamer
+}
+
+/// A block of samples received by a pipeline
+pub struct ReceiveBlock<I> {
+    /// Samples for all channels, with channel i in [i * block_size, (i + 1) * block_size)
+    data: Vec<I>,
+    /// Number of channels
+    num_channels: usize,
+    /// Maximum number of samples per channel
+    block_size: usize,
+    /// Metadata from the receive operation, including the number of valid samples per channel
+    metadata: ReceiveMetadataSnapshot,
+}
+
+impl<I> ReceiveBlock<I> {
+    fn new(num_channels: usize, block_size: usize) -> Self
+    where
+        I: Copy + Default,
+    {
+        ReceiveBlock {
+            data: vec![I::default(); num_channels * block_size],
+            num_channels,
+            block_size,
+            metadata: ReceiveMetadataSnapshot::default(),
+        }
+    }
+
+    /// Receives samples into this block
+    fn fill(
+        &mut self,
+        streamer: &mut ReceiveStreamer<'_, I>,
+        metadata: &mut ReceiveMetadata,
+        timeout: f64,
+    ) -> Result<(), Error> {
+        self.metadata = ReceiveMetadataSnapshot::default();
+        streamer.receive_planes(&mut self.data, self.block_size, metadata, timeout, false)?;
+        self.metadata = metadata.snapshot()?;
+        Ok(())
+    }
+
+    /// Returns the number of samples per channel in this block
+    pub fn samples(&self) -> usize {
+        self.metadata.samples
+    }
+
+    /// Returns the number of channels in this block
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    /// Returns the samples received on a channel
+    ///
+    /// The channel index is relative to the channels of the streamer, not the USRP.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if channel is not less than self.num_channels().
+    pub fn channel(&self, channel: usize) -> &[I] {
+        assert!(channel < self.num_channels, "Channel index out of range");
+        let start = channel * self.block_size;
+        &self.data[start..start + self.metadata.samples]
+    }
+
+    /// Returns the timestamp of the first sample in this block, if available
+    pub fn time_spec(&self) -> Option<&TimeSpec> {
+        self.metadata.time_spec.as_ref()
+    }
+
+    /// Returns true if the samples are at the beginning of a burst
+    pub fn start_of_burst(&self) -> bool {
+        self.metadata.start_of_burst
+    }
+
+    /// Returns true if the samples are at the end of a burst
+    pub fn end_of_burst(&self) -> bool {
+        self.metadata.end_of_burst
+    }
+
+    /// Returns all metadata from the receive operation that filled this block
+    pub fn metadata(&self) -> &ReceiveMetadataSnapshot {
+        &self.metadata
+    }
+}
+
+/// A reference to a received block, which is returned to the pipeline when dropped
+pub struct ReadGuard<'p, I> {
+    consumer: &'p mut Consumer<ReceiveBlock<I>>,
+}
+
+impl<I> Deref for ReadGuard<'_, I> {
+    type Target = ReceiveBlock<I>;
+
+    fn deref(&self) -> &Self::Target {
+        // A guard is only created when a block is available, and only the guard can release it
+        self.consumer
+            .front()
+            .expect("Read guard has no block")
+    }
+}
+
+impl<I> Drop for ReadGuard<'_, I> {
+    fn drop(&mut self) {
+        self.consumer.release();
+    }
+}
+
+/// Statistics about a receive pipeline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReceivePipelineStats {
+    /// Number of blocks made available to the consumer
+    pub blocks: u64,
+    /// Number of samples per channel made available to the consumer
+    pub samples: u64,
+    /// Number of blocks discarded because the consumer did not read blocks quickly enough
+    pub dropped_blocks: u64,
+    /// Number of samples per channel discarded because the consumer did not read blocks quickly
+    /// enough
+    pub dropped_samples: u64,
+    /// Number of overflows reported by the USRP
+    pub overflows: u64,
+    /// Number of receive operations that timed out
+    pub timeouts: u64,
+    /// Number of other errors
+    pub errors: u64,
+}
+
+/// State shared between a pipeline and its thread
+struct Shared {
+    /// Set to false by the pipeline thread when it exits
+    running: AtomicBool,
+    /// Set to true to request that the pipeline thread exit
+    stop: AtomicBool,
+    stats: Counters,
+    /// The error that caused the pipeline thread to exit, if any
+    error: Mutex<Option<Error>>,
+}
+
+#[derive(Default)]
+struct Counters {
+    blocks: AtomicU64,
+    samples: AtomicU64,
+    dropped_blocks: AtomicU64,
+    dropped_samples: AtomicU64,
+    overflows: AtomicU64,
+    timeouts: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Counters {
+    fn snapshot(&self) -> ReceivePipelineStats {
+        ReceivePipelineStats {
+            blocks: self.blocks.load(Ordering::Relaxed),
+            samples: self.samples.load(Ordering::Relaxed),
+            dropped_blocks: self.dropped_blocks.load(Ordering::Relaxed),
+            dropped_samples: self.dropped_samples.load(Ordering::Relaxed),
+            overflows: self.overflows.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,15 +1,22 @@
 use std::marker::PhantomData;
 use std::ptr;
+use std::sync::Arc;
 
 use crate::error::{check_status, Error};
 use crate::receive_metadata::ReceiveMetadata;
 use crate::stream::StreamCommand;
 use crate::usrp::Usrp;
+use crate::utils::plane_length;
+use num_complex::Complex;
 use std::os::raw::c_void;
 
 /// A streamer used to receive samples from a USRP
 ///
 /// The type parameter I is the type of sample that this streamer receives.
+///
+/// A streamer created with `Usrp::get_rx_stream` borrows the USRP. A streamer created with
+/// `Usrp::get_rx_stream_owned` holds a reference-counted pointer to the USRP instead, so it can
+/// be moved into other threads (see `OwnedReceiveStreamer`).
 #[derive(Debug)]
 pub struct ReceiveStreamer<'usrp, I> {
     /// Streamer handle
@@ -22,10 +29,20 @@ pub struct ReceiveStreamer<'usrp, I> {
     buffer_pointers: Vec<*mut c_void>,
     /// Link to the USRP that this streamer is associated with
     usrp: PhantomData<&'usrp Usrp>,
+    /// The USRP that this streamer keeps alive, for owned streamers
+    ///
+    /// This is dropped after the streamer handle is freed.
+    owner: Option<Arc<Usrp>>,
     /// Item type phantom data
     item_phantom: PhantomData<I>,
 }
 
+/// A receive streamer that keeps its USRP alive and does not borrow it
+///
+/// Owned streamers are created using `Usrp::get_rx_stream_owned`. They are `'static` and `Send`,
+/// so they can be moved into threads that outlive the code that opened the USRP.
+pub type OwnedReceiveStreamer<I> = ReceiveStreamer<'static, I>;
+
 impl<I> ReceiveStreamer<'_, I> {
     /// Creates a receive streamer with a null streamer handle (for internal use only)
     ///
@@ -35,6 +52,7 @@ impl<I> ReceiveStreamer<'_, I> {
             handle: ptr::null_mut(),
             buffer_pointers: Vec::new(),
             usrp: PhantomData,
+            owner: None,
             item_phantom: PhantomData,
         }
     }
@@ -47,10 +65,16 @@ impl<I> ReceiveStreamer<'_, I> {
             handle: rx_stream,
             buffer_pointers: Vec::with_capacity(cap),
             usrp: PhantomData,
+            owner: None,
             item_phantom: PhantomData,
         })
     }
 
+    /// Sets the USRP that this streamer keeps alive
+    pub(crate) fn set_owner(&mut self, owner: Arc<Usrp>) {
+        self.owner = Some(owner);
+    }
+
     /// Returns a reference to the streamer handle
     pub(crate) fn handle_mut(&mut self) -> &mut uhd_sys::uhd_rx_streamer_handle {
         &mut self.handle
@@ -70,15 +94,20 @@ impl<I> ReceiveStreamer<'_, I> {
 
     /// Returns the number of channels that this streamer is associated with
     pub fn num_channels(&self) -> usize {
+        self.try_num_channels().unwrap()
+    }
+
+    /// Returns the number of channels that this streamer is associated with, or an error if it
+    /// could not be read
+    pub fn try_num_channels(&self) -> Result<usize, Error> {
         let mut num_channels = 0usize;
         check_status(unsafe {
             uhd_sys::uhd_rx_streamer_num_channels(
                 self.handle,
                 &mut num_channels as *mut usize as *mut _,
             )
-        })
-        .unwrap();
-        num_channels
+        })?;
+        Ok(num_channels)
     }
 
     /// Receives samples from the USRP
@@ -100,14 +129,9 @@ impl<I> ReceiveStreamer<'_, I> {
         timeout: f64,
         one_packet: bool,
     ) -> Result<ReceiveMetadata, Error> {
-        let mut metadata = ReceiveMetadata::default();
-        let mut samples_received = 0usize;
+        let mut metadata = ReceiveMetadata::try_new()?;
 
-        // Initialize buffer_pointers
-        if self.buffer_pointers.is_empty() {
-            self.buffer_pointers
-                .resize(self.num_channels(), ptr::null_mut());
-        }
+        self.init_buffer_pointers()?;
         // Now buffer_pointers.len() is equal to self.num_channels().
         assert_eq!(
             buffers.len(),
@@ -122,6 +146,87 @@ impl<I> ReceiveStreamer<'_, I> {
             *entry = buffer.as_mut_ptr() as *mut c_void;
         }
 
+        self.receive_into_pointers(&mut metadata, buffer_length, timeout, one_packet)?;
+        Ok(metadata)
+    }
+
+    /// Receives samples from the USRP into one contiguous buffer that is split into equal-length
+    /// planes, one per channel
+    ///
+    /// buffer: The buffer where samples will be written. Samples for channel 0 are written
+    /// starting at index 0, samples for channel 1 are written starting at index
+    /// `buffer.len() / channels`, and so on.
+    ///
+    /// channels: The number of channels. This function returns an error if this is not equal to
+    /// self.num_channels(), or if buffer.len() is not a multiple of channels.
+    ///
+    /// timeout and one_packet have the same meaning as in [`receive`](#method.receive).
+    ///
+    /// The number of samples received (per channel) is available from the returned metadata.
+    /// If it is less than the plane length, the end of each plane is not modified.
+    pub fn receive_planar(
+        &mut self,
+        buffer: &mut [I],
+        channels: usize,
+        timeout: f64,
+        one_packet: bool,
+    ) -> Result<ReceiveMetadata, Error> {
+        let plane_length = plane_length(buffer.len(), channels, self.try_num_channels()?)?;
+        let mut metadata = ReceiveMetadata::try_new()?;
+        self.receive_planes(buffer, plane_length, &mut metadata, timeout, one_packet)?;
+        Ok(metadata)
+    }
+
+    /// Receives samples into one contiguous buffer that holds a plane of `plane_length` samples
+    /// for each channel, reusing an existing metadata object
+    ///
+    /// This function does not allocate, which makes it suitable for real-time receive loops.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if buffer.len() is not equal to plane_length * self.num_channels().
+    pub(crate) fn receive_planes(
+        &mut self,
+        buffer: &mut [I],
+        plane_length: usize,
+        metadata: &mut ReceiveMetadata,
+        timeout: f64,
+        one_packet: bool,
+    ) -> Result<(), Error> {
+        self.init_buffer_pointers()?;
+        assert_eq!(
+            buffer.len(),
+            plane_length * self.buffer_pointers.len(),
+            "Buffer length is not equal to plane length times number of channels"
+        );
+        let base = buffer.as_mut_ptr();
+        for (i, entry) in self.buffer_pointers.iter_mut().enumerate() {
+            // In bounds because of the length check above
+            *entry = unsafe { base.add(i * plane_length) } as *mut c_void;
+        }
+        self.receive_into_pointers(metadata, plane_length, timeout, one_packet)
+    }
+
+    /// Initializes buffer_pointers so that its length is equal to self.num_channels()
+    fn init_buffer_pointers(&mut self) -> Result<(), Error> {
+        if self.buffer_pointers.is_empty() {
+            let num_channels = self.try_num_channels()?;
+            self.buffer_pointers.resize(num_channels, ptr::null_mut());
+        }
+        Ok(())
+    }
+
+    /// Receives samples into the buffers that buffer_pointers currently points to
+    ///
+    /// Each buffer must have space for at least buffer_length samples.
+    fn receive_into_pointers(
+        &mut self,
+        metadata: &mut ReceiveMetadata,
+        buffer_length: usize,
+        timeout: f64,
+        one_packet: bool,
+    ) -> Result<(), Error> {
+        let mut samples_received = 0usize;
         check_status(unsafe {
             uhd_sys::uhd_rx_streamer_recv(
                 self.handle,
@@ -134,8 +239,7 @@ impl<I> ReceiveStreamer<'_, I> {
             )
         })?;
         metadata.set_samples(samples_received);
-
-        Ok(metadata)
+        Ok(())
     }
 
     /// Receives samples on a single channel with a timeout of 0.1 seconds and one_packet disabled
@@ -144,6 +248,51 @@ impl<I> ReceiveStreamer<'_, I> {
     }
 }
 
+impl ReceiveStreamer<'_, Complex<i8>> {
+    /// Receives `sc8` samples and converts them into unsigned 8-bit offset-binary form
+    ///
+    /// Each sample is an interleaved `[in-phase, quadrature]` pair of bytes, where 128 represents
+    /// zero (the format that RTL-SDR devices produce).
+    ///
+    /// The buffers, timeout, and one_packet arguments have the same meaning as in
+    /// [`receive`](#method.receive), and this function panics under the same conditions.
+    pub fn receive_offset_binary(
+        &mut self,
+        buffers: &mut [&mut [[u8; 2]]],
+        timeout: f64,
+        one_packet: bool,
+    ) -> Result<ReceiveMetadata, Error> {
+        let mut metadata = ReceiveMetadata::try_new()?;
+
+        self.init_buffer_pointers()?;
+        assert_eq!(
+            buffers.len(),
+            self.buffer_pointers.len(),
+            "Number of buffers is not equal to this streamer's number of channels"
+        );
+        let buffer_length = check_equal_buffer_lengths(buffers);
+
+        // [u8; 2] has the same size and alignment as Complex<i8>
+        for (entry, buffer) in self.buffer_pointers.iter_mut().zip(buffers.iter_mut()) {
+            *entry = buffer.as_mut_ptr() as *mut c_void;
+        }
+
+        self.receive_into_pointers(&mut metadata, buffer_length, timeout, one_packet)?;
+        for buffer in buffers.iter_mut() {
+            sc8_to_offset_binary(&mut buffer[..metadata.samples()]);
+        }
+        Ok(metadata)
+    }
+}
+
+/// Converts samples in place from two's complement to offset binary
+fn sc8_to_offset_binary(samples: &mut [[u8; 2]]) {
+    for sample in samples {
+        sample[0] ^= 0x80;
+        sample[1] ^= 0x80;
+    }
+}
+
 /// Checks that all provided buffers have the same length. Returns the length of the buffers,
 /// or 0 if there are no buffers. Panics if the buffer lengths are not equal.
 fn check_equal_buffer_lengths<I>(buffers: &mut [&mut [I]]) -> usize {
@@ -176,3 +325,25 @@ impl<I> Drop for ReceiveStreamer<'_, I> {
 // self, which enforces single-thread access.
 unsafe impl<I> Send for ReceiveStreamer<'_, I> {}
 unsafe impl<I> Sync for ReceiveStreamer<'_, I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{sc8_to_offset_binary, OwnedReceiveStreamer};
+    use crate::OwnedTransmitStreamer;
+    use num_complex::Complex32;
+
+    fn assert_send_static<T: Send + 'static>() {}
+
+    #[test]
+    fn owned_streamers_send_static() {
+        assert_send_static::<OwnedReceiveStreamer<Complex32>>();
+        assert_send_static::<OwnedTransmitStreamer<Complex32>>();
+    }
+
+    #[test]
+    fn offset_binary() {
+        let mut samples = [[0u8, 0x7f], [0xff, 0x80]];
+        sc8_to_offset_binary(&mut samples);
+        assert_eq!([[0x80, 0xff], [0x7f, 0x00]], samples);
+    }
+}
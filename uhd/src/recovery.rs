@@ -0,0 +1,457 @@
+use crate::error::Error;
+use crate::receive_metadata::{ReceiveErrorKind, ReceiveMetadata};
+use crate::receive_streamer::ReceiveStreamer;
+use crate::stream::StreamCommand;
+use crate::TimeSpec;
+
+/// Settings and state for receiving with gap detection and recovery
+///
+/// This is used with `ReceiveStreamer::receive_with_recovery`. It remembers the expected time of
+/// the next sample, so that when a receive operation returns samples with a later timestamp
+/// (usually after an overflow or sequence error), the number of lost samples can be calculated.
+///
+/// The same Recovery must be used for all receive operations on a stream.
+#[derive(Debug, Clone)]
+pub struct Recovery<I> {
+    /// Sample rate in samples/second
+    sample_rate: f64,
+    /// If true, lost samples are replaced with I::default()
+    zero_fill: bool,
+    /// Maximum number of samples (per channel) to fill for one gap
+    max_fill: u64,
+    /// Stream command to send after a broken chain or late command error
+    restart_command: Option<StreamCommand>,
+    /// Expected time of the next sample, if known
+    next_time: Option<TimeSpec>,
+    /// Number of fill samples that still need to be written
+    pending_fill: u64,
+    /// Samples received after a gap that still need to be written (after the fill samples),
+    /// one vector per channel
+    carry: Vec<Vec<I>>,
+    /// Index in each carry vector of the next sample to write
+    carry_position: usize,
+}
+
+impl<I> Recovery<I> {
+    /// Creates a recovery state for a stream with the provided sample rate (samples/second)
+    ///
+    /// By default, lost samples are not replaced and no stream commands are sent. If zero filling
+    /// is enabled, gaps of up to one second are filled.
+    pub fn new(sample_rate: f64) -> Self {
+        Recovery {
+            sample_rate,
+            zero_fill: false,
+            max_fill: sample_rate as u64,
+            restart_command: None,
+            next_time: None,
+            pending_fill: 0,
+            carry: Vec::new(),
+            carry_position: 0,
+        }
+    }
+
+    /// Enables or disables replacing lost samples with zeros (`I::default()`)
+    ///
+    /// When this is enabled, the samples written to the buffers stay aligned with the USRP's
+    /// clock: sample n always has the time of the first sample plus n / sample_rate.
+    pub fn zero_fill(self, zero_fill: bool) -> Self {
+        Recovery { zero_fill, ..self }
+    }
+
+    /// Sets the maximum number of samples (per channel) to fill for one gap
+    ///
+    /// A gap longer than this is still reported as a `ReceiveEvent::Gap`, but nothing is filled
+    /// and the samples after it are written immediately. This keeps a large timestamp jump from
+    /// producing an unbounded number of fill samples.
+    pub fn max_fill(self, max_fill: u64) -> Self {
+        Recovery { max_fill, ..self }
+    }
+
+    /// Sets a stream command to send after a broken chain or late command error
+    ///
+    /// This is normally a `StartContinuous` command with `StreamTime::Now`.
+    pub fn restart_command(self, command: StreamCommand) -> Self {
+        Recovery {
+            restart_command: Some(command),
+            ..self
+        }
+    }
+
+    /// Returns the expected time of the next sample, if known
+    pub fn next_time(&self) -> Option<&TimeSpec> {
+        self.next_time.as_ref()
+    }
+
+    /// Forgets the expected time of the next sample and discards any pending fill samples
+    ///
+    /// This should be called after intentionally stopping and restarting the stream.
+    pub fn reset(&mut self) {
+        self.next_time = None;
+        self.pending_fill = 0;
+        self.carry.clear();
+        self.carry_position = 0;
+    }
+
+    /// Returns the number of carried samples per channel that have not been written
+    fn carry_remaining(&self) -> usize {
+        self.carry
+            .first()
+            .map(|channel| channel.len() - self.carry_position)
+            .unwrap_or(0)
+    }
+
+    /// Writes pending fill samples and carried samples into buffers, starting at index written
+    ///
+    /// Returns the new number of samples written.
+    fn flush(&mut self, buffers: &mut [&mut [I]], mut written: usize, length: usize) -> usize
+    where
+        I: Copy + Default,
+    {
+        if self.pending_fill != 0 {
+            let count = self.pending_fill.min((length - written) as u64) as usize;
+            for buffer in buffers.iter_mut() {
+                for sample in &mut buffer[written..written + count] {
+                    *sample = I::default();
+                }
+            }
+            written += count;
+            self.pending_fill -= count as u64;
+        }
+        if self.pending_fill == 0 && self.carry_remaining() != 0 {
+            let count = self.carry_remaining().min(length - written);
+            for (buffer, carry) in buffers.iter_mut().zip(self.carry.iter()) {
+                buffer[written..written + count]
+                    .copy_from_slice(&carry[self.carry_position..self.carry_position + count]);
+            }
+            written += count;
+            self.carry_position += count;
+            if self.carry_remaining() == 0 {
+                for channel in self.carry.iter_mut() {
+                    channel.clear();
+                }
+                self.carry_position = 0;
+            }
+        }
+        written
+    }
+
+    /// Returns true if fill or carried samples are waiting to be written
+    fn has_pending(&self) -> bool {
+        self.pending_fill != 0 || self.carry_remaining() != 0
+    }
+
+    /// Compares the timestamp of received samples with the expected time, and updates the
+    /// expected time of the next sample
+    ///
+    /// Returns the number of samples (per channel) lost before the received samples, and the
+    /// event to report if the timestamp was not the expected time.
+    fn check_time(
+        &mut self,
+        time: Option<TimeSpec>,
+        received: usize,
+    ) -> (u64, Option<ReceiveEvent>) {
+        let (lost, event) = match (self.next_time, time) {
+            (Some(expected), Some(actual)) => {
+                let difference = (actual - expected).to_ticks(self.sample_rate);
+                if difference > 0 {
+                    let lost = difference as u64;
+                    let event = ReceiveEvent::Gap {
+                        at: expected,
+                        samples_lost: lost,
+                    };
+                    (lost, Some(event))
+                } else if difference < 0 {
+                    let event = ReceiveEvent::TimeMovedBackward {
+                        expected,
+                        actual,
+                        samples: difference.unsigned_abs(),
+                    };
+                    (0, Some(event))
+                } else {
+                    (0, None)
+                }
+            }
+            _ => (0, None),
+        };
+        self.next_time = time
+            .or(self.next_time)
+            .map(|time| time + TimeSpec::from_ticks(received as i64, self.sample_rate));
+        (lost, event)
+    }
+
+    /// Returns the number of fill samples to write for a gap of lost samples
+    fn fill_count(&self, lost: u64) -> u64 {
+        if self.zero_fill && lost <= self.max_fill {
+            lost
+        } else {
+            0
+        }
+    }
+
+    /// Forgets the expected time of the next sample after the restart command was sent
+    ///
+    /// The first samples after a restart can have any later timestamp, so they are not reported
+    /// as a gap or filled.
+    fn restarted(&mut self) {
+        self.next_time = None;
+    }
+}
+
+/// Something that happened during a receive operation with recovery
+#[derive(Debug, Clone)]
+pub enum ReceiveEvent {
+    /// Samples were lost
+    Gap {
+        /// The time of the first lost sample
+        at: TimeSpec,
+        /// The number of samples (per channel) that were lost
+        samples_lost: u64,
+    },
+    /// The timestamp of the received samples was earlier than expected
+    ///
+    /// The samples are written normally, and the samples after them are expected to follow them.
+    TimeMovedBackward {
+        /// The time that the first received sample was expected to have
+        expected: TimeSpec,
+        /// The timestamp that the first received sample actually has
+        actual: TimeSpec,
+        /// The number of samples (per channel) that the timestamp moved backwards
+        samples: u64,
+    },
+    /// The USRP reported an error
+    Error(ReceiveErrorKind),
+    /// The restart stream command was sent after an error of the provided kind
+    Restarted(ReceiveErrorKind),
+}
+
+/// The result of a receive operation with recovery
+#[derive(Debug)]
+pub struct RecoveredReceive {
+    /// Metadata from the underlying receive operation, or None if this call only wrote samples
+    /// left over from a previous gap
+    pub metadata: Option<ReceiveMetadata>,
+    /// The number of samples (per channel) written to the buffers, including fill samples
+    pub samples: usize,
+    /// Events detected during this call
+    pub events: Vec<ReceiveEvent>,
+}
+
+impl<I> ReceiveStreamer<'_, I>
+where
+    I: Copy + Default,
+{
+    /// Receives samples, detecting and optionally recovering from discontinuities
+    ///
+    /// buffers: One or more buffers (one per channel) where the samples will be written. All
+    /// buffers should have the same length. This function will panic if the number of buffers is
+    /// not equal to self.num_channels(), or if not all buffers have the same length.
+    ///
+    /// timeout: The timeout for the receive operation, in seconds
+    ///
+    /// recovery: The gap detection state for this stream
+    ///
+    /// When the timestamp of the received samples is later than expected, a `ReceiveEvent::Gap`
+    /// reports the exact number of samples lost. If zero filling is enabled and the gap is not
+    /// longer than the maximum fill length, that many fill samples are written before the
+    /// received samples. Fill and received samples that do not
+    /// fit in the buffers are written by the next call. When the timestamp is earlier than
+    /// expected, a `ReceiveEvent::TimeMovedBackward` is reported and nothing is filled.
+    ///
+    /// If a restart command is configured, it is sent after a broken chain or late command
+    /// error. The samples received after a restart are not compared with the expected time, so
+    /// the time that the stream was stopped is not reported as a gap or filled.
+    pub fn receive_with_recovery(
+        &mut self,
+        buffers: &mut [&mut [I]],
+        timeout: f64,
+        recovery: &mut Recovery<I>,
+    ) -> Result<RecoveredReceive, Error> {
+        let length = buffers.first().map(|buffer| buffer.len()).unwrap_or(0);
+        let mut events = Vec::new();
+
+        let mut written = recovery.flush(buffers, 0, length);
+        if written == length || recovery.has_pending() {
+            return Ok(RecoveredReceive {
+                metadata: None,
+                samples: written,
+                events,
+            });
+        }
+
+        let metadata = {
+            let mut remaining: Vec<&mut [I]> = buffers
+                .iter_mut()
+                .map(|buffer| &mut buffer[written..])
+                .collect();
+            self.receive(&mut remaining, timeout, false)?
+        };
+        let received = metadata.samples();
+
+        if let Some(error) = metadata.try_last_error()? {
+            let kind = error.kind();
+            match kind {
+                ReceiveErrorKind::Timeout => {}
+                ReceiveErrorKind::BrokenChain | ReceiveErrorKind::LateCommand => {
+                    events.push(ReceiveEvent::Error(kind));
+                    if let Some(command) = recovery.restart_command.as_ref() {
+                        self.send_command(command)?;
+                        recovery.restarted();
+                        events.push(ReceiveEvent::Restarted(kind));
+                    }
+                }
+                _ => events.push(ReceiveEvent::Error(kind)),
+            }
+        }
+
+        if received != 0 {
+            let (lost, event) = recovery.check_time(metadata.try_time_spec()?, received);
+            events.extend(event);
+
+            let fill = recovery.fill_count(lost);
+            if fill > 0 {
+                // Move the received samples out of the way, then write fill samples followed by
+                // the received samples
+                recovery.carry.resize_with(buffers.len(), Vec::new);
+                for (buffer, carry) in buffers.iter().zip(recovery.carry.iter_mut()) {
+                    carry.extend_from_slice(&buffer[written..written + received]);
+                }
+                recovery.pending_fill = fill;
+                written = recovery.flush(buffers, written, length);
+            } else {
+                written += received;
+            }
+        }
+
+        Ok(RecoveredReceive {
+            metadata: Some(metadata),
+            samples: written,
+            events,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flush_fill_then_carry() {
+        let mut recovery = Recovery::<i16>::new(1e6).zero_fill(true);
+        recovery.pending_fill = 3;
+        recovery.carry = vec![vec![7, 8, 9]];
+        let mut buffer = [1i16; 4];
+        let written = recovery.flush(&mut [&mut buffer[..]], 0, 4);
+        assert_eq!(4, written);
+        assert_eq!([0, 0, 0, 7], buffer);
+        assert!(recovery.has_pending());
+
+        let written = recovery.flush(&mut [&mut buffer[..]], 0, 4);
+        assert_eq!(2, written);
+        assert_eq!([8, 9], buffer[..2]);
+        assert!(!recovery.has_pending());
+    }
+
+    /// Returns the expected time of the next sample, in ticks of 1 millisecond
+    fn next_ticks(recovery: &Recovery<i16>) -> Option<i64> {
+        recovery.next_time().map(|time| time.to_ticks(1000.0))
+    }
+
+    #[test]
+    fn gap() {
+        let mut recovery = Recovery::<i16>::new(1000.0);
+        let (lost, event) = recovery.check_time(Some(TimeSpec::from_ticks(100, 1000.0)), 10);
+        assert_eq!(0, lost);
+        assert!(event.is_none());
+        assert_eq!(Some(110), next_ticks(&recovery));
+
+        let (lost, event) = recovery.check_time(Some(TimeSpec::from_ticks(110, 1000.0)), 10);
+        assert_eq!(0, lost);
+        assert!(event.is_none());
+
+        // Samples 120 through 124 were lost
+        let (lost, event) = recovery.check_time(Some(TimeSpec::from_ticks(125, 1000.0)), 10);
+        assert_eq!(5, lost);
+        match event {
+            Some(ReceiveEvent::Gap { at, samples_lost }) => {
+                assert_eq!(120, at.to_ticks(1000.0));
+                assert_eq!(5, samples_lost);
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+        assert_eq!(Some(135), next_ticks(&recovery));
+
+        // Without a timestamp, the samples are assumed to follow the previous samples
+        let (lost, event) = recovery.check_time(None, 10);
+        assert_eq!(0, lost);
+        assert!(event.is_none());
+        assert_eq!(Some(145), next_ticks(&recovery));
+    }
+
+    #[test]
+    fn gap_longer_than_max_fill() {
+        let mut recovery = Recovery::<i16>::new(1000.0).zero_fill(true).max_fill(50);
+        recovery.check_time(Some(TimeSpec::from_ticks(100, 1000.0)), 10);
+
+        let (lost, event) = recovery.check_time(Some(TimeSpec::from_ticks(160, 1000.0)), 10);
+        assert_eq!(50, lost);
+        assert!(event.is_some());
+        assert_eq!(50, recovery.fill_count(lost));
+
+        // The gap is still reported, but nothing is filled
+        let (lost, event) = recovery.check_time(Some(TimeSpec::from_ticks(10_000, 1000.0)), 10);
+        assert_eq!(9830, lost);
+        match event {
+            Some(ReceiveEvent::Gap { at, samples_lost }) => {
+                assert_eq!(170, at.to_ticks(1000.0));
+                assert_eq!(9830, samples_lost);
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+        assert_eq!(0, recovery.fill_count(lost));
+        assert!(!recovery.has_pending());
+        // The next samples are expected to follow the received samples
+        assert_eq!(Some(10_010), next_ticks(&recovery));
+
+        // The default maximum is one second
+        let recovery = Recovery::<i16>::new(1000.0).zero_fill(true);
+        assert_eq!(1000, recovery.fill_count(1000));
+        assert_eq!(0, recovery.fill_count(1001));
+        assert_eq!(0, Recovery::<i16>::new(1000.0).fill_count(5));
+    }
+
+    #[test]
+    fn time_moved_backward() {
+        let mut recovery = Recovery::<i16>::new(1000.0).zero_fill(true);
+        recovery.check_time(Some(TimeSpec::from_ticks(100, 1000.0)), 10);
+        let (lost, event) = recovery.check_time(Some(TimeSpec::from_ticks(90, 1000.0)), 10);
+        assert_eq!(0, lost);
+        match event {
+            Some(ReceiveEvent::TimeMovedBackward {
+                expected,
+                actual,
+                samples,
+            }) => {
+                assert_eq!(110, expected.to_ticks(1000.0));
+                assert_eq!(90, actual.to_ticks(1000.0));
+                assert_eq!(20, samples);
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+        // Later samples are expected to follow the samples with the earlier timestamp
+        assert_eq!(Some(100), next_ticks(&recovery));
+        assert!(!recovery.has_pending());
+    }
+
+    #[test]
+    fn restart() {
+        let mut recovery = Recovery::<i16>::new(1000.0).zero_fill(true);
+        recovery.check_time(Some(TimeSpec::from_ticks(100, 1000.0)), 10);
+        recovery.restarted();
+        assert_eq!(None, recovery.next_time());
+        // The stream was stopped for a long time, but that is not a gap
+        let (lost, event) = recovery.check_time(Some(TimeSpec::new(60, 0.0)), 10);
+        assert_eq!(0, lost);
+        assert!(event.is_none());
+        assert_eq!(Some(60_010), next_ticks(&recovery));
+    }
+}
@@ -0,0 +1,195 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A fixed-capacity single-producer, single-consumer ring of preallocated slots
+///
+/// Slots are never allocated or freed after construction. The producer writes into the next free
+/// slot in place and then commits it, and the consumer reads the oldest committed slot in place
+/// and then releases it.
+struct RingBuffer<T> {
+    slots: Box<[UnsafeCell<T>]>,
+    /// Number of slots that have been released by the consumer (wrapping)
+    head: AtomicUsize,
+    /// Number of slots that have been committed by the producer (wrapping)
+    ///
+    /// Invariant: tail.wrapping_sub(head) <= slots.len()
+    tail: AtomicUsize,
+}
+
+// Thread safety: The producer only accesses slots in [tail, head + capacity) and the consumer
+// only accesses slots in [head, tail). The Producer and Consumer types are not Clone, so there is
+// at most one of each.
+unsafe impl<T: Send> Sync for RingBuffer<T> {}
+
+impl<T> RingBuffer<T> {
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn len(&self) -> usize {
+        self.tail
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    fn slot(&self, position: usize) -> *mut T {
+        self.slots[position % self.capacity()].get()
+    }
+}
+
+/// Creates a ring buffer containing the provided slots, and returns its two halves
+///
+/// # Panics
+///
+/// This function panics if slots is empty.
+pub(crate) fn ring_buffer<T>(slots: Vec<T>) -> (Producer<T>, Consumer<T>) {
+    assert!(!slots.is_empty(), "Ring buffer must have at least one slot");
+    let ring = Arc::new(RingBuffer {
+        slots: slots.into_iter().map(UnsafeCell::new).collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+    (
+        Producer {
+            ring: Arc::clone(&ring),
+        },
+        Consumer { ring },
+    )
+}
+
+/// The writing half of a ring buffer
+pub(crate) struct Producer<T> {
+    ring: Arc<RingBuffer<T>>,
+}
+
+impl<T> Producer<T> {
+    /// Returns the next free slot, or None if all slots are waiting to be read
+    ///
+    /// The slot does not become visible to the consumer until commit() is called.
+    pub fn next_slot(&mut self) -> Option<&mut T> {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let head = ring.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == ring.capacity() {
+            None
+        } else {
+            // The consumer does not access this slot until it is committed
+            Some(unsafe { &mut *ring.slot(tail) })
+        }
+    }
+
+    /// Makes the slot returned by the last call to next_slot() available to the consumer
+    ///
+    /// This function must only be called after next_slot() returned Some.
+    pub fn commit(&mut self) {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        debug_assert!(tail.wrapping_sub(ring.head.load(Ordering::Acquire)) < ring.capacity());
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+    }
+}
+
+/// The reading half of a ring buffer
+pub(crate) struct Consumer<T> {
+    ring: Arc<RingBuffer<T>>,
+}
+
+impl<T> Consumer<T> {
+    /// Returns the oldest committed slot, or None if no slots are available
+    pub fn front(&self) -> Option<&T> {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        let tail = ring.tail.load(Ordering::Acquire);
+        if head == tail {
+            None
+        } else {
+            // The producer does not access this slot until it is released
+            Some(unsafe { &*ring.slot(head) })
+        }
+    }
+
+    /// Returns the oldest slot to the producer
+    ///
+    /// This function must only be called after front() returned Some.
+    pub fn release(&mut self) {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        debug_assert!(head != ring.tail.load(Ordering::Acquire));
+        ring.head.store(head.wrapping_add(1), Ordering::Release);
+    }
+
+    /// Returns the number of committed slots waiting to be read
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ring_buffer;
+    use std::thread;
+
+    #[test]
+    fn fill_and_drain() {
+        let (mut producer, mut consumer) = ring_buffer(vec![0u32; 3]);
+        assert!(consumer.front().is_none());
+        for value in 1..=3 {
+            *producer.next_slot().unwrap() = value;
+            producer.commit();
+        }
+        // Full
+        assert!(producer.next_slot().is_none());
+        assert_eq!(3, consumer.len());
+
+        assert_eq!(Some(&1), consumer.front());
+        consumer.release();
+        *producer.next_slot().unwrap() = 4;
+        producer.commit();
+
+        for expected in 2..=4 {
+            assert_eq!(Some(&expected), consumer.front());
+            consumer.release();
+        }
+        assert!(consumer.front().is_none());
+        assert_eq!(0, consumer.len());
+    }
+
+    #[test]
+    fn uncommitted_slot_not_visible() {
+        let (mut producer, consumer) = ring_buffer(vec![0u32; 2]);
+        *producer.next_slot().unwrap() = 7;
+        assert!(consumer.front().is_none());
+        producer.commit();
+        assert_eq!(Some(&7), consumer.front());
+    }
+
+    #[test]
+    fn threads_preserve_order() {
+        const COUNT: u64 = 100_000;
+        let (mut producer, mut consumer) = ring_buffer(vec![0u64; 8]);
+        let writer = thread::spawn(move || {
+            let mut next = 0;
+            while next < COUNT {
+                if let Some(slot) = producer.next_slot() {
+                    *slot = next;
+                    producer.commit();
+                    next += 1;
+                } else {
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < COUNT {
+            if let Some(value) = consumer.front() {
+                assert_eq!(expected, *value);
+                consumer.release();
+                expected += 1;
+            } else {
+                thread::yield_now();
+            }
+        }
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+use crate::error::Error;
+use crate::receive_metadata::{ReceiveMetadata, ReceiveMetadataSnapshot};
+use crate::TimeSpec;
+
+/// Tracks the device time of every sample in a receive stream
+///
+/// A SampleClock is updated with the metadata from each receive operation on a stream, in order.
+/// It numbers all received samples (per channel) starting at 0, and remembers the device time of
+/// one anchor sample. The time of any other sample is calculated from the number of samples
+/// between it and the anchor.
+///
+/// When a receive operation reports a timestamp that does not match the number of samples
+/// received since the anchor (usually after an overflow), the update returns a `TimestampJump`
+/// and the first sample of that receive operation becomes the new anchor.
+///
+/// Example:
+///
+/// ```no_run
+/// # use uhd::{ReceiveStreamer, SampleClock};
+/// # use num_complex::Complex32;
+/// # fn run(streamer: &mut ReceiveStreamer<Complex32>) -> Result<(), uhd::Error> {
+/// let mut clock = SampleClock::new(1e6);
+/// let mut buffer = vec![Complex32::default(); 4096];
+/// loop {
+///     let metadata = streamer.receive_simple(&mut buffer)?;
+///     if let Some(jump) = clock.update(&metadata)? {
+///         println!("Lost {} samples at {}", jump.samples, jump.expected);
+///     }
+///     // Device time of the 100th sample in the buffer
+///     let time = clock.buffer_time(100);
+/// #   let _ = time;
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SampleClock {
+    /// Sample rate in samples/second
+    sample_rate: f64,
+    /// Index and time of the anchor sample, if a timestamp has been received
+    anchor: Option<(u64, TimeSpec)>,
+    /// Index of the first sample of the most recent receive operation
+    buffer_start: u64,
+    /// Index of the next sample that will be received
+    next_index: u64,
+}
+
+/// A difference between the timestamp of a received sample and the time expected from the
+/// number of samples received
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimestampJump {
+    /// The index of the first sample with the new timestamp
+    pub index: u64,
+    /// The time that the sample was expected to have
+    pub expected: TimeSpec,
+    /// The timestamp that the sample actually has
+    pub actual: TimeSpec,
+    /// The difference between the actual and expected times, in samples
+    ///
+    /// This is positive if samples were lost, and negative if the timestamp moved backwards.
+    pub samples: i64,
+}
+
+impl SampleClock {
+    /// Creates a clock for a stream with the provided sample rate (samples/second)
+    pub fn new(sample_rate: f64) -> Self {
+        SampleClock {
+            sample_rate,
+            anchor: None,
+            buffer_start: 0,
+            next_index: 0,
+        }
+    }
+
+    /// Returns the sample rate in samples/second
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Updates the clock with the metadata from a receive operation
+    ///
+    /// This returns a TimestampJump if the timestamp in the metadata does not match the number
+    /// of samples received, or an error if the metadata could not be read.
+    pub fn update(&mut self, metadata: &ReceiveMetadata) -> Result<Option<TimestampJump>, Error> {
+        let time = metadata.try_time_spec()?;
+        Ok(self.advance(time, metadata.samples()))
+    }
+
+    /// Updates the clock with a metadata snapshot from a receive operation
+    ///
+    /// This returns a TimestampJump if the timestamp in the metadata does not match the number
+    /// of samples received.
+    pub fn update_snapshot(&mut self, metadata: &ReceiveMetadataSnapshot) -> Option<TimestampJump> {
+        self.advance(metadata.time_spec, metadata.samples)
+    }
+
+    /// Records a receive operation that returned samples samples, with the provided timestamp
+    fn advance(&mut self, time: Option<TimeSpec>, samples: usize) -> Option<TimestampJump> {
+        self.buffer_start = self.next_index;
+        self.next_index += samples as u64;
+        if samples == 0 {
+            // Timestamps of empty receive operations (usually timeouts) are not meaningful
+            return None;
+        }
+        let actual = time?;
+        let jump = match self.time_of(self.buffer_start) {
+            Some(expected) => {
+                let difference = (actual - expected).to_ticks(self.sample_rate);
+                if difference != 0 {
+                    Some(TimestampJump {
+                        index: self.buffer_start,
+                        expected,
+                        actual,
+                        samples: difference,
+                    })
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+        if self.anchor.is_none() || jump.is_some() {
+            self.anchor = Some((self.buffer_start, actual));
+        }
+        jump
+    }
+
+    /// Returns the device time of the sample with the provided index
+    ///
+    /// Samples are numbered from 0, starting with the first sample received after this clock
+    /// was created or reset. For samples received before the most recent timestamp jump, this
+    /// returns the time based on the timestamps after the jump.
+    ///
+    /// This returns None if no timestamps have been received.
+    pub fn time_of(&self, index: u64) -> Option<TimeSpec> {
+        let (anchor_index, anchor_time) = self.anchor?;
+        let offset = index as i64 - anchor_index as i64;
+        Some(anchor_time + TimeSpec::from_ticks(offset, self.sample_rate))
+    }
+
+    /// Returns the index of the sample at the provided device time, rounded to the nearest
+    /// sample
+    ///
+    /// The result is negative if the time is before the first sample. This returns None if no
+    /// timestamps have been received.
+    pub fn index_at(&self, time: TimeSpec) -> Option<i64> {
+        let (anchor_index, anchor_time) = self.anchor?;
+        Some(anchor_index as i64 + (time - anchor_time).to_ticks(self.sample_rate))
+    }
+
+    /// Returns the device time of a sample in the buffer from the most recent update
+    ///
+    /// offset is the index of the sample in the buffer. This returns None if no timestamps have
+    /// been received.
+    pub fn buffer_time(&self, offset: usize) -> Option<TimeSpec> {
+        self.time_of(self.buffer_start + offset as u64)
+    }
+
+    /// Returns the offset in the buffer from the most recent update of the sample at the
+    /// provided device time, rounded to the nearest sample
+    ///
+    /// The result may be negative or past the end of the buffer. This returns None if no
+    /// timestamps have been received.
+    pub fn buffer_offset(&self, time: TimeSpec) -> Option<i64> {
+        self.index_at(time)
+            .map(|index| index - self.buffer_start as i64)
+    }
+
+    /// Returns the index of the first sample in the buffer from the most recent update
+    pub fn buffer_start(&self) -> u64 {
+        self.buffer_start
+    }
+
+    /// Returns the index of the next sample that will be received
+    pub fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    /// Returns the expected device time of the next sample that will be received, if known
+    pub fn next_time(&self) -> Option<TimeSpec> {
+        self.time_of(self.next_index)
+    }
+
+    /// Forgets all timestamps and starts numbering samples from 0 again
+    ///
+    /// This should be called after intentionally stopping and restarting the stream.
+    pub fn reset(&mut self) {
+        self.anchor = None;
+        self.buffer_start = 0;
+        self.next_index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(seconds: i64, fraction: f64, samples: usize) -> ReceiveMetadataSnapshot {
+        ReceiveMetadataSnapshot {
+            samples,
+            time_spec: Some(TimeSpec::new(seconds, fraction)),
+            ..ReceiveMetadataSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn continuous() {
+        let mut clock = SampleClock::new(1000.0);
+        assert_eq!(None, clock.update_snapshot(&snapshot(5, 0.0, 100)));
+        assert_eq!(None, clock.update_snapshot(&snapshot(5, 0.1, 950)));
+        assert_eq!(100, clock.buffer_start());
+        assert_eq!(1050, clock.next_index());
+        assert_eq!(Some(TimeSpec::new(6, 0.05)), clock.next_time());
+        assert_eq!(Some(TimeSpec::new(6, 0.0)), clock.buffer_time(900));
+        assert_eq!(Some(900), clock.buffer_offset(TimeSpec::new(6, 0.0)));
+        assert_eq!(Some(-5), clock.index_at(TimeSpec::new(4, 0.995)));
+    }
+
+    #[test]
+    fn missing_timestamp() {
+        let mut clock = SampleClock::new(1000.0);
+        assert_eq!(None, clock.update_snapshot(&snapshot(1, 0.0, 10)));
+        let untimed = ReceiveMetadataSnapshot {
+            samples: 10,
+            ..ReceiveMetadataSnapshot::default()
+        };
+        assert_eq!(None, clock.update_snapshot(&untimed));
+        assert_eq!(Some(TimeSpec::new(1, 0.01)), clock.buffer_time(0));
+    }
+
+    #[test]
+    fn jump_forward_and_back() {
+        let mut clock = SampleClock::new(1e6);
+        clock.update_snapshot(&snapshot(0, 0.5, 1000));
+        let jump = clock.update_snapshot(&snapshot(0, 0.502, 1000)).unwrap();
+        assert_eq!(1000, jump.index);
+        assert_eq!(TimeSpec::new(0, 0.501), jump.expected);
+        assert_eq!(1000, jump.samples);
+        // Times are now based on the new timestamp
+        assert_eq!(Some(TimeSpec::new(0, 0.502)), clock.buffer_time(0));
+
+        let jump = clock.update_snapshot(&snapshot(0, 0.5025, 10)).unwrap();
+        assert_eq!(-500, jump.samples);
+    }
+
+    #[test]
+    fn empty_receive_ignored() {
+        let mut clock = SampleClock::new(1000.0);
+        clock.update_snapshot(&snapshot(3, 0.0, 10));
+        assert_eq!(None, clock.update_snapshot(&snapshot(0, 0.0, 0)));
+        assert_eq!(None, clock.update_snapshot(&snapshot(3, 0.01, 10)));
+        clock.reset();
+        assert_eq!(None, clock.time_of(0));
+        assert_eq!(0, clock.next_index());
+    }
+}
@@ -0,0 +1,113 @@
+use std::ptr;
+
+use crate::error::{check_status, Error};
+use crate::utils::copy_string;
+
+/// A value read from a USRP sensor
+pub struct SensorValue {
+    /// Handle to C++ object
+    handle: uhd_sys::uhd_sensor_value_handle,
+}
+
+/// The type of data that a sensor reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorType {
+    Boolean,
+    Integer,
+    RealNumber,
+    String,
+}
+
+impl SensorValue {
+    /// Creates an empty sensor value, returning an error if UHD cannot allocate it
+    pub(crate) fn try_new() -> Result<Self, Error> {
+        let mut handle: uhd_sys::uhd_sensor_value_handle = ptr::null_mut();
+        check_status(unsafe { uhd_sys::uhd_sensor_value_make(&mut handle) })?;
+        Ok(SensorValue { handle })
+    }
+
+    /// Returns the name of the sensor
+    pub fn name(&self) -> Result<String, Error> {
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_sensor_value_name(self.handle, buffer, length as _)
+        })
+    }
+
+    /// Returns the value of the sensor, formatted as a string
+    pub fn value(&self) -> Result<String, Error> {
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_sensor_value_value(self.handle, buffer, length as _)
+        })
+    }
+
+    /// Returns the unit of the value (or, for boolean sensors, the string that describes the
+    /// value)
+    pub fn unit(&self) -> Result<String, Error> {
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_sensor_value_unit(self.handle, buffer, length as _)
+        })
+    }
+
+    /// Returns the type of data that this sensor reports
+    pub fn data_type(&self) -> Result<SensorType, Error> {
+        let mut code = 0;
+        check_status(unsafe { uhd_sys::uhd_sensor_value_data_type(self.handle, &mut code) })?;
+        use uhd_sys::uhd_sensor_value_data_type_t::*;
+        Ok(match code {
+            UHD_SENSOR_VALUE_BOOLEAN => SensorType::Boolean,
+            UHD_SENSOR_VALUE_INTEGER => SensorType::Integer,
+            UHD_SENSOR_VALUE_REALNUM => SensorType::RealNumber,
+            _ => SensorType::String,
+        })
+    }
+
+    /// Returns the value of the sensor as a boolean
+    pub fn to_bool(&self) -> Result<bool, Error> {
+        let mut value = false;
+        check_status(unsafe { uhd_sys::uhd_sensor_value_to_bool(self.handle, &mut value) })?;
+        Ok(value)
+    }
+
+    /// Returns the value of the sensor as an integer
+    pub fn to_int(&self) -> Result<i32, Error> {
+        let mut value = 0;
+        check_status(unsafe { uhd_sys::uhd_sensor_value_to_int(self.handle, &mut value) })?;
+        Ok(value)
+    }
+
+    /// Returns the value of the sensor as a real number
+    pub fn to_f64(&self) -> Result<f64, Error> {
+        let mut value = 0.0;
+        check_status(unsafe { uhd_sys::uhd_sensor_value_to_realnum(self.handle, &mut value) })?;
+        Ok(value)
+    }
+
+    pub(crate) fn handle_mut(&mut self) -> &mut uhd_sys::uhd_sensor_value_handle {
+        &mut self.handle
+    }
+}
+
+// Thread safety: The uhd_sensor_value struct just stores data. All exposed functions read fields.
+unsafe impl Send for SensorValue {}
+unsafe impl Sync for SensorValue {}
+
+impl Drop for SensorValue {
+    fn drop(&mut self) {
+        let _ = unsafe { uhd_sys::uhd_sensor_value_free(&mut self.handle) };
+    }
+}
+
+mod fmt {
+    use super::SensorValue;
+    use std::fmt::{Debug, Formatter, Result};
+
+    impl Debug for SensorValue {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            f.debug_struct("SensorValue")
+                .field("name", &self.name())
+                .field("value", &self.value())
+                .field("unit", &self.unit())
+                .finish()
+        }
+    }
+}
@@ -0,0 +1,806 @@
+//!
+//! Streaming received samples over a network
+//!
+//! `RtlTcpServer` implements the server side of the rtl_tcp protocol used by RTL-SDR software
+//! (for example, SDR# and GQRX). Clients can change the frequency, sample rate, gain, and
+//! automatic gain control, and receive unsigned 8-bit IQ samples.
+//!
+//! `RawServer` sends `sc16` samples over UDP or TCP, in packets that start with a `RawHeader`.
+//!
+//! Both servers use a `Radio`, which is usually a `UsrpRadio`. Other implementations can be
+//! used for testing.
+//!
+
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use num_complex::Complex;
+
+use crate::error::{Error, ErrorKind};
+use crate::io::Sample;
+use crate::receive_metadata::{ReceiveErrorKind, ReceiveMetadataSnapshot};
+use crate::receive_streamer::ReceiveStreamer;
+use crate::stream::{StreamArgs, StreamCommand, StreamCommandType, StreamTime};
+use crate::tune_request::TuneRequest;
+use crate::usrp::Usrp;
+use crate::TimeSpec;
+
+/// Timeout for each receive operation, in seconds
+const RECEIVE_TIMEOUT: f64 = 0.1;
+/// The first bytes that an rtl_tcp server sends
+pub const RTL_TCP_MAGIC: [u8; 4] = *b"RTL0";
+/// Tuner type reported to rtl_tcp clients (Rafael Micro R820T)
+const TUNER_R820T: u32 = 5;
+/// Gains of the R820T tuner, in tenths of a decibel, used for gain-by-index commands
+const R820T_GAINS: [i32; 29] = [
+    0, 9, 14, 27, 37, 77, 87, 125, 144, 157, 166, 197, 207, 229, 254, 280, 297, 328, 338, 364, 372,
+    386, 402, 421, 434, 439, 445, 480, 496,
+];
+/// Default number of samples that the rtl_tcp server receives and sends at a time
+const DEFAULT_RTL_TCP_BUFFER: usize = 16384;
+
+/// The first bytes of each raw packet
+pub const RAW_MAGIC: [u8; 4] = *b"SC16";
+/// Length of a raw packet header, in bytes
+pub const RAW_HEADER_SIZE: usize = 32;
+/// Default number of samples in a raw packet (a packet fits in a 1500-byte Ethernet frame)
+const DEFAULT_RAW_PACKET_SAMPLES: usize = 360;
+/// Raw header flag: The header has a timestamp
+const RAW_FLAG_TIME: u32 = 1 << 0;
+/// Raw header flag: Samples were lost before this packet
+const RAW_FLAG_DISCONTINUITY: u32 = 1 << 1;
+
+/// A receiver that the servers can configure and stream samples from
+pub trait Radio {
+    /// Sets the center frequency, in hertz
+    fn set_frequency(&mut self, frequency: f64) -> Result<(), Error>;
+    /// Sets the sample rate, in samples per second
+    fn set_sample_rate(&mut self, sample_rate: f64) -> Result<(), Error>;
+    /// Sets the overall gain, in decibels
+    fn set_gain(&mut self, gain: f64) -> Result<(), Error>;
+    /// Enables or disables automatic gain control
+    fn set_agc_enabled(&mut self, enabled: bool) -> Result<(), Error>;
+    /// Starts streaming samples
+    fn start(&mut self) -> Result<(), Error>;
+    /// Stops streaming samples
+    fn stop(&mut self) -> Result<(), Error>;
+    /// Receives samples into buffer
+    ///
+    /// The returned metadata has the number of samples received, which may be 0 if no samples
+    /// are available. An overflow is reported in the metadata error field, not as an error.
+    fn receive(&mut self, buffer: &mut [Complex<i16>]) -> Result<ReceiveMetadataSnapshot, Error>;
+}
+
+/// A `Radio` that uses one receive channel of a USRP
+#[derive(Debug)]
+pub struct UsrpRadio<'usrp> {
+    usrp: &'usrp Usrp,
+    channel: usize,
+    streamer: ReceiveStreamer<'usrp, Complex<i16>>,
+}
+
+impl<'usrp> UsrpRadio<'usrp> {
+    /// Creates a radio that receives `sc16` samples from a channel of a USRP
+    pub fn new(usrp: &'usrp Usrp, channel: usize) -> Result<Self, Error> {
+        let args = StreamArgs::<Complex<i16>>::builder()
+            .channels(vec![channel])
+            .build();
+        let streamer = usrp.get_rx_stream(&args)?;
+        Ok(UsrpRadio {
+            usrp,
+            channel,
+            streamer,
+        })
+    }
+}
+
+impl Radio for UsrpRadio<'_> {
+    fn set_frequency(&mut self, frequency: f64) -> Result<(), Error> {
+        self.usrp
+            .set_rx_frequency(&TuneRequest::with_frequency(frequency), self.channel)?;
+        Ok(())
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) -> Result<(), Error> {
+        self.usrp.set_rx_sample_rate(sample_rate, self.channel)
+    }
+
+    fn set_gain(&mut self, gain: f64) -> Result<(), Error> {
+        self.usrp.set_rx_gain(gain, self.channel, "")
+    }
+
+    fn set_agc_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        self.usrp.set_rx_agc_enabled(enabled, self.channel)
+    }
+
+    fn start(&mut self) -> Result<(), Error> {
+        self.streamer.send_command(&StreamCommand {
+            command_type: StreamCommandType::StartContinuous,
+            time: StreamTime::Now,
+        })
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        self.streamer.send_command(&StreamCommand {
+            command_type: StreamCommandType::StopContinuous,
+            time: StreamTime::Now,
+        })
+    }
+
+    fn receive(&mut self, buffer: &mut [Complex<i16>]) -> Result<ReceiveMetadataSnapshot, Error> {
+        let metadata = self
+            .streamer
+            .receive(&mut [buffer], RECEIVE_TIMEOUT, false)?;
+        let snapshot = metadata.snapshot()?;
+        match snapshot.error {
+            None | Some(ReceiveErrorKind::Timeout) | Some(ReceiveErrorKind::Overflow) => {
+                Ok(snapshot)
+            }
+            Some(kind) => {
+                let message = metadata
+                    .try_last_error()?
+                    .map(|error| error.to_string())
+                    .unwrap_or_default();
+                Err(Error::with_message(ErrorKind::Receive(kind), message))
+            }
+        }
+    }
+}
+
+/// A command from an rtl_tcp client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Set the center frequency, in hertz
+    SetFrequency(u32),
+    /// Set the sample rate, in samples per second
+    SetSampleRate(u32),
+    /// Select manual (true) or automatic (false) gain
+    SetGainMode(bool),
+    /// Set the gain, in tenths of a decibel
+    SetGain(i32),
+    /// Enable or disable automatic gain control
+    SetAgcMode(bool),
+    /// Set the gain to an entry in the list of tuner gains
+    SetGainIndex(u32),
+    /// A command that this server ignores, with its code and parameter
+    Other(u8, u32),
+}
+
+impl Command {
+    /// Parses a command from the 5 bytes that a client sends
+    pub fn parse(bytes: [u8; 5]) -> Self {
+        let parameter = u32::from_be_bytes(bytes[1..].try_into().unwrap());
+        match bytes[0] {
+            0x01 => Command::SetFrequency(parameter),
+            0x02 => Command::SetSampleRate(parameter),
+            0x03 => Command::SetGainMode(parameter != 0),
+            0x04 => Command::SetGain(parameter as i32),
+            0x08 => Command::SetAgcMode(parameter != 0),
+            0x0d => Command::SetGainIndex(parameter),
+            code => Command::Other(code, parameter),
+        }
+    }
+
+    /// Encodes this command in the form that a client sends
+    pub fn encode(&self) -> [u8; 5] {
+        let (code, parameter) = match *self {
+            Command::SetFrequency(frequency) => (0x01, frequency),
+            Command::SetSampleRate(rate) => (0x02, rate),
+            Command::SetGainMode(manual) => (0x03, u32::from(manual)),
+            Command::SetGain(gain) => (0x04, gain as u32),
+            Command::SetAgcMode(enabled) => (0x08, u32::from(enabled)),
+            Command::SetGainIndex(index) => (0x0d, index),
+            Command::Other(code, parameter) => (code, parameter),
+        };
+        let mut bytes = [code; 5];
+        bytes[1..].copy_from_slice(&parameter.to_be_bytes());
+        bytes
+    }
+
+    /// Applies this command to a radio
+    fn apply<R: Radio>(&self, radio: &mut R) -> Result<(), Error> {
+        match *self {
+            Command::SetFrequency(frequency) => radio.set_frequency(f64::from(frequency)),
+            Command::SetSampleRate(rate) => radio.set_sample_rate(f64::from(rate)),
+            Command::SetGainMode(manual) => radio.set_agc_enabled(!manual),
+            Command::SetGain(gain) => radio.set_gain(f64::from(gain) / 10.0),
+            Command::SetAgcMode(enabled) => radio.set_agc_enabled(enabled),
+            Command::SetGainIndex(index) => match R820T_GAINS.get(index as usize) {
+                Some(&gain) => radio.set_gain(f64::from(gain) / 10.0),
+                None => Err(Error::with_message(
+                    ErrorKind::Value,
+                    format!("Gain index {} out of range", index),
+                )),
+            },
+            Command::Other(_, _) => Ok(()),
+        }
+    }
+}
+
+/// Information about a finished rtl_tcp connection
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Number of sample bytes sent to the client
+    pub bytes_sent: u64,
+    /// Number of commands received from the client
+    pub commands: usize,
+    /// Commands that could not be applied, and the errors that they caused
+    pub failed_commands: Vec<(Command, Error)>,
+}
+
+/// A server that implements the rtl_tcp protocol
+///
+/// The server handles one client at a time. Like rtl_tcp, it keeps streaming when a command
+/// fails (for example, because the sample rate is not supported); failed commands are reported
+/// in the `Session`.
+///
+/// Example:
+///
+/// ```no_run
+/// use uhd::server::{RtlTcpServer, UsrpRadio};
+/// use uhd::Usrp;
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let usrp = Usrp::open("")?;
+/// let mut radio = UsrpRadio::new(&usrp, 0)?;
+/// let server = RtlTcpServer::bind("0.0.0.0:1234")?;
+/// server.serve(&mut radio)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RtlTcpServer {
+    listener: TcpListener,
+    /// Number of samples received and sent at a time
+    buffer_size: usize,
+}
+
+impl RtlTcpServer {
+    /// Creates a server that listens on the provided address
+    pub fn bind<A: ToSocketAddrs>(address: A) -> Result<Self, Error> {
+        Ok(RtlTcpServer {
+            listener: TcpListener::bind(address)?,
+            buffer_size: DEFAULT_RTL_TCP_BUFFER,
+        })
+    }
+
+    /// Sets the number of samples that are received and sent at a time
+    pub fn buffer_size(self, buffer_size: usize) -> Self {
+        RtlTcpServer {
+            buffer_size: buffer_size.max(1),
+            ..self
+        }
+    }
+
+    /// Returns the address that the server is listening on
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Serves clients, one at a time, until an error occurs
+    pub fn serve<R: Radio>(&self, radio: &mut R) -> Result<(), Error> {
+        loop {
+            self.serve_one(radio)?;
+        }
+    }
+
+    /// Waits for a client, and streams samples to it until it disconnects
+    pub fn serve_one<R: Radio>(&self, radio: &mut R) -> Result<Session, Error> {
+        let (mut stream, _) = self.listener.accept()?;
+        stream.set_nodelay(true)?;
+
+        let mut header = [0u8; 12];
+        header[..4].copy_from_slice(&RTL_TCP_MAGIC);
+        header[4..8].copy_from_slice(&TUNER_R820T.to_be_bytes());
+        header[8..].copy_from_slice(&(R820T_GAINS.len() as u32).to_be_bytes());
+        match stream.write_all(&header) {
+            Ok(()) => {}
+            Err(e) if is_disconnect(&e) => return Ok(Session::default()),
+            Err(e) => return Err(e.into()),
+        }
+
+        let (sender, commands) = mpsc::channel();
+        let command_stream = stream.try_clone()?;
+        let command_thread = thread::spawn(move || read_commands(command_stream, sender));
+
+        let result = match radio.start() {
+            Ok(()) => {
+                let result = self.stream_samples(&mut stream, radio, &commands);
+                let stop_result = radio.stop();
+                result.and_then(|session| stop_result.map(|()| session))
+            }
+            Err(e) => Err(e),
+        };
+        // Unblock the command thread if the client has not closed the connection
+        let _ = stream.shutdown(Shutdown::Both);
+        let _ = command_thread.join();
+        result
+    }
+
+    /// Applies commands and sends samples until the client disconnects
+    fn stream_samples<R: Radio>(
+        &self,
+        stream: &mut TcpStream,
+        radio: &mut R,
+        commands: &Receiver<Command>,
+    ) -> Result<Session, Error> {
+        let mut session = Session::default();
+        let mut samples = vec![Complex::default(); self.buffer_size];
+        let mut bytes = Vec::with_capacity(2 * self.buffer_size);
+        loop {
+            loop {
+                match commands.try_recv() {
+                    Ok(command) => {
+                        session.commands += 1;
+                        if let Err(e) = command.apply(radio) {
+                            session.failed_commands.push((command, e));
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    // The client closed the connection
+                    Err(TryRecvError::Disconnected) => return Ok(session),
+                }
+            }
+
+            let metadata = radio.receive(&mut samples)?;
+            bytes.clear();
+            bytes.extend(
+                samples[..metadata.samples]
+                    .iter()
+                    .flat_map(|&sample| to_offset_binary(sample)),
+            );
+            match stream.write_all(&bytes) {
+                Ok(()) => session.bytes_sent += bytes.len() as u64,
+                Err(e) if is_disconnect(&e) => return Ok(session),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Reads commands from a client until it disconnects
+fn read_commands(mut stream: TcpStream, sender: Sender<Command>) {
+    let mut bytes = [0u8; 5];
+    while stream.read_exact(&mut bytes).is_ok() {
+        if sender.send(Command::parse(bytes)).is_err() {
+            break;
+        }
+    }
+}
+
+/// Converts a 16-bit sample into unsigned 8-bit offset-binary form, keeping the upper 8 bits
+fn to_offset_binary(sample: Complex<i16>) -> [u8; 2] {
+    [
+        ((sample.re >> 8) as u8) ^ 0x80,
+        ((sample.im >> 8) as u8) ^ 0x80,
+    ]
+}
+
+/// Returns true if an error means that the other end of a connection has closed it
+fn is_disconnect(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+    )
+}
+
+/// The header at the beginning of each raw packet
+///
+/// The header has these big-endian fields, followed by the samples as little-endian `i16`
+/// in-phase and quadrature values:
+///
+/// | Bytes  | Field                                                  |
+/// |--------|--------------------------------------------------------|
+/// | 0..4   | `SC16`                                                 |
+/// | 4..8   | Sequence number (u32)                                  |
+/// | 8..12  | Number of samples (u32)                                |
+/// | 12..16 | Flags (bit 0: timestamp present, bit 1: discontinuity) |
+/// | 16..24 | Timestamp whole seconds (i64)                          |
+/// | 24..32 | Timestamp fractional seconds (f64)                     |
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawHeader {
+    /// Sequence number, which increases by one for each packet
+    pub sequence: u32,
+    /// Number of samples in the packet
+    pub samples: u32,
+    /// Device time of the first sample
+    pub time_spec: Option<TimeSpec>,
+    /// True if samples were lost between the previous packet and this one
+    pub discontinuity: bool,
+}
+
+impl RawHeader {
+    /// Encodes this header
+    pub fn encode(&self) -> [u8; RAW_HEADER_SIZE] {
+        let mut flags = 0;
+        if self.time_spec.is_some() {
+            flags |= RAW_FLAG_TIME;
+        }
+        if self.discontinuity {
+            flags |= RAW_FLAG_DISCONTINUITY;
+        }
+        let time = self.time_spec.unwrap_or_default();
+        let mut bytes = [0u8; RAW_HEADER_SIZE];
+        bytes[..4].copy_from_slice(&RAW_MAGIC);
+        bytes[4..8].copy_from_slice(&self.sequence.to_be_bytes());
+        bytes[8..12].copy_from_slice(&self.samples.to_be_bytes());
+        bytes[12..16].copy_from_slice(&flags.to_be_bytes());
+        bytes[16..24].copy_from_slice(&time.seconds.to_be_bytes());
+        bytes[24..32].copy_from_slice(&time.fraction.to_be_bytes());
+        bytes
+    }
+
+    /// Decodes a header from the beginning of a packet
+    ///
+    /// This returns an error of kind `ErrorKind::Value` if the packet is too short or does not
+    /// start with the raw packet magic bytes.
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < RAW_HEADER_SIZE || bytes[..4] != RAW_MAGIC {
+            return Err(Error::with_message(
+                ErrorKind::Value,
+                "Packet does not start with a raw header".to_string(),
+            ));
+        }
+        let word = |start: usize| u32::from_be_bytes(bytes[start..start + 4].try_into().unwrap());
+        let flags = word(12);
+        let time_spec = if flags & RAW_FLAG_TIME != 0 {
+            Some(TimeSpec::new(
+                i64::from_be_bytes(bytes[16..24].try_into().unwrap()),
+                f64::from_be_bytes(bytes[24..32].try_into().unwrap()),
+            ))
+        } else {
+            None
+        };
+        Ok(RawHeader {
+            sequence: word(4),
+            samples: word(8),
+            time_spec,
+            discontinuity: flags & RAW_FLAG_DISCONTINUITY != 0,
+        })
+    }
+}
+
+/// A connection that raw packets are sent over
+#[derive(Debug)]
+pub enum RawTransport {
+    /// A UDP socket, which must be connected to the destination
+    Udp(UdpSocket),
+    /// A TCP connection
+    Tcp(TcpStream),
+}
+
+/// Sends `sc16` samples in packets that start with a `RawHeader`
+///
+/// Over UDP, each datagram contains one packet. Over TCP, packets are sent one after another.
+///
+/// Example:
+///
+/// ```no_run
+/// use std::net::UdpSocket;
+/// use uhd::server::{RawServer, RawTransport, UsrpRadio};
+/// use uhd::Usrp;
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let usrp = Usrp::open("")?;
+/// let mut radio = UsrpRadio::new(&usrp, 0)?;
+/// let socket = UdpSocket::bind("0.0.0.0:0")?;
+/// socket.connect("192.168.1.20:5000")?;
+/// let mut server = RawServer::new(RawTransport::Udp(socket));
+/// server.run(&mut radio, || true)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RawServer {
+    transport: RawTransport,
+    /// Maximum number of samples in a packet
+    packet_samples: usize,
+    /// Sequence number of the next packet
+    sequence: u32,
+}
+
+impl RawServer {
+    /// Creates a server that sends packets over the provided transport
+    pub fn new(transport: RawTransport) -> Self {
+        RawServer {
+            transport,
+            packet_samples: DEFAULT_RAW_PACKET_SAMPLES,
+            sequence: 0,
+        }
+    }
+
+    /// Sets the maximum number of samples in a packet (default 360)
+    pub fn packet_samples(self, packet_samples: usize) -> Self {
+        RawServer {
+            packet_samples: packet_samples.max(1),
+            ..self
+        }
+    }
+
+    /// Streams samples until keep_going returns false or a TCP client disconnects, and returns
+    /// the number of packets sent
+    ///
+    /// keep_going is called before each receive operation.
+    pub fn run<R, F>(&mut self, radio: &mut R, mut keep_going: F) -> Result<u64, Error>
+    where
+        R: Radio,
+        F: FnMut() -> bool,
+    {
+        let mut samples = vec![Complex::default(); self.packet_samples];
+        let mut packet = Vec::with_capacity(RAW_HEADER_SIZE + self.packet_samples * 4);
+        let mut discontinuity = false;
+        let mut packets = 0;
+        radio.start()?;
+        let result = loop {
+            if !keep_going() {
+                break Ok(packets);
+            }
+            let metadata = match radio.receive(&mut samples) {
+                Ok(metadata) => metadata,
+                Err(e) => break Err(e),
+            };
+            discontinuity |= metadata.error == Some(ReceiveErrorKind::Overflow);
+            if metadata.samples == 0 {
+                continue;
+            }
+            let header = RawHeader {
+                sequence: self.sequence,
+                samples: metadata.samples as u32,
+                time_spec: metadata.time_spec,
+                discontinuity,
+            };
+            packet.clear();
+            packet.extend_from_slice(&header.encode());
+            packet.resize(RAW_HEADER_SIZE + metadata.samples * 4, 0);
+            for (sample, bytes) in samples[..metadata.samples]
+                .iter()
+                .zip(packet[RAW_HEADER_SIZE..].chunks_exact_mut(4))
+            {
+                sample.encode(bytes);
+            }
+            let sent = match &mut self.transport {
+                RawTransport::Udp(socket) => socket.send(&packet).map(|_| ()),
+                RawTransport::Tcp(stream) => stream.write_all(&packet),
+            };
+            match sent {
+                Ok(()) => {}
+                Err(e) if is_disconnect(&e) => break Ok(packets),
+                Err(e) => break Err(e.into()),
+            }
+            self.sequence = self.sequence.wrapping_add(1);
+            discontinuity = false;
+            packets += 1;
+        };
+        let stop_result = radio.stop();
+        let packets = result?;
+        stop_result?;
+        Ok(packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// A radio that produces samples whose in-phase values are the frequency in megahertz
+    /// (in the upper 8 bits)
+    #[derive(Debug, Default)]
+    struct MockRadio {
+        frequency: f64,
+        agc: bool,
+        gain: f64,
+        running: bool,
+        sample_time: i64,
+        /// If true, start returns an error
+        start_fails: bool,
+    }
+
+    impl Radio for MockRadio {
+        fn set_frequency(&mut self, frequency: f64) -> Result<(), Error> {
+            self.frequency = frequency;
+            Ok(())
+        }
+        fn set_sample_rate(&mut self, _sample_rate: f64) -> Result<(), Error> {
+            Err(Error::with_message(
+                ErrorKind::Value,
+                "Unsupported rate".to_string(),
+            ))
+        }
+        fn set_gain(&mut self, gain: f64) -> Result<(), Error> {
+            self.gain = gain;
+            Ok(())
+        }
+        fn set_agc_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+            self.agc = enabled;
+            Ok(())
+        }
+        fn start(&mut self) -> Result<(), Error> {
+            if self.start_fails {
+                return Err(Error::new(ErrorKind::Io));
+            }
+            self.running = true;
+            Ok(())
+        }
+        fn stop(&mut self) -> Result<(), Error> {
+            self.running = false;
+            Ok(())
+        }
+        fn receive(
+            &mut self,
+            buffer: &mut [Complex<i16>],
+        ) -> Result<ReceiveMetadataSnapshot, Error> {
+            assert!(self.running);
+            thread::sleep(Duration::from_millis(1));
+            let value = Complex::new((self.frequency / 1e6) as i16 * 256, -256);
+            for sample in buffer.iter_mut() {
+                *sample = value;
+            }
+            let time_spec = Some(TimeSpec::from_ticks(self.sample_time, 1000.0));
+            self.sample_time += buffer.len() as i64;
+            Ok(ReceiveMetadataSnapshot {
+                samples: buffer.len(),
+                time_spec,
+                ..ReceiveMetadataSnapshot::default()
+            })
+        }
+    }
+
+    #[test]
+    fn commands() {
+        for command in &[
+            Command::SetFrequency(100_000_000),
+            Command::SetGain(-15),
+            Command::SetAgcMode(true),
+            Command::Other(0x0e, 1),
+        ] {
+            assert_eq!(*command, Command::parse(command.encode()));
+        }
+        assert_eq!(
+            Command::SetSampleRate(2_048_000),
+            Command::parse([0x02, 0x00, 0x1f, 0x40, 0x00])
+        );
+        let mut radio = MockRadio::default();
+        Command::SetGainIndex(28).apply(&mut radio).unwrap();
+        assert_eq!(49.6, radio.gain);
+        Command::SetGainMode(false).apply(&mut radio).unwrap();
+        assert!(radio.agc);
+        assert!(Command::SetGainIndex(29).apply(&mut radio).is_err());
+    }
+
+    #[test]
+    fn rtl_tcp_localhost() {
+        let server = RtlTcpServer::bind("127.0.0.1:0").unwrap().buffer_size(64);
+        let address = server.local_addr().unwrap();
+        let server_thread = thread::spawn(move || {
+            let mut radio = MockRadio::default();
+            let session = server.serve_one(&mut radio).unwrap();
+            (radio, session)
+        });
+
+        let mut client = TcpStream::connect(address).unwrap();
+        let mut header = [0u8; 12];
+        client.read_exact(&mut header).unwrap();
+        assert_eq!(RTL_TCP_MAGIC, header[..4]);
+        assert_eq!([0, 0, 0, 29], header[8..]);
+        client
+            .write_all(&Command::SetSampleRate(2_048_000).encode())
+            .unwrap();
+        client
+            .write_all(&Command::SetFrequency(5_000_000).encode())
+            .unwrap();
+        // Read until samples show the new frequency
+        let mut samples = [0u8; 128];
+        loop {
+            client.read_exact(&mut samples).unwrap();
+            if samples[126..] == [128 + 5, 127] {
+                break;
+            }
+        }
+        drop(client);
+
+        let (radio, session) = server_thread.join().unwrap();
+        assert_eq!(5e6, radio.frequency);
+        assert!(!radio.running);
+        assert_eq!(2, session.commands);
+        assert_eq!(1, session.failed_commands.len());
+        assert_eq!(
+            Command::SetSampleRate(2_048_000),
+            session.failed_commands[0].0
+        );
+        assert!(session.bytes_sent >= 128);
+    }
+
+    #[test]
+    fn rtl_tcp_start_error() {
+        let server = RtlTcpServer::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        let server_thread = thread::spawn(move || {
+            let mut radio = MockRadio {
+                start_fails: true,
+                ..MockRadio::default()
+            };
+            server.serve_one(&mut radio).map(|_| ())
+        });
+
+        let mut client = TcpStream::connect(address).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        // The server closes the connection after the header, even though the client has not
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert_eq!(12, received.len());
+        let error = server_thread.join().unwrap().unwrap_err();
+        assert_eq!(ErrorKind::Io, error.kind());
+    }
+
+    #[test]
+    fn raw_udp_localhost() {
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.connect(client.local_addr().unwrap()).unwrap();
+        let mut server = RawServer::new(RawTransport::Udp(socket)).packet_samples(10);
+        let mut radio = MockRadio {
+            frequency: 3e6,
+            ..MockRadio::default()
+        };
+        let mut remaining = 3;
+        let packets = server
+            .run(&mut radio, || {
+                remaining -= 1;
+                remaining >= 0
+            })
+            .unwrap();
+        assert_eq!(3, packets);
+
+        let mut datagram = [0u8; 1024];
+        for sequence in 0..3 {
+            let length = client.recv(&mut datagram).unwrap();
+            assert_eq!(RAW_HEADER_SIZE + 40, length);
+            let header = RawHeader::decode(&datagram).unwrap();
+            assert_eq!(
+                RawHeader {
+                    sequence,
+                    samples: 10,
+                    time_spec: Some(TimeSpec::from_ticks(i64::from(sequence) * 10, 1000.0)),
+                    discontinuity: false,
+                },
+                header
+            );
+            assert_eq!(
+                Complex::new(768, -256),
+                Complex::<i16>::decode(&datagram[RAW_HEADER_SIZE..RAW_HEADER_SIZE + 4])
+            );
+        }
+    }
+
+    #[test]
+    fn raw_tcp_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut server = RawServer::new(RawTransport::Tcp(stream)).packet_samples(4);
+        let mut remaining = 2;
+        server
+            .run(&mut MockRadio::default(), || {
+                remaining -= 1;
+                remaining >= 0
+            })
+            .unwrap();
+        drop(server);
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert_eq!(2 * (RAW_HEADER_SIZE + 16), received.len());
+        let second = RawHeader::decode(&received[RAW_HEADER_SIZE + 16..]).unwrap();
+        assert_eq!(1, second.sequence);
+        assert_eq!(4, second.samples);
+        assert!(RawHeader::decode(&received[1..]).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+//!
+//! Snapshots of the complete state of a USRP
+//!
+//! A `DeviceSnapshot` records the settings, sensor values, and identity of every motherboard and
+//! channel of a USRP. Snapshots can be converted to and from JSON, and compared with
+//! `DeviceSnapshot::diff`.
+//!
+//! This module is available when the `snapshot` feature is enabled.
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Error, ErrorKind};
+use crate::sensor_value::SensorValue;
+use crate::usrp::Usrp;
+
+/// The state of a USRP at one time
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSnapshot {
+    /// Motherboards, in order of index
+    pub mboards: Vec<MboardSnapshot>,
+    /// Receive channels, in order of index
+    pub rx: Vec<ChannelSnapshot>,
+    /// Transmit channels, in order of index
+    pub tx: Vec<ChannelSnapshot>,
+}
+
+/// The state of one motherboard
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MboardSnapshot {
+    pub name: String,
+    /// Identifying values from the motherboard EEPROM (`name`, `product`, `revision`, and
+    /// `serial`), if present
+    pub eeprom: BTreeMap<String, String>,
+    pub clock_source: String,
+    pub time_source: String,
+    /// Master clock rate, hertz
+    pub master_clock_rate: f64,
+    pub sensors: BTreeMap<String, SensorReading>,
+}
+
+/// The state of one receive or transmit channel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChannelSnapshot {
+    pub antenna: String,
+    /// Center frequency, hertz
+    pub frequency: f64,
+    /// Sample rate, samples/second
+    pub sample_rate: f64,
+    /// Overall gain, decibels
+    pub gain: f64,
+    /// Gain of each gain element, decibels
+    pub gains: BTreeMap<String, f64>,
+    /// Analog bandwidth, hertz (None if the device does not report it)
+    pub bandwidth: Option<f64>,
+    pub local_oscillators: BTreeMap<String, LocalOscillatorSnapshot>,
+    pub sensors: BTreeMap<String, SensorReading>,
+    /// The identity of the front end (None if the device does not report it)
+    pub frontend: Option<FrontendIdentity>,
+}
+
+/// The state of a local oscillator
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocalOscillatorSnapshot {
+    /// Frequency, hertz
+    pub frequency: f64,
+    /// The source of the local oscillator (for example, `internal` or `external`)
+    pub source: Option<String>,
+    pub export_enabled: Option<bool>,
+}
+
+/// A sensor value, formatted as a string
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SensorReading {
+    pub value: String,
+    pub unit: String,
+}
+
+/// Identifying information about the daughterboard that a channel uses
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrontendIdentity {
+    pub daughterboard_id: String,
+    pub daughterboard_serial: String,
+    pub subdev_name: String,
+}
+
+/// Motherboard EEPROM keys that are recorded in snapshots
+const EEPROM_KEYS: [&str; 4] = ["name", "product", "revision", "serial"];
+
+impl DeviceSnapshot {
+    /// Reads the state of all motherboards and channels of a USRP
+    ///
+    /// This returns an error if any basic setting (such as the frequency or antenna of a channel)
+    /// cannot be read. Values that some devices do not support (bandwidth, local oscillators,
+    /// sensors, and EEPROM contents) are omitted if they cannot be read.
+    pub fn capture(usrp: &Usrp) -> Result<Self, Error> {
+        let mboards = (0..usrp.get_num_motherboards()?)
+            .map(|mboard| capture_mboard(usrp, mboard))
+            .collect::<Result<_, Error>>()?;
+        let rx = (0..usrp.get_num_rx_channels()?)
+            .map(|channel| capture_rx(usrp, channel))
+            .collect::<Result<_, Error>>()?;
+        let tx = (0..usrp.get_num_tx_channels()?)
+            .map(|channel| capture_tx(usrp, channel))
+            .collect::<Result<_, Error>>()?;
+        Ok(DeviceSnapshot { mboards, rx, tx })
+    }
+
+    /// Converts this snapshot into pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(json_error)
+    }
+
+    /// Parses a snapshot from JSON
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        serde_json::from_str(text).map_err(json_error)
+    }
+
+    /// Returns the differences between this snapshot and a later snapshot
+    ///
+    /// The differences are ordered by path, with the keys of each object in alphabetical order
+    /// and array elements in order of index.
+    pub fn diff(&self, after: &DeviceSnapshot) -> Result<Vec<Difference>, Error> {
+        let before = serde_json::to_value(self).map_err(json_error)?;
+        let after = serde_json::to_value(after).map_err(json_error)?;
+        let mut differences = Vec::new();
+        diff_values(String::new(), Some(&before), Some(&after), &mut differences);
+        Ok(differences)
+    }
+}
+
+fn capture_mboard(usrp: &Usrp, mboard: usize) -> Result<MboardSnapshot, Error> {
+    let mut eeprom = BTreeMap::new();
+    if let Ok(contents) = usrp.get_motherboard_eeprom(mboard) {
+        for &key in EEPROM_KEYS.iter() {
+            if let Ok(Some(value)) = contents.get(key) {
+                eeprom.insert(key.to_string(), value);
+            }
+        }
+    }
+    let sensors = capture_sensors(usrp.get_mboard_sensor_names(mboard), |name| {
+        usrp.get_mboard_sensor(name, mboard)
+    });
+    Ok(MboardSnapshot {
+        name: usrp.get_motherboard_name(mboard)?,
+        eeprom,
+        clock_source: usrp.get_clock_source(mboard)?,
+        time_source: usrp.get_time_source(mboard)?,
+        master_clock_rate: usrp.get_master_clock_rate(mboard)?,
+        sensors,
+    })
+}
+
+fn capture_rx(usrp: &Usrp, channel: usize) -> Result<ChannelSnapshot, Error> {
+    let gains = usrp
+        .get_rx_gain_names(channel)?
+        .into_iter()
+        .map(|name| {
+            let gain = usrp.get_rx_gain(channel, &name)?;
+            Ok((name, gain))
+        })
+        .collect::<Result<_, Error>>()?;
+    let local_oscillators = usrp
+        .get_rx_lo_names(channel)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|name| {
+            let frequency = usrp.get_rx_lo_frequency(channel, &name).ok()?;
+            let oscillator = LocalOscillatorSnapshot {
+                frequency,
+                source: usrp.get_rx_lo_source(channel, &name).ok(),
+                export_enabled: usrp.get_rx_lo_export_enabled(channel, &name).ok(),
+            };
+            Some((name, oscillator))
+        })
+        .collect();
+    let sensors = capture_sensors(usrp.get_rx_sensor_names(channel), |name| {
+        usrp.get_rx_sensor(name, channel)
+    });
+    let frontend = usrp.get_rx_info(channel).ok().map(|info| FrontendIdentity {
+        daughterboard_id: info.daughterboard_id().to_string(),
+        daughterboard_serial: info.daughterboard_serial().to_string(),
+        subdev_name: info.subdev_name().to_string(),
+    });
+    Ok(ChannelSnapshot {
+        antenna: usrp.get_rx_antenna(channel)?,
+        frequency: usrp.get_rx_frequency(channel)?,
+        sample_rate: usrp.get_rx_sample_rate(channel)?,
+        gain: usrp.get_rx_gain(channel, "")?,
+        gains,
+        bandwidth: usrp.get_rx_bandwidth(channel).ok(),
+        local_oscillators,
+        sensors,
+        frontend,
+    })
+}
+
+fn capture_tx(usrp: &Usrp, channel: usize) -> Result<ChannelSnapshot, Error> {
+    let gains = usrp
+        .get_tx_gain_names(channel)?
+        .into_iter()
+        .map(|name| {
+            let gain = usrp.get_tx_gain(channel, &name)?;
+            Ok((name, gain))
+        })
+        .collect::<Result<_, Error>>()?;
+    let local_oscillators = usrp
+        .get_tx_lo_names(channel)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|name| {
+            let frequency = usrp.get_tx_lo_frequency(channel, &name).ok()?;
+            let oscillator = LocalOscillatorSnapshot {
+                frequency,
+                source: usrp.get_tx_lo_source(channel, &name).ok(),
+                export_enabled: usrp.get_tx_lo_export_enabled(channel, &name).ok(),
+            };
+            Some((name, oscillator))
+        })
+        .collect();
+    let sensors = capture_sensors(usrp.get_tx_sensor_names(channel), |name| {
+        usrp.get_tx_sensor(name, channel)
+    });
+    let frontend = usrp.get_tx_info(channel).ok().map(|info| FrontendIdentity {
+        daughterboard_id: info.daughterboard_id().to_string(),
+        daughterboard_serial: info.daughterboard_serial().to_string(),
+        subdev_name: info.subdev_name().to_string(),
+    });
+    Ok(ChannelSnapshot {
+        antenna: usrp.get_tx_antenna(channel)?,
+        frequency: usrp.get_tx_frequency(channel)?,
+        sample_rate: usrp.get_tx_sample_rate(channel)?,
+        gain: usrp.get_tx_gain(channel, "")?,
+        gains,
+        bandwidth: usrp.get_tx_bandwidth(channel).ok(),
+        local_oscillators,
+        sensors,
+        frontend,
+    })
+}
+
+/// Reads the sensors with the provided names, skipping any sensors that cannot be read
+fn capture_sensors<F>(names: Result<Vec<String>, Error>, read: F) -> BTreeMap<String, SensorReading>
+where
+    F: Fn(&str) -> Result<SensorValue, Error>,
+{
+    names
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|name| {
+            let reading = read(&name).and_then(|value| read_sensor(&value)).ok()?;
+            Some((name, reading))
+        })
+        .collect()
+}
+
+fn read_sensor(value: &SensorValue) -> Result<SensorReading, Error> {
+    Ok(SensorReading {
+        value: value.value()?,
+        unit: value.unit()?,
+    })
+}
+
+/// Converts a JSON error into an Error
+fn json_error(error: serde_json::Error) -> Error {
+    Error::with_message(ErrorKind::Value, error.to_string())
+}
+
+/// A value that is different in two snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// The location of the value (for example, `rx[0].gains.PGA0`)
+    pub path: String,
+    /// The value in the first snapshot, or None if it was not present
+    pub before: Option<Value>,
+    /// The value in the second snapshot, or None if it was not present
+    pub after: Option<Value>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.before {
+            Some(value) => write!(f, "{}", value)?,
+            None => write!(f, "(none)")?,
+        }
+        write!(f, " -> ")?;
+        match &self.after {
+            Some(value) => write!(f, "{}", value),
+            None => write!(f, "(none)"),
+        }
+    }
+}
+
+/// Compares two JSON values and adds the differences between them
+fn diff_values(
+    path: String,
+    before: Option<&Value>,
+    after: Option<&Value>,
+    differences: &mut Vec<Difference>,
+) {
+    match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_values(path, before.get(key), after.get(key), differences);
+            }
+        }
+        (Some(Value::Array(before)), Some(Value::Array(after))) => {
+            for i in 0..before.len().max(after.len()) {
+                diff_values(
+                    format!("{}[{}]", path, i),
+                    before.get(i),
+                    after.get(i),
+                    differences,
+                );
+            }
+        }
+        (before, after) => {
+            if before != after {
+                differences.push(Difference {
+                    path,
+                    before: before.cloned(),
+                    after: after.cloned(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> DeviceSnapshot {
+        let mut sensors = BTreeMap::new();
+        sensors.insert(
+            "ref_locked".to_string(),
+            SensorReading {
+                value: "true".to_string(),
+                unit: "locked".to_string(),
+            },
+        );
+        let mut gains = BTreeMap::new();
+        gains.insert("PGA0".to_string(), 20.0);
+        DeviceSnapshot {
+            mboards: vec![MboardSnapshot {
+                name: "B210".to_string(),
+                clock_source: "internal".to_string(),
+                time_source: "none".to_string(),
+                master_clock_rate: 16e6,
+                sensors,
+                ..MboardSnapshot::default()
+            }],
+            rx: vec![ChannelSnapshot {
+                antenna: "RX2".to_string(),
+                frequency: 2.45e9,
+                sample_rate: 1e6,
+                gain: 20.0,
+                gains,
+                ..ChannelSnapshot::default()
+            }],
+            tx: vec![],
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let snapshot = example();
+        let json = snapshot.to_json().unwrap();
+        assert_eq!(snapshot, DeviceSnapshot::from_json(&json).unwrap());
+        assert!(DeviceSnapshot::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn diff() {
+        let before = example();
+        assert!(before.diff(&before).unwrap().is_empty());
+
+        let mut after = example();
+        after.rx[0].gains.insert("PGA0".to_string(), 30.0);
+        after.rx[0].bandwidth = Some(1e6);
+        after.mboards[0].sensors.clear();
+        after.rx[0].local_oscillators.insert(
+            "lo1".to_string(),
+            LocalOscillatorSnapshot {
+                frequency: 2.4e9,
+                source: Some("internal".to_string()),
+                export_enabled: Some(false),
+            },
+        );
+        after.tx.push(ChannelSnapshot::default());
+
+        let differences = before.diff(&after).unwrap();
+        let paths: Vec<&str> = differences.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(
+            vec![
+                "mboards[0].sensors.ref_locked",
+                "rx[0].bandwidth",
+                "rx[0].gains.PGA0",
+                "rx[0].local_oscillators.lo1",
+                "tx[0]"
+            ],
+            paths
+        );
+        assert_eq!(None, differences[0].after);
+        assert_eq!("rx[0].gains.PGA0: 20.0 -> 30.0", differences[2].to_string());
+    }
+}
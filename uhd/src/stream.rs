@@ -1,8 +1,11 @@
+use crate::error::{Error, ErrorKind};
 use crate::TimeSpec;
-use num_complex::{Complex, Complex32, Complex64};
+use num_complex::Complex;
 use std::convert::{TryFrom, TryInto};
 use std::ffi::{CString, NulError};
+use std::fmt;
 use std::marker::PhantomData;
+use std::str::FromStr;
 
 /// Arguments used to create a stream
 ///
@@ -10,15 +13,35 @@ use std::marker::PhantomData;
 ///
 /// The default stream arguments use wire format `sc16` and host format `fc32`:
 /// ```
-/// use uhd::StreamArgs;
+/// use uhd::{StreamArgs, WireFormat};
 /// use num_complex::Complex32;
-/// let args = StreamArgs::<Complex32>::new("sc16");
+/// # fn main() -> Result<(), uhd::Error> {
+/// let args = StreamArgs::<Complex32>::new(WireFormat::Sc16)?;
+/// # Ok(())
+/// # }
 /// ```
 ///
+/// Other options can be set using a builder:
+/// ```
+/// use uhd::{wire, StreamArgs, UnderflowPolicy};
+/// use num_complex::Complex32;
+/// let args = StreamArgs::<Complex32>::builder()
+///     .wire::<wire::Sc8>()
+///     .spp(1000)
+///     .underflow_policy(UnderflowPolicy::NextBurst)
+///     .channels(vec![0, 1])
+///     .build();
+/// ```
+///
+/// `StreamArgsBuilder::wire` checks at compile time that UHD can convert between the host and
+/// wire formats. `StreamArgs::new` and `StreamArgsBuilder::wire_format` take a wire format that
+/// is chosen at run time, and return an error if the formats are not compatible.
+///
 #[derive(Debug, Clone)]
 pub struct StreamArgs<I> {
     host_format: PhantomData<I>,
-    wire_format: String,
+    wire_format: WireFormat,
+    options: StreamOptions,
     args: String,
     channels: Vec<usize>,
 }
@@ -26,14 +49,16 @@ pub struct StreamArgs<I> {
 impl<I> StreamArgs<I> {
     /// Creates stream arguments with the provided wire format, no additional
     /// arguments, and channel 0
-    pub fn new<S>(wire_format: S) -> Self
+    ///
+    /// This returns an error if UHD cannot convert between the host format I and the wire
+    /// format.
+    pub fn new(wire_format: WireFormat) -> Result<Self, Error>
     where
-        S: Into<String>,
+        I: Item,
     {
-        StreamArgs {
-            wire_format: wire_format.into(),
-            ..StreamArgs::default()
-        }
+        StreamArgs::builder()
+            .wire_format(wire_format)
+            .map(StreamArgsBuilder::build)
     }
 
     /// Creates a builder, initialized with default arguments, that can be used to configure
@@ -43,6 +68,26 @@ impl<I> StreamArgs<I> {
             args: StreamArgs::default(),
         }
     }
+
+    /// Returns the wire format
+    pub fn wire_format(&self) -> WireFormat {
+        self.wire_format
+    }
+
+    /// Returns the indexes of channels to stream
+    pub fn channels(&self) -> &[usize] {
+        &self.channels
+    }
+
+    /// Returns the typed options and additional arguments formatted as a UHD argument string
+    /// (`key=value` pairs separated by commas)
+    pub fn args_string(&self) -> String {
+        let mut pairs = self.options.to_pairs();
+        if !self.args.is_empty() {
+            pairs.push(self.args.clone());
+        }
+        pairs.join(",")
+    }
 }
 
 impl<I> Default for StreamArgs<I> {
@@ -51,7 +96,8 @@ impl<I> Default for StreamArgs<I> {
     fn default() -> Self {
         StreamArgs {
             host_format: PhantomData::default(),
-            wire_format: "sc16".to_string(),
+            wire_format: WireFormat::Sc16,
+            options: StreamOptions::default(),
             args: "".to_string(),
             // Empty list = just channel 0
             channels: vec![],
@@ -65,7 +111,27 @@ pub struct StreamArgsBuilder<I> {
 
 impl<I> StreamArgsBuilder<I> {
     /// Sets the wire data format
-    pub fn wire_format(self, wire_format: String) -> Self {
+    ///
+    /// This returns an error if UHD cannot convert between the host format I and the wire
+    /// format. Use `wire` to check the formats at compile time instead.
+    pub fn wire_format(self, wire_format: WireFormat) -> Result<Self, Error>
+    where
+        I: Item,
+    {
+        if !wire::converts(I::FORMAT, wire_format) {
+            return Err(Error::with_message(
+                ErrorKind::Value,
+                format!(
+                    "Host format {} cannot be converted to wire format {}",
+                    I::FORMAT,
+                    wire_format
+                ),
+            ));
+        }
+        Ok(self.set_wire_format(wire_format))
+    }
+
+    fn set_wire_format(self, wire_format: WireFormat) -> Self {
         StreamArgsBuilder {
             args: StreamArgs {
                 wire_format,
@@ -74,7 +140,22 @@ impl<I> StreamArgsBuilder<I> {
         }
     }
 
+    /// Sets the wire data format, checking at compile time that UHD can convert between the host
+    /// format I and the wire format
+    ///
+    /// See the `wire` module for the available wire format types.
+    pub fn wire<W>(self) -> Self
+    where
+        W: wire::WireType,
+        I: wire::ConvertsTo<W>,
+    {
+        self.set_wire_format(W::FORMAT)
+    }
+
     /// Sets additional arguments for the stream
+    ///
+    /// These arguments are appended to the arguments generated from the typed options. They can
+    /// be used to set options that this library does not support directly.
     pub fn args(self, args: String) -> Self {
         StreamArgsBuilder {
             args: StreamArgs { args, ..self.args },
@@ -91,12 +172,170 @@ impl<I> StreamArgsBuilder<I> {
         }
     }
 
+    /// Sets the number of samples per packet (`spp`)
+    pub fn spp(mut self, spp: usize) -> Self {
+        self.args.options.spp = Some(spp);
+        self
+    }
+
+    /// Sets the host sample value that corresponds to the full scale of the wire format
+    /// (`fullscale`)
+    pub fn fullscale(mut self, fullscale: f64) -> Self {
+        self.args.options.fullscale = Some(fullscale);
+        self
+    }
+
+    /// Sets the expected peak value of the host samples, used to scale samples when the wire
+    /// format is `sc8` (`peak`)
+    pub fn peak(mut self, peak: f64) -> Self {
+        self.args.options.peak = Some(peak);
+        self
+    }
+
+    /// Sets how a transmit stream recovers from an underflow (`underflow_policy`)
+    pub fn underflow_policy(mut self, policy: UnderflowPolicy) -> Self {
+        self.args.options.underflow_policy = Some(policy);
+        self
+    }
+
+    /// Sets the number of receive frames to allocate in the transport (`num_recv_frames`)
+    pub fn num_recv_frames(mut self, frames: usize) -> Self {
+        self.args.options.num_recv_frames = Some(frames);
+        self
+    }
+
+    /// Sets the size of each receive frame in the transport, in bytes (`recv_frame_size`)
+    pub fn recv_frame_size(mut self, size: usize) -> Self {
+        self.args.options.recv_frame_size = Some(size);
+        self
+    }
+
+    /// Sets the size of each send frame in the transport, in bytes (`send_frame_size`)
+    pub fn send_frame_size(mut self, size: usize) -> Self {
+        self.args.options.send_frame_size = Some(size);
+        self
+    }
+
     /// Builds a StreamArgs with the configured options
     pub fn build(self) -> StreamArgs<I> {
         self.args
     }
 }
 
+/// Formats used to transfer samples between the host and the USRP
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum WireFormat {
+    /// Complex 16-bit integers
+    #[default]
+    Sc16,
+    /// Complex 12-bit integers, packed
+    Sc12,
+    /// Complex 8-bit integers
+    Sc8,
+    /// Complex 32-bit floating-point values
+    Fc32,
+    /// Real 16-bit integers
+    S16,
+    /// Real 8-bit integers
+    S8,
+}
+
+impl WireFormat {
+    /// Returns the name that UHD uses for this format
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WireFormat::Sc16 => "sc16",
+            WireFormat::Sc12 => "sc12",
+            WireFormat::Sc8 => "sc8",
+            WireFormat::Fc32 => "fc32",
+            WireFormat::S16 => "s16",
+            WireFormat::S8 => "s8",
+        }
+    }
+}
+
+impl fmt::Display for WireFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WireFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sc16" => Ok(WireFormat::Sc16),
+            "sc12" => Ok(WireFormat::Sc12),
+            "sc8" => Ok(WireFormat::Sc8),
+            "fc32" => Ok(WireFormat::Fc32),
+            "s16" => Ok(WireFormat::S16),
+            "s8" => Ok(WireFormat::S8),
+            _ => Err(Error::new(ErrorKind::Value)),
+        }
+    }
+}
+
+/// How a transmit stream recovers from an underflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnderflowPolicy {
+    /// Drop samples until the start of the next packet
+    NextPacket,
+    /// Drop samples until the start of the next burst
+    NextBurst,
+}
+
+impl UnderflowPolicy {
+    /// Returns the name that UHD uses for this policy
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnderflowPolicy::NextPacket => "next_packet",
+            UnderflowPolicy::NextBurst => "next_burst",
+        }
+    }
+}
+
+/// Stream options that are passed to UHD in the argument string
+#[derive(Debug, Clone, Default, PartialEq)]
+struct StreamOptions {
+    spp: Option<usize>,
+    fullscale: Option<f64>,
+    peak: Option<f64>,
+    underflow_policy: Option<UnderflowPolicy>,
+    num_recv_frames: Option<usize>,
+    recv_frame_size: Option<usize>,
+    send_frame_size: Option<usize>,
+}
+
+impl StreamOptions {
+    /// Returns the options that are set as `key=value` strings
+    fn to_pairs(&self) -> Vec<String> {
+        let mut pairs = Vec::new();
+        if let Some(spp) = self.spp {
+            pairs.push(format!("spp={}", spp));
+        }
+        if let Some(fullscale) = self.fullscale {
+            pairs.push(format!("fullscale={}", fullscale));
+        }
+        if let Some(peak) = self.peak {
+            pairs.push(format!("peak={}", peak));
+        }
+        if let Some(policy) = self.underflow_policy {
+            pairs.push(format!("underflow_policy={}", policy.as_str()));
+        }
+        if let Some(frames) = self.num_recv_frames {
+            pairs.push(format!("num_recv_frames={}", frames));
+        }
+        if let Some(size) = self.recv_frame_size {
+            pairs.push(format!("recv_frame_size={}", size));
+        }
+        if let Some(size) = self.send_frame_size {
+            pairs.push(format!("send_frame_size={}", size));
+        }
+        pairs
+    }
+}
+
 /// C-compatible version of StreamArgs
 pub(crate) struct StreamArgsC<'args> {
     pub host_format: CString,
@@ -114,14 +353,25 @@ where
     fn try_from(args: &'args StreamArgs<I>) -> Result<Self, Self::Error> {
         Ok(StreamArgsC {
             host_format: CString::new(I::FORMAT)?,
-            wire_format: CString::new(&*args.wire_format)?,
-            args: CString::new(&*args.args)?,
+            wire_format: CString::new(args.wire_format.as_str())?,
+            args: CString::new(args.args_string())?,
             channels: &args.channels,
         })
     }
 }
 
 /// A stream item
+///
+/// The item type is the host (CPU) format: the layout of samples in the buffers passed to
+/// streamers. UHD converts between the host format and the wire format when samples are sent or
+/// received.
+///
+/// Complex samples can be represented as `Complex<T>` or as interleaved `[T; 2]` arrays
+/// (in-phase component first). Both have the same layout in memory. Real-only samples use the
+/// scalar types `f32`, `i16`, and `i8`.
+///
+/// UHD does not have a host format for packed 12-bit samples. To use the `sc12` wire format,
+/// use `fc32` or `sc16` samples on the host.
 pub trait Item {
     /// The format name (examples: `fc32` for Complex<f32>, `sc16` for Complex<i16>)
     const FORMAT: &'static str;
@@ -148,6 +398,120 @@ impl<T: InnerItem> Item for Complex<T> {
     const FORMAT: &'static str = T::FORMAT;
 }
 
+impl<T: InnerItem> Item for [T; 2] {
+    const FORMAT: &'static str = T::FORMAT;
+}
+
+impl Item for f32 {
+    const FORMAT: &'static str = "f32";
+}
+impl Item for i16 {
+    const FORMAT: &'static str = "s16";
+}
+impl Item for i8 {
+    const FORMAT: &'static str = "s8";
+}
+
+/// Marker types for wire formats, used to check host and wire format pairs at compile time
+///
+/// ```
+/// use uhd::{wire, StreamArgs};
+/// use num_complex::Complex32;
+/// let args = StreamArgs::<Complex32>::builder()
+///     .wire::<wire::Sc12>()
+///     .build();
+/// ```
+///
+/// A host format that UHD cannot convert to the wire format is rejected:
+/// ```compile_fail
+/// use uhd::{wire, StreamArgs};
+/// use num_complex::Complex;
+/// let args = StreamArgs::<Complex<i8>>::builder()
+///     .wire::<wire::Fc32>()
+///     .build();
+/// ```
+pub mod wire {
+    use super::{Item, WireFormat};
+    use num_complex::Complex;
+
+    /// A wire format marker type
+    pub trait WireType {
+        /// The corresponding wire format
+        const FORMAT: WireFormat;
+    }
+
+    /// Implemented for host formats that UHD can convert to and from the wire format W
+    pub trait ConvertsTo<W: WireType>: Item {}
+
+    macro_rules! wire_types {
+        ($($(#[$attr:meta])* $name:ident => $format:ident,)*) => {
+            $(
+                $(#[$attr])*
+                #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+                pub struct $name;
+
+                impl WireType for $name {
+                    const FORMAT: WireFormat = WireFormat::$format;
+                }
+            )*
+        };
+    }
+
+    wire_types! {
+        /// Complex 16-bit integers (`sc16`)
+        Sc16 => Sc16,
+        /// Complex 12-bit integers, packed (`sc12`)
+        Sc12 => Sc12,
+        /// Complex 8-bit integers (`sc8`)
+        Sc8 => Sc8,
+        /// Complex 32-bit floating-point values (`fc32`)
+        Fc32 => Fc32,
+        /// Real 16-bit integers (`s16`)
+        S16 => S16,
+        /// Real 8-bit integers (`s8`)
+        S8 => S8,
+    }
+
+    /// Implements ConvertsTo for complex host types with scalar type $inner, in both the
+    /// Complex and interleaved array representations
+    macro_rules! complex_converts {
+        ($inner:ty => $($wire:ident),*) => {
+            $(
+                impl ConvertsTo<$wire> for Complex<$inner> {}
+                impl ConvertsTo<$wire> for [$inner; 2] {}
+            )*
+        };
+    }
+
+    complex_converts!(f64 => Sc16, Sc8, Fc32);
+    complex_converts!(f32 => Sc16, Sc12, Sc8, Fc32);
+    complex_converts!(i16 => Sc16, Sc12, Sc8);
+    complex_converts!(i8 => Sc8);
+
+    impl ConvertsTo<S16> for f32 {}
+    impl ConvertsTo<S8> for f32 {}
+    impl ConvertsTo<S16> for i16 {}
+    impl ConvertsTo<S8> for i8 {}
+
+    /// Returns true if UHD can convert between a host format (the `Item::FORMAT` of a host
+    /// type) and a wire format
+    ///
+    /// This is the run-time equivalent of `ConvertsTo`.
+    pub(crate) fn converts(host_format: &str, wire_format: WireFormat) -> bool {
+        use WireFormat::*;
+        match host_format {
+            "fc64" => matches!(wire_format, Sc16 | Sc8 | Fc32),
+            "fc32" => matches!(wire_format, Sc16 | Sc12 | Sc8 | Fc32),
+            "sc16" => matches!(wire_format, Sc16 | Sc12 | Sc8),
+            "sc8" => wire_format == Sc8,
+            "f32" => matches!(wire_format, S16 | S8),
+            "s16" => wire_format == S16,
+            "s8" => wire_format == S8,
+            _ => false,
+        }
+    }
+}
+
 /// A stream command that can be sent to a USRP to control streaming
 #[derive(Debug, Clone)]
 pub struct StreamCommand {
@@ -223,3 +587,109 @@ impl StreamCommand {
         c_cmd
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_complex::Complex32;
+
+    #[test]
+    fn default_args_string() {
+        let args = StreamArgs::<Complex32>::default();
+        assert_eq!(WireFormat::Sc16, args.wire_format());
+        assert_eq!("", args.args_string());
+    }
+
+    #[test]
+    fn typed_options_args_string() {
+        let args = StreamArgs::<Complex32>::builder()
+            .wire_format(WireFormat::Sc12)
+            .unwrap()
+            .spp(364)
+            .fullscale(1.5)
+            .peak(0.25)
+            .underflow_policy(UnderflowPolicy::NextBurst)
+            .num_recv_frames(256)
+            .recv_frame_size(8000)
+            .send_frame_size(4000)
+            .args("custom=1".to_string())
+            .build();
+        assert_eq!(WireFormat::Sc12, args.wire_format());
+        assert_eq!(
+            "spp=364,fullscale=1.5,peak=0.25,underflow_policy=next_burst,num_recv_frames=256,\
+             recv_frame_size=8000,send_frame_size=4000,custom=1",
+            args.args_string()
+        );
+    }
+
+    #[test]
+    fn wire_format_round_trip() {
+        for format in &[
+            WireFormat::Sc16,
+            WireFormat::Sc12,
+            WireFormat::Sc8,
+            WireFormat::Fc32,
+            WireFormat::S16,
+            WireFormat::S8,
+        ] {
+            assert_eq!(Ok(*format), format.as_str().parse());
+        }
+        assert!("sc17".parse::<WireFormat>().is_err());
+    }
+
+    #[test]
+    fn host_formats() {
+        assert_eq!("sc16", <[i16; 2]>::FORMAT);
+        assert_eq!("fc32", <[f32; 2]>::FORMAT);
+        assert_eq!("sc8", <Complex<i8>>::FORMAT);
+        assert_eq!("s16", <i16 as Item>::FORMAT);
+        assert_eq!("f32", <f32 as Item>::FORMAT);
+    }
+
+    #[test]
+    fn typed_wire_format() {
+        let args = StreamArgs::<[i16; 2]>::builder()
+            .wire::<wire::Sc8>()
+            .build();
+        assert_eq!(WireFormat::Sc8, args.wire_format());
+        let args = StreamArgs::<f32>::builder().wire::<wire::S16>().build();
+        assert_eq!(WireFormat::S16, args.wire_format());
+    }
+
+    /// Checks that the run-time check accepts a pair that ConvertsTo accepts
+    fn check_converts<I, W>()
+    where
+        W: wire::WireType,
+        I: wire::ConvertsTo<W>,
+    {
+        assert!(wire::converts(I::FORMAT, W::FORMAT));
+        assert!(StreamArgs::<I>::new(W::FORMAT).is_ok());
+    }
+
+    #[test]
+    fn run_time_wire_format() {
+        check_converts::<Complex<f64>, wire::Sc16>();
+        check_converts::<Complex<f64>, wire::Sc8>();
+        check_converts::<Complex<f64>, wire::Fc32>();
+        check_converts::<Complex32, wire::Sc16>();
+        check_converts::<Complex32, wire::Sc12>();
+        check_converts::<Complex32, wire::Sc8>();
+        check_converts::<Complex32, wire::Fc32>();
+        check_converts::<[i16; 2], wire::Sc16>();
+        check_converts::<[i16; 2], wire::Sc12>();
+        check_converts::<Complex<i16>, wire::Sc8>();
+        check_converts::<Complex<i8>, wire::Sc8>();
+        check_converts::<f32, wire::S16>();
+        check_converts::<f32, wire::S8>();
+        check_converts::<i16, wire::S16>();
+        check_converts::<i8, wire::S8>();
+
+        let error = StreamArgs::<Complex<i8>>::new(WireFormat::Fc32).unwrap_err();
+        assert_eq!(ErrorKind::Value, error.kind());
+        assert!(StreamArgs::<i8>::builder()
+            .wire_format(WireFormat::Sc8)
+            .is_err());
+        assert!(StreamArgs::<i16>::new(WireFormat::Sc16).is_err());
+        assert!(StreamArgs::<Complex<f64>>::new(WireFormat::Sc12).is_err());
+    }
+}
@@ -0,0 +1,532 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use num_complex::{Complex32, Complex64};
+
+use crate::error::{Error, ErrorKind};
+use crate::fft::fft;
+use crate::receive_metadata::ReceiveErrorKind;
+use crate::receive_streamer::ReceiveStreamer;
+use crate::stream::{StreamArgs, StreamCommand, StreamCommandType, StreamTime};
+use crate::tune_request::TuneRequest;
+use crate::usrp::Usrp;
+
+/// Name of the receive sensor that reports if the local oscillator is locked
+const LO_LOCKED_SENSOR: &str = "lo_locked";
+/// Time between reads of the LO lock sensor
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(1);
+/// Extra time to wait for the samples of each step, in seconds
+const RECEIVE_MARGIN: f64 = 0.5;
+
+/// A window function applied to the samples before each FFT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    /// No window (all coefficients 1)
+    Rectangular,
+    /// Hann window
+    Hann,
+    /// 4-term Blackman-Harris window, which has lower sidelobes than the Hann window
+    BlackmanHarris,
+}
+
+impl Window {
+    /// Returns the coefficients of this window for a transform of the provided length
+    fn coefficients(&self, length: usize) -> Vec<f64> {
+        let cosines = |terms: &[f64]| -> Vec<f64> {
+            (0..length)
+                .map(|n| {
+                    let phase = 2.0 * PI * n as f64 / length as f64;
+                    terms
+                        .iter()
+                        .enumerate()
+                        .map(|(k, &a)| {
+                            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                            sign * a * (k as f64 * phase).cos()
+                        })
+                        .sum()
+                })
+                .collect()
+        };
+        match self {
+            Window::Rectangular => vec![1.0; length],
+            Window::Hann => cosines(&[0.5, 0.5]),
+            Window::BlackmanHarris => cosines(&[0.35875, 0.48829, 0.14128, 0.01168]),
+        }
+    }
+}
+
+/// A power spectrum produced by a `Sweep`
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spectrum {
+    /// The center frequency of each bin, in hertz, in increasing order
+    pub frequencies: Vec<f64>,
+    /// The power in each bin, in dB relative to full scale plus the sweep's calibration offset
+    pub power: Vec<f64>,
+    /// The width of each bin, in hertz
+    pub bin_width: f64,
+}
+
+impl Spectrum {
+    /// Returns the number of bins
+    pub fn len(&self) -> usize {
+        self.frequencies.len()
+    }
+
+    /// Returns true if this spectrum has no bins
+    pub fn is_empty(&self) -> bool {
+        self.frequencies.is_empty()
+    }
+
+    /// Returns the frequency and power of the bin with the highest power, if any
+    pub fn peak(&self) -> Option<(f64, f64)> {
+        self.frequencies
+            .iter()
+            .copied()
+            .zip(self.power.iter().copied())
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+}
+
+/// Settings for a wideband scan that combines many tuning steps into one spectrum
+///
+/// A sweep divides the span from the start frequency to the stop frequency into steps. At each
+/// step it tunes the receive channel, waits for the local oscillator to lock, discards samples
+/// received while the hardware settles, and averages the power of several windowed FFTs. Adjacent
+/// steps overlap so that the edges of each step, where the anti-aliasing filters reduce the
+/// power, can be discarded. The remaining bins of all steps are combined into one `Spectrum`.
+///
+/// Power is calculated relative to full scale, so that a full-scale sine wave in the center of a
+/// bin has a power of 0 dB. A calibration offset (for example, measured with a signal generator
+/// at the gain used for the sweep) can be added to convert this into absolute power.
+///
+/// Example (scanning the full range of a B210):
+///
+/// ```no_run
+/// use uhd::{Sweep, Usrp};
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let usrp = Usrp::open("type=b200")?;
+/// let spectrum = Sweep::new(70e6, 6e9)
+///     .sample_rate(28e6)
+///     .fft_size(2048)
+///     .gain(40.0)
+///     .run(&usrp)?;
+/// if let Some((frequency, power)) = spectrum.peak() {
+///     println!("Strongest signal: {:.3} MHz, {:.1} dBFS", frequency / 1e6, power);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    /// Lowest frequency, in hertz
+    start: f64,
+    /// Highest frequency, in hertz
+    stop: f64,
+    /// Requested sample rate, in samples/second
+    sample_rate: f64,
+    /// Number of samples in each FFT
+    fft_size: usize,
+    /// Fraction of each step's bandwidth that overlaps with adjacent steps
+    overlap: f64,
+    /// Number of FFTs averaged at each step
+    averages: usize,
+    /// Number of samples discarded after tuning
+    settling_samples: usize,
+    /// Window function
+    window: Window,
+    /// Receive channel
+    channel: usize,
+    /// Receive gain, or None to leave the gain unchanged
+    gain: Option<f64>,
+    /// Maximum time to wait for the LO to lock
+    lock_timeout: Duration,
+    /// Offset added to all power values, in dB
+    calibration: f64,
+}
+
+impl Sweep {
+    /// Creates a sweep from a start frequency to a stop frequency (in hertz), with default
+    /// settings
+    ///
+    /// The defaults are a sample rate of 20 MHz, 1024-point FFTs with a Hann window, 25%
+    /// overlap, 8 averages, 10000 settling samples, channel 0, the current gain, a 1 second
+    /// lock timeout, and no calibration offset.
+    pub fn new(start: f64, stop: f64) -> Self {
+        Sweep {
+            start,
+            stop,
+            sample_rate: 20e6,
+            fft_size: 1024,
+            overlap: 0.25,
+            averages: 8,
+            settling_samples: 10_000,
+            window: Window::Hann,
+            channel: 0,
+            gain: None,
+            lock_timeout: Duration::from_secs(1),
+            calibration: 0.0,
+        }
+    }
+
+    /// Sets the sample rate, in samples/second
+    ///
+    /// The sample rate determines the bandwidth of each step.
+    pub fn sample_rate(self, sample_rate: f64) -> Self {
+        Sweep {
+            sample_rate,
+            ..self
+        }
+    }
+
+    /// Sets the number of samples in each FFT, which must be a power of two
+    pub fn fft_size(self, fft_size: usize) -> Self {
+        Sweep { fft_size, ..self }
+    }
+
+    /// Sets the fraction of each step's bandwidth that overlaps with adjacent steps, in the
+    /// range [0, 1)
+    ///
+    /// The part of each step that is used is rounded to a whole number of FFT bins, so the actual
+    /// overlap can differ slightly from this value.
+    pub fn overlap(self, overlap: f64) -> Self {
+        Sweep { overlap, ..self }
+    }
+
+    /// Sets the number of FFTs averaged at each step
+    pub fn averages(self, averages: usize) -> Self {
+        Sweep { averages, ..self }
+    }
+
+    /// Sets the number of samples discarded after each retune
+    pub fn settling_samples(self, settling_samples: usize) -> Self {
+        Sweep {
+            settling_samples,
+            ..self
+        }
+    }
+
+    /// Sets the window function
+    pub fn window(self, window: Window) -> Self {
+        Sweep { window, ..self }
+    }
+
+    /// Sets the receive channel
+    pub fn channel(self, channel: usize) -> Self {
+        Sweep { channel, ..self }
+    }
+
+    /// Sets the overall receive gain, in decibels
+    pub fn gain(self, gain: f64) -> Self {
+        Sweep {
+            gain: Some(gain),
+            ..self
+        }
+    }
+
+    /// Sets the maximum time to wait for the local oscillator to lock after each retune
+    pub fn lock_timeout(self, lock_timeout: Duration) -> Self {
+        Sweep {
+            lock_timeout,
+            ..self
+        }
+    }
+
+    /// Sets an offset (in dB) that is added to every power value
+    pub fn calibration(self, calibration: f64) -> Self {
+        Sweep {
+            calibration,
+            ..self
+        }
+    }
+
+    /// Configures the USRP, runs the sweep, and returns the combined spectrum
+    ///
+    /// This returns an error of kind `ErrorKind::Value` if the settings are not valid, or
+    /// `ErrorKind::Timeout` if the local oscillator does not lock.
+    pub fn run(&self, usrp: &Usrp) -> Result<Spectrum, Error> {
+        self.validate()?;
+        usrp.set_rx_sample_rate(self.sample_rate, self.channel)?;
+        let sample_rate = usrp.get_rx_sample_rate(self.channel)?;
+        if let Some(gain) = self.gain {
+            usrp.set_rx_gain(gain, self.channel, "")?;
+        }
+        let has_lock_sensor = usrp
+            .get_rx_sensor_names(self.channel)?
+            .iter()
+            .any(|name| name == LO_LOCKED_SENSOR);
+
+        let args = StreamArgs::<Complex32>::builder()
+            .channels(vec![self.channel])
+            .build();
+        let mut streamer = usrp.get_rx_stream(&args)?;
+        let window = self.window.coefficients(self.fft_size);
+        let mut samples =
+            vec![Complex32::default(); self.settling_samples + self.fft_size * self.averages];
+        let bin_width = sample_rate / self.fft_size as f64;
+        let usable_bins = usable_bins(self.fft_size, self.overlap);
+
+        let mut spectrum = Spectrum {
+            bin_width,
+            ..Spectrum::default()
+        };
+        for center in plan_steps(self.start, self.stop, usable_bins as f64 * bin_width) {
+            usrp.set_rx_frequency(&TuneRequest::with_frequency(center), self.channel)?;
+            if has_lock_sensor {
+                self.wait_for_lock(usrp)?;
+            }
+            receive_count(&mut streamer, &mut samples, sample_rate)?;
+            let power = averaged_power(&samples[self.settling_samples..], &window);
+            // The tuned frequency can differ slightly from the requested frequency
+            let step = Step {
+                center: usrp.get_rx_frequency(self.channel)?,
+                sample_rate,
+                usable_bins,
+            };
+            step.append_bins(
+                &power,
+                self.start,
+                self.stop,
+                self.calibration,
+                &mut spectrum,
+            );
+        }
+        Ok(spectrum)
+    }
+
+    /// Checks that the settings are valid
+    fn validate(&self) -> Result<(), Error> {
+        let problem = if self.stop.partial_cmp(&self.start) != Some(Ordering::Greater) {
+            Some("Stop frequency must be greater than start frequency")
+        } else if !self.fft_size.is_power_of_two() || self.fft_size < 2 {
+            Some("FFT size must be a power of two and at least 2")
+        } else if !(0.0..1.0).contains(&self.overlap) {
+            Some("Overlap must be at least 0 and less than 1")
+        } else if self.averages == 0 {
+            Some("Number of averages must not be 0")
+        } else if self.sample_rate.partial_cmp(&0.0) != Some(Ordering::Greater) {
+            Some("Sample rate must be greater than 0")
+        } else {
+            None
+        };
+        match problem {
+            Some(problem) => Err(Error::with_message(ErrorKind::Value, problem.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Waits until the receive LO is locked
+    fn wait_for_lock(&self, usrp: &Usrp) -> Result<(), Error> {
+        let deadline = Instant::now() + self.lock_timeout;
+        loop {
+            if usrp
+                .get_rx_sensor(LO_LOCKED_SENSOR, self.channel)?
+                .to_bool()?
+            {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::with_message(
+                    ErrorKind::Timeout,
+                    format!("LO on channel {} did not lock", self.channel),
+                ));
+            }
+            thread::sleep(LOCK_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Returns the number of bins of each step that are used, which is the part of the FFT size
+/// that does not overlap with adjacent steps, rounded to a whole number of bins
+///
+/// Steps are spaced by this many bins, so that the bins of all steps are on the same grid.
+fn usable_bins(fft_size: usize, overlap: f64) -> usize {
+    ((fft_size as f64 * (1.0 - overlap)).round() as usize).max(1)
+}
+
+/// Returns the center frequencies of steps that cover the span from start to stop
+fn plan_steps(start: f64, stop: f64, usable_bandwidth: f64) -> Vec<f64> {
+    let steps = ((stop - start) / usable_bandwidth).ceil().max(1.0) as usize;
+    (0..steps)
+        .map(|i| start + usable_bandwidth * (i as f64 + 0.5))
+        .collect()
+}
+
+/// Receives exactly enough samples to fill a buffer, starting now
+fn receive_count(
+    streamer: &mut ReceiveStreamer<'_, Complex32>,
+    buffer: &mut [Complex32],
+    sample_rate: f64,
+) -> Result<(), Error> {
+    streamer.send_command(&StreamCommand {
+        command_type: StreamCommandType::CountAndDone(buffer.len() as u64),
+        time: StreamTime::Now,
+    })?;
+    let timeout = buffer.len() as f64 / sample_rate + RECEIVE_MARGIN;
+    let mut received = 0;
+    while received < buffer.len() {
+        let metadata = streamer.receive(&mut [&mut buffer[received..]], timeout, false)?;
+        match metadata.try_last_error()? {
+            None => {}
+            Some(error) if error.kind() == ReceiveErrorKind::Timeout => {
+                return Err(Error::with_message(
+                    ErrorKind::Timeout,
+                    format!("Received {} of {} samples", received, buffer.len()),
+                ))
+            }
+            Some(error) => {
+                return Err(Error::with_message(
+                    ErrorKind::Receive(error.kind()),
+                    error.to_string(),
+                ))
+            }
+        }
+        received += metadata.samples();
+        if metadata.try_end_of_burst()? && received < buffer.len() {
+            return Err(Error::with_message(
+                ErrorKind::IncompleteCapture,
+                format!("Received {} of {} samples", received, buffer.len()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the average power in each bin of windowed FFTs of consecutive blocks of samples
+///
+/// The number of blocks is samples.len() / window.len(). The power values are linear and
+/// relative to full scale, and are in increasing frequency order (the bin at index
+/// `window.len() / 2` is at the center frequency).
+fn averaged_power(samples: &[Complex32], window: &[f64]) -> Vec<f64> {
+    let size = window.len();
+    let window_sum: f64 = window.iter().sum();
+    let blocks = samples.len() / size;
+    let scale = 1.0 / (window_sum * window_sum * blocks as f64);
+
+    let mut power = vec![0.0; size];
+    let mut buffer = vec![Complex64::default(); size];
+    for block in samples.chunks_exact(size) {
+        for ((entry, sample), &coefficient) in buffer.iter_mut().zip(block).zip(window) {
+            *entry = Complex64::new(f64::from(sample.re), f64::from(sample.im)) * coefficient;
+        }
+        fft(&mut buffer);
+        // Move negative frequencies to the start
+        for (i, entry) in power.iter_mut().enumerate() {
+            *entry += buffer[(i + size / 2) % size].norm_sqr() * scale;
+        }
+    }
+    power
+}
+
+/// One tuning step of a sweep
+struct Step {
+    /// Center frequency, in hertz
+    center: f64,
+    /// Sample rate, in samples/second
+    sample_rate: f64,
+    /// Number of bins around the center frequency that are used
+    usable_bins: usize,
+}
+
+impl Step {
+    /// Appends the bins from the usable part of this step that are between start and stop to a
+    /// spectrum
+    fn append_bins(
+        &self,
+        power: &[f64],
+        start: f64,
+        stop: f64,
+        calibration: f64,
+        spectrum: &mut Spectrum,
+    ) {
+        let size = power.len();
+        let bin_width = self.sample_rate / size as f64;
+        // Bins are numbered relative to the center bin, and the used bins are as close to the
+        // center as possible
+        let first_usable = -(self.usable_bins as isize / 2);
+        let usable = first_usable..first_usable + self.usable_bins as isize;
+        for (i, &bin_power) in power.iter().enumerate() {
+            let bin = i as isize - (size / 2) as isize;
+            let frequency = self.center + bin as f64 * bin_width;
+            if usable.contains(&bin) && frequency >= start && frequency <= stop {
+                spectrum.frequencies.push(frequency);
+                spectrum.power.push(10.0 * bin_power.log10() + calibration);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_cover_span() {
+        assert_eq!(vec![15.0, 25.0, 35.0], plan_steps(10.0, 40.0, 10.0));
+        assert_eq!(vec![15.0, 25.0, 35.0], plan_steps(10.0, 32.0, 10.0));
+        assert_eq!(vec![12.5], plan_steps(10.0, 11.0, 5.0));
+    }
+
+    #[test]
+    fn full_scale_tone() {
+        let size = 64;
+        let bin = 5;
+        let samples: Vec<Complex32> = (0..size * 4)
+            .map(|n| {
+                let phase = 2.0 * PI * (bin * n) as f64 / size as f64;
+                Complex32::new(phase.cos() as f32, phase.sin() as f32)
+            })
+            .collect();
+        for &window in &[Window::Rectangular, Window::Hann, Window::BlackmanHarris] {
+            let power = averaged_power(&samples, &window.coefficients(size));
+            let peak = power
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .unwrap();
+            assert_eq!(size / 2 + bin, peak.0);
+            assert!((10.0 * peak.1.log10()).abs() < 1e-4, "{:?}", window);
+        }
+    }
+
+    #[test]
+    fn stitch_steps() {
+        let mut spectrum = Spectrum::default();
+        for &center in &[100.0, 106.0] {
+            let step = Step {
+                center,
+                sample_rate: 8.0,
+                usable_bins: 6,
+            };
+            step.append_bins(&[1.0; 8], 97.0, 107.0, -3.0, &mut spectrum);
+        }
+        assert_eq!(
+            vec![97.0, 98.0, 99.0, 100.0, 101.0, 102.0, 103.0, 104.0, 105.0, 106.0, 107.0],
+            spectrum.frequencies
+        );
+        assert!(spectrum.power.iter().all(|&power| power == -3.0));
+
+        // An overlap that is not a whole number of bins
+        let (size, sample_rate) = (1024, 1024e3);
+        let bin_width = sample_rate / size as f64;
+        let usable_bins = usable_bins(size, 0.3);
+        assert_eq!(717, usable_bins);
+        let (start, stop) = (100e6, 105e6);
+        let mut spectrum = Spectrum::default();
+        for center in plan_steps(start, stop, usable_bins as f64 * bin_width) {
+            let step = Step {
+                center,
+                sample_rate,
+                usable_bins,
+            };
+            step.append_bins(&vec![1.0; size], start, stop, 0.0, &mut spectrum);
+        }
+        assert!(spectrum.len() > 3 * usable_bins);
+        for pair in spectrum.frequencies.windows(2) {
+            assert!(((pair[1] - pair[0]) - bin_width).abs() < 1e-6, "{:?}", pair);
+        }
+    }
+}
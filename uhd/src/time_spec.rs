@@ -0,0 +1,323 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::time::Duration;
+
+use crate::error::{Error, ErrorKind};
+
+/// Number of nanoseconds in one second
+const NANOS_PER_SECOND: f64 = 1e9;
+
+/// A time value, represented as an integer number of seconds and a floating-point fraction of
+/// a second
+///
+/// The constructors and arithmetic operators keep the fraction in the range [0, 1). A negative
+/// time has a negative number of seconds and a non-negative fraction: -0.25 seconds is
+/// represented as `seconds: -1, fraction: 0.75`.
+///
+/// Comparisons normalize both values first, so a TimeSpec with a fraction outside [0, 1) is equal
+/// to the normalized TimeSpec that represents the same time.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeSpec {
+    // In some versions of UHD, the corresponding field of uhd::time_spec_t is a time_t.
+    // In other versions, it's a int64_t. The Rust code does conversion to keep this
+    // an i64.
+    pub seconds: i64,
+    pub fraction: f64,
+}
+
+impl TimeSpec {
+    /// Creates a time from a number of seconds and a fraction of a second
+    ///
+    /// The fraction may be outside the range [0, 1). Whole seconds in the fraction are moved into
+    /// the seconds field.
+    pub fn new(seconds: i64, fraction: f64) -> Self {
+        TimeSpec { seconds, fraction }.normalized()
+    }
+
+    /// Creates a time from a floating-point number of seconds
+    pub fn from_secs_f64(seconds: f64) -> Self {
+        let whole = seconds.floor();
+        TimeSpec::new(whole as i64, seconds - whole)
+    }
+
+    /// Returns this time as a floating-point number of seconds
+    ///
+    /// This may lose precision for large times.
+    pub fn as_secs_f64(&self) -> f64 {
+        self.seconds as f64 + self.fraction
+    }
+
+    /// Creates a time from a number of ticks of a clock with the provided rate (ticks/second)
+    ///
+    /// If the rate is an integer, the whole seconds are calculated without rounding errors.
+    pub fn from_ticks(ticks: i64, rate: f64) -> Self {
+        let rate_integer = rate as i64;
+        if rate_integer == 0 {
+            return TimeSpec::from_secs_f64(ticks as f64 / rate);
+        }
+        let rate_fraction = rate - rate_integer as f64;
+        let seconds = ticks.div_euclid(rate_integer);
+        let remaining_ticks =
+            ticks.rem_euclid(rate_integer) as f64 - seconds as f64 * rate_fraction;
+        TimeSpec::new(seconds, remaining_ticks / rate)
+    }
+
+    /// Returns the number of ticks of a clock with the provided rate (ticks/second) in this time,
+    /// rounded to the nearest tick
+    pub fn to_ticks(&self, rate: f64) -> i64 {
+        let time = self.normalized();
+        let rate_integer = rate as i64;
+        let rate_fraction = rate - rate_integer as f64;
+        let fraction_ticks = time.fraction * rate + time.seconds as f64 * rate_fraction;
+        time.seconds * rate_integer + fraction_ticks.round() as i64
+    }
+
+    /// Returns an equivalent time with the fraction in the range [0, 1)
+    pub fn normalized(&self) -> Self {
+        let carry = self.fraction.floor();
+        let mut seconds = self.seconds + carry as i64;
+        let mut fraction = self.fraction - carry;
+        // A very small negative fraction plus 1 can round to 1
+        if fraction >= 1.0 {
+            seconds += 1;
+            fraction -= 1.0;
+        }
+        // Adding 0 changes -0.0 into 0.0
+        TimeSpec {
+            seconds,
+            fraction: fraction + 0.0,
+        }
+    }
+}
+
+impl Add for TimeSpec {
+    type Output = TimeSpec;
+
+    fn add(self, rhs: TimeSpec) -> TimeSpec {
+        TimeSpec::new(self.seconds + rhs.seconds, self.fraction + rhs.fraction)
+    }
+}
+
+impl AddAssign for TimeSpec {
+    fn add_assign(&mut self, rhs: TimeSpec) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for TimeSpec {
+    type Output = TimeSpec;
+
+    fn sub(self, rhs: TimeSpec) -> TimeSpec {
+        TimeSpec::new(self.seconds - rhs.seconds, self.fraction - rhs.fraction)
+    }
+}
+
+impl SubAssign for TimeSpec {
+    fn sub_assign(&mut self, rhs: TimeSpec) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for TimeSpec {
+    type Output = TimeSpec;
+
+    fn neg(self) -> TimeSpec {
+        TimeSpec::new(-self.seconds, -self.fraction)
+    }
+}
+
+impl Ord for TimeSpec {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let left = self.normalized();
+        let right = other.normalized();
+        left.seconds
+            .cmp(&right.seconds)
+            .then_with(|| left.fraction.total_cmp(&right.fraction))
+    }
+}
+
+impl PartialOrd for TimeSpec {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for TimeSpec {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TimeSpec {}
+
+impl From<Duration> for TimeSpec {
+    /// Converts a duration into a time
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the duration has more than i64::MAX seconds.
+    fn from(duration: Duration) -> Self {
+        let seconds = i64::try_from(duration.as_secs()).expect("Duration too long for a TimeSpec");
+        TimeSpec::new(
+            seconds,
+            f64::from(duration.subsec_nanos()) / NANOS_PER_SECOND,
+        )
+    }
+}
+
+impl TryFrom<TimeSpec> for Duration {
+    type Error = Error;
+
+    /// Converts a time into a duration, rounded to the nearest nanosecond
+    ///
+    /// This returns an error if the time is negative.
+    fn try_from(time: TimeSpec) -> Result<Self, Self::Error> {
+        let time = time.normalized();
+        if time.seconds < 0 {
+            return Err(Error::with_message(
+                ErrorKind::Value,
+                format!("Negative time {} cannot be converted into a Duration", time),
+            ));
+        }
+        let nanos = (time.fraction * NANOS_PER_SECOND).round() as u64;
+        Ok(Duration::from_secs(time.seconds as u64) + Duration::from_nanos(nanos))
+    }
+}
+
+impl fmt::Display for TimeSpec {
+    /// Formats this time as a decimal number of seconds
+    ///
+    /// The precision (number of digits after the decimal point) defaults to 9.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = self.normalized();
+        let (sign, time) = if time.seconds < 0 {
+            ("-", -time)
+        } else {
+            ("", time)
+        };
+        let precision = f.precision().unwrap_or(9);
+        let mut seconds = time.seconds;
+        let mut fraction = format!("{:.*}", precision, time.fraction);
+        if fraction.starts_with('1') {
+            // The fraction was rounded up to 1
+            seconds += 1;
+            fraction = format!("{:.*}", precision, 0.0);
+        }
+        // Skip the leading 0
+        write!(f, "{}{}{}", sign, seconds, &fraction[1..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_time(time: TimeSpec, seconds: i64, fraction: f64) {
+        assert_eq!(seconds, time.seconds, "seconds of {:?}", time);
+        assert!(
+            (time.fraction - fraction).abs() < 1e-12,
+            "fraction of {:?}",
+            time
+        );
+    }
+
+    #[test]
+    fn normalize_carry() {
+        assert_time(TimeSpec::new(1, 2.5), 3, 0.5);
+        assert_time(TimeSpec::new(1, 1.0), 2, 0.0);
+        assert_time(TimeSpec::new(1, -0.25), 0, 0.75);
+        assert_time(TimeSpec::new(0, -2.25), -3, 0.75);
+        assert_time(TimeSpec::new(0, -1e-18), 0, 0.0);
+        assert!(TimeSpec::new(5, -0.0).fraction.is_sign_positive());
+    }
+
+    #[test]
+    fn add_sub() {
+        let a = TimeSpec::new(10, 0.75);
+        let b = TimeSpec::new(2, 0.5);
+        assert_time(a + b, 13, 0.25);
+        assert_time(a - b, 8, 0.25);
+        assert_time(b - a, -9, 0.75);
+        assert_time(-b, -3, 0.5);
+
+        let mut c = a;
+        c += b;
+        c -= b;
+        assert_eq!(a, c);
+    }
+
+    #[test]
+    fn negative_from_secs() {
+        assert_time(TimeSpec::from_secs_f64(-0.25), -1, 0.75);
+        assert_time(TimeSpec::from_secs_f64(3.5), 3, 0.5);
+        assert!((TimeSpec::from_secs_f64(-2.75).as_secs_f64() + 2.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ticks() {
+        let time = TimeSpec::from_ticks(25_000_001, 10e6);
+        assert_time(time, 2, 0.5000001);
+        assert_eq!(25_000_001, time.to_ticks(10e6));
+
+        let negative = TimeSpec::from_ticks(-1, 4.0);
+        assert_time(negative, -1, 0.75);
+        assert_eq!(-1, negative.to_ticks(4.0));
+
+        // Non-integer rate
+        let time = TimeSpec::from_ticks(7, 2.5);
+        assert_time(time, 2, 0.8);
+        assert_eq!(7, time.to_ticks(2.5));
+
+        // Large times do not lose ticks
+        let time = TimeSpec::new(1_000_000_000, 0.0) + TimeSpec::from_ticks(3, 200e6);
+        assert_eq!(200_000_000_000_000_003, time.to_ticks(200e6));
+    }
+
+    #[test]
+    fn duration() {
+        let duration = Duration::new(12, 345_678_901);
+        let time = TimeSpec::from(duration);
+        assert_time(time, 12, 0.345678901);
+        assert_eq!(duration, Duration::try_from(time).unwrap());
+
+        let error = Duration::try_from(TimeSpec::from_secs_f64(-0.5)).unwrap_err();
+        assert_eq!(ErrorKind::Value, error.kind());
+    }
+
+    #[test]
+    fn ordering() {
+        let unnormalized = TimeSpec {
+            seconds: 1,
+            fraction: 1.5,
+        };
+        assert_eq!(TimeSpec::new(2, 0.5), unnormalized);
+        assert!(TimeSpec::new(-1, 0.9) < TimeSpec::new(0, 0.0));
+        assert!(TimeSpec::new(3, 0.1) > TimeSpec::new(2, 0.9));
+        let mut times = vec![
+            TimeSpec::new(1, 0.5),
+            TimeSpec::new(-1, 0.5),
+            TimeSpec::new(1, 0.25),
+        ];
+        times.sort();
+        assert_eq!(
+            vec![
+                TimeSpec::new(-1, 0.5),
+                TimeSpec::new(1, 0.25),
+                TimeSpec::new(1, 0.5)
+            ],
+            times
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!("12.250000000", TimeSpec::new(12, 0.25).to_string());
+        assert_eq!("-0.250", format!("{:.3}", TimeSpec::from_secs_f64(-0.25)));
+        assert_eq!("-2.5", format!("{:.1}", TimeSpec::new(-3, 0.5)));
+        assert_eq!("4.000", format!("{:.3}", TimeSpec::new(3, 0.99999)));
+        assert_eq!("7", format!("{:.0}", TimeSpec::new(7, 0.25)));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::async_metadata::{AsyncEvent, AsyncMetadata};
+use crate::error::{Error, ErrorKind};
+use crate::transmit_metadata::TransmitMetadata;
+use crate::transmit_streamer::TransmitStreamer;
+use crate::utils::check_buffers;
+use crate::TimeSpec;
+
+/// The result of a burst sent with `TransmitStreamer::send_at`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurstStatus {
+    /// The USRP acknowledged the burst on every channel with no errors
+    OnTime,
+    /// The samples reached the USRP after the start time, so the burst was not transmitted
+    TimeError,
+    /// The USRP acknowledged the burst, but ran out of samples at least once while
+    /// transmitting it
+    Underflow,
+    /// The USRP acknowledged the burst, but packets were lost between the host and the device
+    SequenceError,
+}
+
+impl<I> TransmitStreamer<'_, I> {
+    /// Sends samples as one burst that starts at a device time, and waits for the USRP to
+    /// acknowledge it
+    ///
+    /// buffers: One or more buffers (one per channel) containing the samples to send. All
+    /// buffers must have the same length.
+    ///
+    /// start: The device time when the first sample should be transmitted
+    ///
+    /// timeout: The maximum time to wait for each send operation and for each asynchronous
+    /// message, in seconds. This must be longer than the time from now until the end of the
+    /// burst.
+    ///
+    /// The buffers are sent in as many send operations as necessary. The first one has the start
+    /// time and the start of burst flag. After all samples have been sent, a send operation with
+    /// no samples and the end of burst flag ends the burst.
+    /// This function then reads asynchronous messages until every channel has reported a
+    /// `BurstAck` or the USRP reports a `TimeError`.
+    ///
+    /// Asynchronous messages left over from earlier bursts can be mistaken for messages about
+    /// this burst, so all earlier bursts should be acknowledged before calling this function.
+    ///
+    /// This function returns an error of kind `ErrorKind::Timeout` if the samples could not be
+    /// sent or no acknowledgement arrived before the timeout expired.
+    pub fn send_at(
+        &mut self,
+        buffers: &[&[I]],
+        start: TimeSpec,
+        timeout: f64,
+    ) -> Result<BurstStatus, Error> {
+        let num_channels = self.try_num_channels()?;
+        let buffer_length = check_buffers(buffers, num_channels)?;
+
+        let mut metadata = TransmitMetadata::try_with_options(true, false, Some(&start))?;
+        let mut sent = 0;
+        while sent != buffer_length {
+            let remaining: Vec<&[I]> = buffers.iter().map(|buffer| &buffer[sent..]).collect();
+            let samples = self.send_with_metadata(&remaining, &mut metadata, timeout)?;
+            if samples == 0 {
+                return Err(Error::with_message(
+                    ErrorKind::Timeout,
+                    format!("Sent {} of {} samples", sent, buffer_length),
+                ));
+            }
+            sent += samples;
+            // The rest of the buffers continue the burst
+            metadata = TransmitMetadata::try_with_options(false, false, None)?;
+        }
+        // End the burst. If the buffers are empty, this is also the start of the burst.
+        let mut end_metadata = if buffer_length == 0 {
+            TransmitMetadata::try_with_options(true, true, Some(&start))?
+        } else {
+            TransmitMetadata::try_with_options(false, true, None)?
+        };
+        let end: Vec<&[I]> = buffers.iter().map(|buffer| &buffer[sent..]).collect();
+        self.send_with_metadata(&end, &mut end_metadata, timeout)?;
+
+        let mut tracker = BurstTracker::new(num_channels);
+        let mut message = AsyncMetadata::try_new()?;
+        loop {
+            if !self.receive_async_message_into(&mut message, timeout)? {
+                return Err(Error::with_message(
+                    ErrorKind::Timeout,
+                    "No burst acknowledgement received".to_string(),
+                ));
+            }
+            if let Some(status) = tracker.event(message.try_channel()?, message.try_event()?) {
+                return Ok(status);
+            }
+        }
+    }
+}
+
+/// Combines the asynchronous events from all channels of a burst
+struct BurstTracker {
+    /// True for each channel that has acknowledged the burst
+    acknowledged: Vec<bool>,
+    /// The status to report when all channels have acknowledged the burst
+    status: BurstStatus,
+}
+
+impl BurstTracker {
+    fn new(num_channels: usize) -> Self {
+        BurstTracker {
+            acknowledged: vec![false; num_channels],
+            status: BurstStatus::OnTime,
+        }
+    }
+
+    /// Records an event, and returns the status of the burst if it is complete
+    fn event(&mut self, channel: usize, event: AsyncEvent) -> Option<BurstStatus> {
+        match event {
+            AsyncEvent::BurstAck => {
+                if let Some(acknowledged) = self.acknowledged.get_mut(channel) {
+                    *acknowledged = true;
+                }
+                if self.acknowledged.iter().all(|&acknowledged| acknowledged) {
+                    return Some(self.status);
+                }
+            }
+            // The USRP discards the rest of a late burst without acknowledging it
+            AsyncEvent::TimeError => return Some(BurstStatus::TimeError),
+            AsyncEvent::Underflow | AsyncEvent::UnderflowInPacket => {
+                self.status = BurstStatus::Underflow
+            }
+            AsyncEvent::SequenceError | AsyncEvent::SequenceErrorInBurst => {
+                if self.status == BurstStatus::OnTime {
+                    self.status = BurstStatus::SequenceError;
+                }
+            }
+            AsyncEvent::UserPayload | AsyncEvent::Other(_) => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_channels_acknowledge() {
+        let mut tracker = BurstTracker::new(2);
+        assert_eq!(None, tracker.event(1, AsyncEvent::BurstAck));
+        assert_eq!(None, tracker.event(1, AsyncEvent::BurstAck));
+        assert_eq!(
+            Some(BurstStatus::OnTime),
+            tracker.event(0, AsyncEvent::BurstAck)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let mut tracker = BurstTracker::new(1);
+        assert_eq!(None, tracker.event(0, AsyncEvent::SequenceErrorInBurst));
+        assert_eq!(None, tracker.event(0, AsyncEvent::Underflow));
+        assert_eq!(None, tracker.event(0, AsyncEvent::SequenceError));
+        assert_eq!(
+            Some(BurstStatus::Underflow),
+            tracker.event(0, AsyncEvent::BurstAck)
+        );
+
+        let mut tracker = BurstTracker::new(2);
+        assert_eq!(
+            Some(BurstStatus::TimeError),
+            tracker.event(0, AsyncEvent::TimeError)
+        );
+    }
+}
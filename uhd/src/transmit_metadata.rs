@@ -1,6 +1,6 @@
 use std::ptr;
 
-use crate::error::check_status;
+use crate::error::{check_status, Error};
 use crate::utils::copy_string;
 use crate::TimeSpec;
 
@@ -17,52 +17,104 @@ impl TransmitMetadata {
         Default::default()
     }
 
+    /// Creates a metadata object with the default options, returning an error if UHD cannot
+    /// allocate it
+    pub fn try_new() -> Result<Self, Error> {
+        let mut handle: uhd_sys::uhd_tx_metadata_handle = ptr::null_mut();
+        check_status(unsafe {
+            uhd_sys::uhd_tx_metadata_make(&mut handle, false, 0, 0.1, true, true)
+        })?;
+        Ok(TransmitMetadata { handle, samples: 0 })
+    }
+
+    /// Creates transmit metadata with the provided burst flags and optional transmit time
+    ///
+    /// start_of_burst: True if the samples sent with this metadata start a burst
+    ///
+    /// end_of_burst: True if the samples sent with this metadata end a burst
+    ///
+    /// time_spec: The time when the first sample should be transmitted, or None to transmit
+    /// as soon as possible
+    pub fn with_options(
+        start_of_burst: bool,
+        end_of_burst: bool,
+        time_spec: Option<&TimeSpec>,
+    ) -> Self {
+        Self::try_with_options(start_of_burst, end_of_burst, time_spec).unwrap()
+    }
+
+    /// Creates transmit metadata with the provided burst flags and optional transmit time,
+    /// returning an error if UHD cannot allocate it
+    pub fn try_with_options(
+        start_of_burst: bool,
+        end_of_burst: bool,
+        time_spec: Option<&TimeSpec>,
+    ) -> Result<Self, Error> {
+        let mut handle: uhd_sys::uhd_tx_metadata_handle = ptr::null_mut();
+        let (seconds, fraction) = time_spec
+            .map(|time| (time.seconds, time.fraction))
+            .unwrap_or((0, 0.0));
+        check_status(unsafe {
+            uhd_sys::uhd_tx_metadata_make(
+                &mut handle,
+                time_spec.is_some(),
+                seconds,
+                fraction,
+                start_of_burst,
+                end_of_burst,
+            )
+        })?;
+        Ok(TransmitMetadata { handle, samples: 0 })
+    }
+
     /// Returns the timestamp of (the first?) of the received samples, according to the USRP's
     /// internal clock
     pub fn time_spec(&self) -> Option<TimeSpec> {
-        if self.has_time_spec() {
-            let mut time = TimeSpec::default();
-            let mut seconds_time_t: libc::time_t = Default::default();
-            let mut seconds_time_t_i64: i64 = seconds_time_t as i64;
+        self.try_time_spec().unwrap()
+    }
 
+    /// Returns the transmit time, or an error if it could not be read
+    pub fn try_time_spec(&self) -> Result<Option<TimeSpec>, Error> {
+        if self.has_time_spec()? {
+            let mut time = TimeSpec::default();
             check_status(unsafe {
-                uhd_sys::uhd_tx_metadata_time_spec(
-                    self.handle,
-                    &mut seconds_time_t_i64 as *mut i64,
-                    &mut time.fraction,
-                )
-            })
-            .unwrap();
-            // Convert seconds from time_t to i64
-            time.seconds = seconds_time_t.into();
-            Some(time)
+                uhd_sys::uhd_tx_metadata_time_spec(self.handle, &mut time.seconds, &mut time.fraction)
+            })?;
+            Ok(Some(time))
         } else {
-            None
+            Ok(None)
         }
     }
 
     /// Returns true if this metadata object has a time
-    fn has_time_spec(&self) -> bool {
+    fn has_time_spec(&self) -> Result<bool, Error> {
         let mut has = false;
-        check_status(unsafe { uhd_sys::uhd_tx_metadata_has_time_spec(self.handle, &mut has) })
-            .unwrap();
-        has
+        check_status(unsafe { uhd_sys::uhd_tx_metadata_has_time_spec(self.handle, &mut has) })?;
+        Ok(has)
     }
 
     /// Returns true if the received samples are at the beginning of a burst
     pub fn start_of_burst(&self) -> bool {
+        self.try_start_of_burst().unwrap()
+    }
+
+    /// Returns the start of burst flag, or an error if it could not be read
+    pub fn try_start_of_burst(&self) -> Result<bool, Error> {
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_tx_metadata_start_of_burst(self.handle, &mut value) })
-            .unwrap();
-        value
+        check_status(unsafe { uhd_sys::uhd_tx_metadata_start_of_burst(self.handle, &mut value) })?;
+        Ok(value)
     }
 
     /// Returns true if the received samples are at the end of a burst
     pub fn end_of_burst(&self) -> bool {
+        self.try_end_of_burst().unwrap()
+    }
+
+    /// Returns the end of burst flag, or an error if it could not be read
+    pub fn try_end_of_burst(&self) -> Result<bool, Error> {
         let mut value = false;
-        check_status(unsafe { uhd_sys::uhd_tx_metadata_end_of_burst(self.handle, &mut value) })
-            .unwrap();
-        value
+        check_status(unsafe { uhd_sys::uhd_tx_metadata_end_of_burst(self.handle, &mut value) })?;
+        Ok(value)
     }
 
     /// Returns the number of samples received
@@ -86,9 +138,7 @@ unsafe impl Sync for TransmitMetadata {}
 
 impl Default for TransmitMetadata {
     fn default() -> Self {
-        let mut handle: uhd_sys::uhd_tx_metadata_handle = ptr::null_mut();
-        check_status(unsafe { uhd_sys::uhd_tx_metadata_make(&mut handle, false, 0, 0.1, true, true) }).unwrap();
-        TransmitMetadata { handle, samples: 0 }
+        TransmitMetadata::try_new().unwrap()
     }
 }
 
@@ -104,10 +154,10 @@ mod fmt {
 
     impl Debug for TransmitMetadata {
         fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-            f.debug_struct("ReceiveMetadata")
-                .field("time_spec", &self.time_spec())
-                .field("start_of_burst", &self.start_of_burst())
-                .field("end_of_burst", &self.end_of_burst())
+            f.debug_struct("TransmitMetadata")
+                .field("time_spec", &self.try_time_spec())
+                .field("start_of_burst", &self.try_start_of_burst())
+                .field("end_of_burst", &self.try_end_of_burst())
                 .finish()
         }
     }
@@ -0,0 +1,865 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TryRecvError};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::async_metadata::{AsyncEvent, AsyncMetadata};
+use crate::error::{Error, ErrorKind};
+use crate::set_thread_priority;
+use crate::transmit_metadata::TransmitMetadata;
+use crate::transmit_streamer::{OwnedTransmitStreamer, TransmitStreamer};
+
+/// Sends samples to a USRP from a dedicated thread
+///
+/// A pipeline is created using a `TransmitPipelineBuilder`. Producer threads get empty blocks from
+/// a `TransmitSender`, fill them, and submit them. The pipeline thread sends each block with
+/// `TransmitStreamer::send` and then returns it to the pool of empty blocks, so no allocation
+/// happens after the pipeline starts.
+///
+/// The first non-empty block after the pipeline starts (or after the end of a burst) is sent with
+/// the start-of-burst flag. Empty blocks are not sent. Calling `TransmitSender::end_burst()` ends
+/// the current burst.
+///
+/// Between blocks, the pipeline thread reads asynchronous messages from the USRP and counts
+/// underflows, sequence errors, and time errors in the pipeline statistics.
+///
+/// Example:
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use num_complex::Complex32;
+/// use uhd::{StreamArgs, TransmitPipelineBuilder, Usrp, WireFormat};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let usrp = Arc::new(Usrp::open("")?);
+/// let streamer = usrp.get_tx_stream_owned(&StreamArgs::<Complex32>::new(WireFormat::Sc16)?)?;
+/// let pipeline = TransmitPipelineBuilder::new().block_size(8192).start(streamer)?;
+///
+/// let sender = pipeline.sender();
+/// for _ in 0..100 {
+///     let mut block = sender.acquire()?;
+///     for sample in block.channel_mut(0) {
+///         *sample = Complex32::new(0.5, 0.0);
+///     }
+///     block.set_len(block.capacity());
+///     sender.submit(block)?;
+/// }
+/// sender.end_burst()?;
+/// pipeline.stop()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TransmitPipeline<I> {
+    sender: TransmitSender<I>,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<OwnedTransmitStreamer<I>>>,
+}
+
+impl<I> TransmitPipeline<I>
+where
+    I: Copy + Default + Send + 'static,
+{
+    /// Returns a handle that producer threads can use to submit blocks
+    pub fn sender(&self) -> TransmitSender<I> {
+        self.sender.clone()
+    }
+
+    /// Returns true if the pipeline thread is still sending
+    pub fn is_running(&self) -> bool {
+        self.shared.running.load(Ordering::Acquire)
+    }
+
+    /// Returns a snapshot of the pipeline statistics
+    pub fn stats(&self) -> TransmitPipelineStats {
+        self.shared.stats.snapshot()
+    }
+
+    /// Sends all blocks that have already been submitted, ends the current burst, stops the
+    /// pipeline thread, and returns the streamer
+    ///
+    /// If the pipeline thread stopped because of an error, that error is returned instead.
+    pub fn stop(mut self) -> Result<OwnedTransmitStreamer<I>, Error> {
+        let streamer = self.stop_thread();
+        match self.shared.error.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(streamer.expect("Transmit pipeline thread already stopped")),
+        }
+    }
+
+    fn stop_thread(&mut self) -> Option<OwnedTransmitStreamer<I>> {
+        let thread = self.thread.take()?;
+        // If the thread has already exited, the message can't be delivered
+        let _ = self.sender.blocks.send(Message::Stop);
+        match thread.join() {
+            Ok(streamer) => Some(streamer),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl<I> Drop for TransmitPipeline<I> {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.sender.blocks.send(Message::Stop);
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A builder for a transmit pipeline
+#[derive(Debug, Clone)]
+pub struct TransmitPipelineBuilder {
+    block_size: usize,
+    num_blocks: usize,
+    timeout: f64,
+    priority: Option<f32>,
+}
+
+impl Default for TransmitPipelineBuilder {
+    fn default() -> Self {
+        TransmitPipelineBuilder {
+            block_size: 16384,
+            num_blocks: 2,
+            timeout: 0.1,
+            priority: Some(0.5),
+        }
+    }
+}
+
+impl TransmitPipelineBuilder {
+    /// Creates a builder with default settings
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the maximum number of samples per channel in each block
+    pub fn block_size(self, block_size: usize) -> Self {
+        TransmitPipelineBuilder { block_size, ..self }
+    }
+
+    /// Sets the number of blocks that can be filled or waiting to be sent at the same time
+    ///
+    /// The default of 2 allows one block to be filled while the other is being sent.
+    pub fn num_blocks(self, num_blocks: usize) -> Self {
+        TransmitPipelineBuilder { num_blocks, ..self }
+    }
+
+    /// Sets the timeout for each send operation, in seconds
+    pub fn timeout(self, timeout: f64) -> Self {
+        TransmitPipelineBuilder { timeout, ..self }
+    }
+
+    /// Sets the real-time scheduling priority of the pipeline thread, in the range [0, 1]
+    ///
+    /// If this is None, the thread runs at normal priority. Failing to set the priority (for
+    /// example, because the process does not have permission) is not an error.
+    pub fn priority(self, priority: Option<f32>) -> Self {
+        TransmitPipelineBuilder { priority, ..self }
+    }
+
+    /// Allocates the blocks and starts the pipeline thread
+    ///
+    /// The streamer is moved into the pipeline thread, so it must be an owned streamer (created
+    /// with `Usrp::get_tx_stream_owned`).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the block size or number of blocks is zero.
+    pub fn start<I>(self, streamer: OwnedTransmitStreamer<I>) -> Result<TransmitPipeline<I>, Error>
+    where
+        I: Copy + Default + Send + 'static,
+    {
+        assert_ne!(self.block_size, 0, "Block size must not be zero");
+        assert_ne!(self.num_blocks, 0, "Number of blocks must not be zero");
+        let num_channels = streamer.try_num_channels()?;
+        let metadata = BurstMetadata {
+            start: TransmitMetadata::try_with_options(true, false, None)?,
+            middle: TransmitMetadata::try_with_options(false, false, None)?,
+            end: TransmitMetadata::try_with_options(false, true, None)?,
+        };
+        let async_metadata = AsyncMetadata::try_new()?;
+
+        let (free_tx, free_rx) = mpsc::channel();
+        for _ in 0..self.num_blocks {
+            // Can't fail because free_rx still exists
+            let _ = free_tx.send(TransmitBlock::new(num_channels, self.block_size));
+        }
+        // There are never more than num_blocks blocks, so sending a block never waits
+        let (blocks_tx, blocks_rx) = mpsc::sync_channel(self.num_blocks + 1);
+
+        let shared = Arc::new(Shared {
+            running: AtomicBool::new(true),
+            stats: Counters::default(),
+            error: Mutex::new(None),
+        });
+        let thread_shared = Arc::clone(&shared);
+        let thread = thread::Builder::new()
+            .name("uhd-tx-pipeline".into())
+            .spawn(move || {
+                if let Some(priority) = self.priority {
+                    let _ = set_thread_priority(priority, true);
+                }
+                run_transmit(
+                    streamer,
+                    metadata,
+                    async_metadata,
+                    blocks_rx,
+                    free_tx,
+                    &thread_shared,
+                    self.timeout,
+                )
+            })
+            .expect("Failed to spawn transmit pipeline thread");
+
+        Ok(TransmitPipeline {
+            sender: TransmitSender {
+                blocks: blocks_tx,
+                free: Arc::new(Mutex::new(free_rx)),
+            },
+            shared,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// Messages from senders to the pipeline thread
+enum Message<I> {
+    Block(TransmitBlock<I>),
+    EndOfBurst,
+    Stop,
+}
+
+/// The body of the pipeline thread
+fn run_transmit<I>(
+    mut streamer: OwnedTransmitStreamer<I>,
+    metadata: BurstMetadata,
+    mut async_metadata: AsyncMetadata,
+    blocks: Receiver<Message<I>>,
+    free: Sender<TransmitBlock<I>>,
+    shared: &Shared,
+    timeout: f64,
+) -> OwnedTransmitStreamer<I> {
+    let stats = &shared.stats;
+    let mut sink = StreamerSink {
+        streamer: &mut streamer,
+        metadata,
+        stats,
+        timeout,
+    };
+    let mut state = BurstState::default();
+
+    let result = loop {
+        let result = match blocks.recv_timeout(Duration::from_millis(10)) {
+            Ok(Message::Block(mut block)) => {
+                let result = state.block(&mut sink, &block);
+                block.len = 0;
+                // If all senders have been dropped, the block is not needed
+                let _ = free.send(block);
+                result
+            }
+            Ok(Message::EndOfBurst) => state.end(&mut sink),
+            Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => break Ok(()),
+            Err(RecvTimeoutError::Timeout) => Ok(()),
+        };
+        let result = result
+            .and_then(|_| read_async_messages(sink.streamer, &mut async_metadata, 0.0, stats));
+        if let Err(e) = result {
+            break Err(e);
+        }
+    };
+
+    let result = result.and_then(|_| {
+        flush(&mut state, &mut sink, &blocks)?;
+        // Collect messages about the final burst
+        read_async_messages(sink.streamer, &mut async_metadata, timeout, stats)
+    });
+
+    if let Err(e) = result {
+        stats.errors.fetch_add(1, Ordering::Relaxed);
+        *shared.error.lock().unwrap() = Some(e);
+    }
+    shared.running.store(false, Ordering::Release);
+    streamer
+}
+
+/// Something that the pipeline thread sends blocks to (a transmit streamer, or a mock in tests)
+trait BlockSink<I> {
+    /// Sends the samples in a block and returns the number of samples per channel sent
+    ///
+    /// If start_of_burst is true, the first samples are sent with the start-of-burst flag.
+    fn send_block(
+        &mut self,
+        block: &TransmitBlock<I>,
+        start_of_burst: bool,
+    ) -> Result<usize, Error>;
+    /// Ends the current burst
+    fn end_burst(&mut self) -> Result<(), Error>;
+}
+
+/// Keeps track of whether a burst is in progress, and sets the burst flags accordingly
+#[derive(Debug, Default)]
+struct BurstState {
+    in_burst: bool,
+}
+
+impl BurstState {
+    /// Sends a block, starting a new burst if no burst is in progress
+    ///
+    /// Empty blocks are ignored.
+    fn block<I, S: BlockSink<I>>(
+        &mut self,
+        sink: &mut S,
+        block: &TransmitBlock<I>,
+    ) -> Result<(), Error> {
+        if block.len == 0 {
+            return Ok(());
+        }
+        let sent = sink.send_block(block, !self.in_burst)?;
+        // If nothing was sent, the start-of-burst flag was not sent either
+        self.in_burst |= sent != 0;
+        Ok(())
+    }
+
+    /// Ends the current burst, if any
+    fn end<I, S: BlockSink<I>>(&mut self, sink: &mut S) -> Result<(), Error> {
+        if self.in_burst {
+            sink.end_burst()?;
+            self.in_burst = false;
+        }
+        Ok(())
+    }
+}
+
+/// Sends the blocks that were submitted before the pipeline stopped, and then ends the current
+/// burst
+fn flush<I, S: BlockSink<I>>(
+    state: &mut BurstState,
+    sink: &mut S,
+    messages: &Receiver<Message<I>>,
+) -> Result<(), Error> {
+    loop {
+        match messages.try_recv() {
+            Ok(Message::Block(block)) => state.block(sink, &block)?,
+            Ok(Message::EndOfBurst) => state.end(sink)?,
+            Ok(Message::Stop) => {}
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => break,
+        }
+    }
+    state.end(sink)
+}
+
+/// Reusable metadata for the parts of a burst
+struct BurstMetadata {
+    /// Metadata for the first samples of a burst
+    start: TransmitMetadata,
+    /// Metadata for samples in the middle of a burst
+    middle: TransmitMetadata,
+    /// Metadata for the (empty) end of a burst
+    end: TransmitMetadata,
+}
+
+/// Sends blocks through a transmit streamer
+struct StreamerSink<'a, I> {
+    streamer: &'a mut OwnedTransmitStreamer<I>,
+    metadata: BurstMetadata,
+    stats: &'a Counters,
+    timeout: f64,
+}
+
+impl<I> BlockSink<I> for StreamerSink<'_, I> {
+    fn send_block(
+        &mut self,
+        block: &TransmitBlock<I>,
+        start_of_burst: bool,
+    ) -> Result<usize, Error> {
+        let mut sent = 0;
+        while sent < block.len {
+            let metadata = if sent == 0 && start_of_burst {
+                &mut self.metadata.start
+            } else {
+                &mut self.metadata.middle
+            };
+            let sent_now = self.streamer.send_planes(
+                &block.data,
+                block.block_size,
+                sent,
+                block.len - sent,
+                metadata,
+                self.timeout,
+            )?;
+            if sent_now == 0 {
+                // Timed out without sending anything. Discard the rest of the block so that a
+                // stalled device can't block the pipeline forever.
+                self.stats.timeouts.fetch_add(1, Ordering::Relaxed);
+                break;
+            }
+            sent += sent_now;
+        }
+        self.stats.blocks.fetch_add(1, Ordering::Relaxed);
+        self.stats.samples.fetch_add(sent as u64, Ordering::Relaxed);
+        Ok(sent)
+    }
+
+    /// Ends the current burst by sending no samples with the end-of-burst flag
+    fn end_burst(&mut self) -> Result<(), Error> {
+        self.streamer
+            .send_planes(&[], 0, 0, 0, &mut self.metadata.end, self.timeout)?;
+        self.stats.bursts.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Reads asynchronous messages until none are available, and updates the statistics
+///
+/// timeout applies to each message.
+fn read_async_messages<I>(
+    streamer: &mut TransmitStreamer<'_, I>,
+    metadata: &mut AsyncMetadata,
+    timeout: f64,
+    stats: &Counters,
+) -> Result<(), Error> {
+    while streamer.receive_async_message_into(metadata, timeout)? {
+        stats.count_event(metadata.try_event()?);
+    }
+    Ok(())
+}
+
+/// Maximum time that `TransmitSender::acquire` holds the lock on the free blocks
+const ACQUIRE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A handle used to submit blocks of samples to a transmit pipeline
+///
+/// Senders can be cloned and moved to other threads.
+pub struct TransmitSender<I> {
+    blocks: SyncSender<Message<I>>,
+    free: Arc<Mutex<Receiver<TransmitBlock<I>>>>,
+}
+
+impl<I> Clone for TransmitSender<I> {
+    fn clone(&self) -> Self {
+        TransmitSender {
+            blocks: self.blocks.clone(),
+            free: Arc::clone(&self.free),
+        }
+    }
+}
+
+impl<I> TransmitSender<I>
+where
+    I: Copy,
+{
+    /// Waits until an empty block is available and returns it
+    ///
+    /// This function returns an error if the pipeline has stopped.
+    pub fn acquire(&self) -> Result<TransmitBlock<I>, Error> {
+        loop {
+            // Release the lock between attempts so that other senders are not blocked for long
+            let free = self.free.lock().unwrap();
+            match free.recv_timeout(ACQUIRE_INTERVAL) {
+                Ok(block) => return Ok(block),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(Error::new(ErrorKind::PipelineStopped))
+                }
+            }
+        }
+    }
+
+    /// Returns an empty block if one is available, without waiting
+    ///
+    /// If another sender is waiting in `acquire`, no blocks are available and this returns None.
+    pub fn try_acquire(&self) -> Option<TransmitBlock<I>> {
+        match self.free.try_lock() {
+            Ok(free) => free.try_recv().ok(),
+            Err(TryLockError::WouldBlock) => None,
+            Err(TryLockError::Poisoned(e)) => panic!("{}", e),
+        }
+    }
+
+    /// Submits a block to be sent
+    ///
+    /// This function returns an error if the pipeline has stopped.
+    pub fn submit(&self, block: TransmitBlock<I>) -> Result<(), Error> {
+        self.blocks
+            .send(Message::Block(block))
+            .map_err(|_| Error::new(ErrorKind::PipelineStopped))
+    }
+
+    /// Copies samples into an empty block and submits it
+    ///
+    /// buffers: One buffer per channel. All buffers must have the same length, which must not
+    /// be greater than the block size.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the number of buffers is not equal to the number of channels,
+    /// or if the buffers are too long or have different lengths.
+    pub fn send(&self, buffers: &[&[I]]) -> Result<(), Error> {
+        let mut block = self.acquire()?;
+        block.copy_from(buffers);
+        self.submit(block)
+    }
+
+    /// Ends the current burst after all previously submitted blocks have been sent
+    ///
+    /// The next block submitted will start a new burst.
+    pub fn end_burst(&self) -> Result<(), Error> {
+        self.blocks
+            .send(Message::EndOfBurst)
+            .map_err(|_| Error::new(ErrorKind::PipelineStopped))
+    }
+}
+
+/// A block of samples to be sent by a transmit pipeline
+pub struct TransmitBlock<I> {
+    /// Samples for all channels, with channel i in [i * block_size, (i + 1) * block_size)
+    data: Vec<I>,
+    /// Number of channels
+    num_channels: usize,
+    /// Maximum number of samples per channel
+    block_size: usize,
+    /// Number of samples per channel to send
+    len: usize,
+}
+
+impl<I> TransmitBlock<I>
+where
+    I: Copy,
+{
+    fn new(num_channels: usize, block_size: usize) -> Self
+    where
+        I: Default,
+    {
+        TransmitBlock {
+            data: vec![I::default(); num_channels * block_size],
+            num_channels,
+            block_size,
+            len: 0,
+        }
+    }
+
+    /// Returns the maximum number of samples per channel that this block can hold
+    pub fn capacity(&self) -> usize {
+        self.block_size
+    }
+
+    /// Returns the number of samples per channel that will be sent
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if this block contains no samples to send
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Sets the number of samples per channel that will be sent
+    ///
+    /// # Panics
+    ///
+    /// This function panics if len is greater than self.capacity().
+    pub fn set_len(&mut self, len: usize) {
+        assert!(len <= self.block_size, "Length greater than block capacity");
+        self.len = len;
+    }
+
+    /// Returns the number of channels in this block
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    /// Returns the full capacity of a channel for writing
+    ///
+    /// After writing, use set_len() to set the number of samples to send.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if channel is not less than self.num_channels().
+    pub fn channel_mut(&mut self, channel: usize) -> &mut [I] {
+        assert!(channel < self.num_channels, "Channel index out of range");
+        let start = channel * self.block_size;
+        &mut self.data[start..start + self.block_size]
+    }
+
+    /// Returns the samples that will be sent on a channel
+    ///
+    /// # Panics
+    ///
+    /// This function panics if channel is not less than self.num_channels().
+    pub fn channel(&self, channel: usize) -> &[I] {
+        assert!(channel < self.num_channels, "Channel index out of range");
+        let start = channel * self.block_size;
+        &self.data[start..start + self.len]
+    }
+
+    /// Copies samples into this block and sets its length
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the number of buffers is not equal to self.num_channels(),
+    /// or if the buffers are too long or have different lengths.
+    pub fn copy_from(&mut self, buffers: &[&[I]]) {
+        assert_eq!(
+            buffers.len(),
+            self.num_channels,
+            "Number of buffers is not equal to the number of channels"
+        );
+        let len = buffers.first().map(|buffer| buffer.len()).unwrap_or(0);
+        assert!(len <= self.block_size, "Buffers are longer than the block size");
+        for (channel, buffer) in buffers.iter().enumerate() {
+            assert_eq!(len, buffer.len(), "Unequal buffer sizes");
+            self.channel_mut(channel)[..len].copy_from_slice(buffer);
+        }
+        self.len = len;
+    }
+}
+
+/// Statistics about a transmit pipeline
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransmitPipelineStats {
+    /// Number of blocks sent
+    pub blocks: u64,
+    /// Number of samples per channel sent
+    pub samples: u64,
+    /// Number of bursts ended
+    pub bursts: u64,
+    /// Number of bursts that the USRP acknowledged
+    pub burst_acks: u64,
+    /// Number of underflows reported by the USRP
+    pub underflows: u64,
+    /// Number of sequence errors (lost packets) reported by the USRP
+    pub sequence_errors: u64,
+    /// Number of time errors (late packets) reported by the USRP
+    pub time_errors: u64,
+    /// Number of send operations that timed out
+    pub timeouts: u64,
+    /// Number of other errors
+    pub errors: u64,
+}
+
+/// State shared between a pipeline and its thread
+struct Shared {
+    /// Set to false by the pipeline thread when it exits
+    running: AtomicBool,
+    stats: Counters,
+    /// The error that caused the pipeline thread to exit, if any
+    error: Mutex<Option<Error>>,
+}
+
+#[derive(Default)]
+struct Counters {
+    blocks: AtomicU64,
+    samples: AtomicU64,
+    bursts: AtomicU64,
+    burst_acks: AtomicU64,
+    underflows: AtomicU64,
+    sequence_errors: AtomicU64,
+    time_errors: AtomicU64,
+    timeouts: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Counters {
+    /// Counts an asynchronous event reported by the USRP
+    fn count_event(&self, event: AsyncEvent) {
+        let counter = match event {
+            AsyncEvent::BurstAck => &self.burst_acks,
+            AsyncEvent::Underflow | AsyncEvent::UnderflowInPacket => &self.underflows,
+            AsyncEvent::SequenceError | AsyncEvent::SequenceErrorInBurst => &self.sequence_errors,
+            AsyncEvent::TimeError => &self.time_errors,
+            _ => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> TransmitPipelineStats {
+        TransmitPipelineStats {
+            blocks: self.blocks.load(Ordering::Relaxed),
+            samples: self.samples.load(Ordering::Relaxed),
+            bursts: self.bursts.load(Ordering::Relaxed),
+            burst_acks: self.burst_acks.load(Ordering::Relaxed),
+            underflows: self.underflows.load(Ordering::Relaxed),
+            sequence_errors: self.sequence_errors.load(Ordering::Relaxed),
+            time_errors: self.time_errors.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Something that the mock sink was asked to do
+    #[derive(Debug, PartialEq)]
+    enum Action {
+        /// Send a number of samples, with or without the start-of-burst flag
+        Send(usize, bool),
+        End,
+    }
+
+    /// A sink that records actions and can simulate send timeouts
+    #[derive(Default)]
+    struct MockSink {
+        actions: Vec<Action>,
+        timed_out: bool,
+    }
+
+    impl BlockSink<i16> for MockSink {
+        fn send_block(
+            &mut self,
+            block: &TransmitBlock<i16>,
+            start_of_burst: bool,
+        ) -> Result<usize, Error> {
+            let sent = if self.timed_out { 0 } else { block.len() };
+            self.actions.push(Action::Send(sent, start_of_burst));
+            Ok(sent)
+        }
+
+        fn end_burst(&mut self) -> Result<(), Error> {
+            self.actions.push(Action::End);
+            Ok(())
+        }
+    }
+
+    fn block(len: usize) -> TransmitBlock<i16> {
+        let mut block = TransmitBlock::new(1, 4);
+        block.set_len(len);
+        block
+    }
+
+    #[test]
+    fn burst_flags() {
+        let mut sink = MockSink::default();
+        let mut state = BurstState::default();
+        // Ending a burst before any samples does nothing
+        state.end(&mut sink).unwrap();
+        state.block(&mut sink, &block(0)).unwrap();
+        state.block(&mut sink, &block(2)).unwrap();
+        state.block(&mut sink, &block(0)).unwrap();
+        state.block(&mut sink, &block(3)).unwrap();
+        state.end(&mut sink).unwrap();
+        state.end(&mut sink).unwrap();
+        state.block(&mut sink, &block(4)).unwrap();
+        assert_eq!(
+            vec![
+                Action::Send(2, true),
+                Action::Send(3, false),
+                Action::End,
+                Action::Send(4, true),
+            ],
+            sink.actions
+        );
+    }
+
+    #[test]
+    fn burst_starts_after_timeout() {
+        let mut sink = MockSink {
+            timed_out: true,
+            ..MockSink::default()
+        };
+        let mut state = BurstState::default();
+        state.block(&mut sink, &block(2)).unwrap();
+        state.end(&mut sink).unwrap();
+        sink.timed_out = false;
+        state.block(&mut sink, &block(2)).unwrap();
+        assert_eq!(
+            vec![Action::Send(0, true), Action::Send(2, true)],
+            sink.actions
+        );
+    }
+
+    #[test]
+    fn flush_on_stop() {
+        let (sender, receiver) = mpsc::sync_channel(8);
+        sender.send(Message::Block(block(1))).unwrap();
+        sender.send(Message::EndOfBurst).unwrap();
+        sender.send(Message::Block(block(2))).unwrap();
+        sender.send(Message::Stop).unwrap();
+        sender.send(Message::Block(block(3))).unwrap();
+
+        let mut sink = MockSink::default();
+        let mut state = BurstState::default();
+        flush(&mut state, &mut sink, &receiver).unwrap();
+        assert_eq!(
+            vec![
+                Action::Send(1, true),
+                Action::End,
+                Action::Send(2, true),
+                Action::Send(3, false),
+                Action::End,
+            ],
+            sink.actions
+        );
+        // The pipeline can be flushed again with no new messages
+        flush(&mut state, &mut sink, &receiver).unwrap();
+        assert_eq!(5, sink.actions.len());
+    }
+
+    #[test]
+    fn count_events() {
+        let counters = Counters::default();
+        let events = [
+            AsyncEvent::BurstAck,
+            AsyncEvent::Underflow,
+            AsyncEvent::UnderflowInPacket,
+            AsyncEvent::SequenceError,
+            AsyncEvent::SequenceErrorInBurst,
+            AsyncEvent::SequenceError,
+            AsyncEvent::TimeError,
+            AsyncEvent::UserPayload,
+        ];
+        for &event in events.iter() {
+            counters.count_event(event);
+        }
+        let stats = counters.snapshot();
+        assert_eq!(1, stats.burst_acks);
+        assert_eq!(2, stats.underflows);
+        assert_eq!(3, stats.sequence_errors);
+        assert_eq!(1, stats.time_errors);
+        assert_eq!(0, stats.errors);
+    }
+
+    #[test]
+    fn try_acquire_while_waiting() {
+        let (free_sender, free_receiver) = mpsc::channel();
+        let (blocks, _block_receiver) = mpsc::sync_channel(1);
+        let sender = TransmitSender {
+            blocks,
+            free: Arc::new(Mutex::new(free_receiver)),
+        };
+        let waiting = sender.clone();
+        let waiter = thread::spawn(move || waiting.acquire().map(|block| block.capacity()));
+        thread::sleep(Duration::from_millis(20));
+        // Does not wait for the other sender
+        assert!(sender.try_acquire().is_none());
+
+        free_sender.send(block(0)).unwrap();
+        assert_eq!(4, waiter.join().unwrap().unwrap());
+        drop(free_sender);
+        assert_eq!(
+            ErrorKind::PipelineStopped,
+            sender.acquire().err().unwrap().kind()
+        );
+    }
+
+    #[test]
+    fn block_copy_from() {
+        let mut block = TransmitBlock::<i16>::new(2, 4);
+        assert!(block.is_empty());
+        block.copy_from(&[&[1, 2, 3], &[4, 5, 6]]);
+        assert_eq!(3, block.len());
+        assert_eq!(&[1, 2, 3], block.channel(0));
+        assert_eq!(&[4, 5, 6], block.channel(1));
+        assert_eq!(4, block.channel_mut(1).len());
+    }
+
+    #[test]
+    #[should_panic]
+    fn block_copy_from_too_long() {
+        let mut block = TransmitBlock::<i16>::new(1, 2);
+        block.copy_from(&[&[1, 2, 3]]);
+    }
+}
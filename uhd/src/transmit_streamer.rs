@@ -1,56 +1,73 @@
 use std::marker::PhantomData;
 use std::ptr;
+use std::sync::Arc;
 
+use crate::async_metadata::AsyncMetadata;
 use crate::TransmitMetadata;
-use crate::error::{check_status, Error};
-use crate::receive_metadata::ReceiveMetadata;
-use crate::stream::StreamCommand;
+use crate::error::{check_status, Error, ErrorKind};
 use crate::usrp::Usrp;
+use crate::utils::{check_buffers, plane_length};
 use std::os::raw::c_void;
 
-/// A streamer used to receive samples from a USRP
+/// A streamer used to transmit samples from a USRP
 ///
-/// The type parameter I is the type of sample that this streamer receives.
+/// The type parameter I is the type of sample that this streamer transmits.
+///
+/// A streamer created with `Usrp::get_tx_stream` borrows the USRP. A streamer created with
+/// `Usrp::get_tx_stream_owned` holds a reference-counted pointer to the USRP instead, so it can
+/// be moved into other threads (see `OwnedTransmitStreamer`).
 #[derive(Debug)]
-pub struct TransmitStreamer<I> {
+pub struct TransmitStreamer<'usrp, I> {
     /// Streamer handle
     handle: uhd_sys::uhd_tx_streamer_handle,
-    /// A vector of pointers to buffers (used in transmit() to convert `&mut [&mut [I]]` to `*mut *mut I`
-    /// without reallocating memory each time
+    /// A vector of pointers to buffers (used in send() to convert `&[&[I]]` to `*const *const I`
+    /// without reallocating memory each time)
     ///
-    /// Invariant: If this is not empty, its length is equal to the value returned by
-    /// self.num_channels().
-    buffer_pointers: Vec<*mut c_void>,
+    /// Invariant: After the streamer is associated with a USRP, the length of this vector is
+    /// equal to the value returned by self.num_channels().
+    buffer_pointers: Vec<*const c_void>,
     /// Link to the USRP that this streamer is associated with
-    // usrp: PhantomData<&'usrp Usrp>,
+    usrp: PhantomData<&'usrp Usrp>,
+    /// The USRP that this streamer keeps alive, for owned streamers
+    ///
+    /// This is dropped after the streamer handle is freed.
+    owner: Option<Arc<Usrp>>,
     /// Item type phantom data
     item_phantom: PhantomData<I>,
 }
 
+/// A transmit streamer that keeps its USRP alive and does not borrow it
+///
+/// Owned streamers are created using `Usrp::get_tx_stream_owned`. They are `'static` and `Send`,
+/// so they can be moved into threads that outlive the code that opened the USRP.
+pub type OwnedTransmitStreamer<I> = TransmitStreamer<'static, I>;
 
-impl<I> TransmitStreamer< I> {
-    /// Creates a receive streamer with a null streamer handle (for internal use only)
+impl<I> TransmitStreamer<'_, I> {
+    /// Creates a transmit streamer with a null streamer handle (for internal use only)
     ///
     /// After creating a streamer with this function, its streamer handle must be initialized.
-    pub(crate) fn new(capacity: usize) -> Self {
+    pub(crate) fn new() -> Self {
         TransmitStreamer {
             handle: ptr::null_mut(),
-            buffer_pointers: Vec::with_capacity(capacity),
-            // usrp: PhantomData,
+            buffer_pointers: Vec::new(),
+            usrp: PhantomData,
+            owner: None,
             item_phantom: PhantomData,
         }
     }
 
-    pub fn buff_size(&self)->usize{
-        self.buffer_pointers.capacity()
-    }
-
-    pub(crate) fn set_len(&mut self, cap: usize){
-        unsafe {self.buffer_pointers.set_len(cap)};
+    /// Initializes buffer_pointers so that its length is equal to self.num_channels()
+    ///
+    /// This must be called after the streamer is associated with a USRP.
+    pub(crate) fn init_buffer_pointers(&mut self) -> Result<(), Error> {
+        let num_channels = self.try_num_channels()?;
+        self.buffer_pointers.resize(num_channels, ptr::null());
+        Ok(())
     }
 
-    pub fn buff_len(&self)->usize{
-        self.buffer_pointers.len()
+    /// Sets the USRP that this streamer keeps alive
+    pub(crate) fn set_owner(&mut self, owner: Arc<Usrp>) {
+        self.owner = Some(owner);
     }
 
     /// Returns a reference to the streamer handle
@@ -64,95 +81,218 @@ impl<I> TransmitStreamer< I> {
 
     /// Returns the number of channels that this streamer is associated with
     pub fn num_channels(&self) -> usize {
+        self.try_num_channels().unwrap()
+    }
+
+    /// Returns the number of channels that this streamer is associated with, or an error if it
+    /// could not be read
+    pub fn try_num_channels(&self) -> Result<usize, Error> {
         let mut num_channels = 0usize;
         check_status(unsafe {
             uhd_sys::uhd_tx_streamer_num_channels(
                 self.handle,
                 &mut num_channels as *mut usize as *mut _,
             )
-        })
-        .unwrap();
-        num_channels
+        })?;
+        Ok(num_channels)
     }
 
-    /// Receives samples from the USRP
+    /// Returns the maximum number of samples (per channel) that can be sent in one packet
+    pub fn max_num_samples(&self) -> usize {
+        self.try_max_num_samples().unwrap()
+    }
+
+    /// Returns the maximum number of samples (per channel) that can be sent in one packet, or
+    /// an error if it could not be read
+    pub fn try_max_num_samples(&self) -> Result<usize, Error> {
+        let mut samples = 0usize;
+        check_status(unsafe {
+            uhd_sys::uhd_tx_streamer_max_num_samps(self.handle, &mut samples as *mut usize as *mut _)
+        })?;
+        Ok(samples)
+    }
+
+    /// Sends samples to the USRP, as a complete burst
     ///
-    /// buffers: One or more buffers (one per channel) where the samples will be written. All
-    /// buffers should have the same length. This function will panic if the number of buffers is
-    /// not equal to self.num_channels(), or if not all buffers have the same length.
+    /// buffers: One or more buffers (one per channel) containing the samples to send. All
+    /// buffers must have the same length.
     ///
-    /// timeout: The timeout for the receive operation, in seconds
+    /// timeout: The timeout for each send operation, in seconds
     ///
-    /// one_packet: If this is true, one call to receive() will not copy samples from more than
-    /// one packet of the underlying protocol
+    /// The buffers are sent in as many send operations as necessary, and the last one ends the
+    /// burst.
     ///
-    /// On success, this function returns a ReceiveMetadata object with information about
-    /// the number of samples actually received.
-    pub fn send(
+    /// This function returns an error if the number of buffers is not equal to
+    /// self.num_channels(), or if not all buffers have the same length. It returns an error of
+    /// kind `ErrorKind::Timeout` if a send operation timed out without sending any samples.
+    pub fn send(&mut self, buffers: &[&[I]], timeout: f64) -> Result<(), Error> {
+        let buffer_length = check_buffers(buffers, self.buffer_pointers.len())?;
+
+        let mut metadata = TransmitMetadata::try_new()?;
+        let mut sent = 0;
+        loop {
+            let remaining: Vec<&[I]> = buffers.iter().map(|buffer| &buffer[sent..]).collect();
+            let samples = self.send_with_metadata(&remaining, &mut metadata, timeout)?;
+            sent += samples;
+            if sent == buffer_length {
+                return Ok(());
+            }
+            if samples == 0 {
+                return Err(Error::with_message(
+                    ErrorKind::Timeout,
+                    format!("Sent {} of {} samples", sent, buffer_length),
+                ));
+            }
+            // The rest of the buffers continue the burst, and the last of them end it
+            metadata = TransmitMetadata::try_with_options(false, true, None)?;
+        }
+    }
+
+    /// Sends samples to the USRP, using the provided metadata to control bursts and timing
+    ///
+    /// buffers: One or more buffers (one per channel) containing the samples to send. All
+    /// buffers must have the same length.
+    ///
+    /// metadata: The start/end of burst flags and optional time for these samples
+    ///
+    /// timeout: The timeout for the send operation, in seconds
+    ///
+    /// On success, this function returns the number of samples (per channel) that were sent.
+    /// This may be less than the buffer length if the timeout expired.
+    ///
+    /// This function returns an error if the number of buffers is not equal to
+    /// self.num_channels(), or if not all buffers have the same length.
+    pub fn send_with_metadata(
         &mut self,
-        buffers: &mut [&mut [I]],
+        buffers: &[&[I]],
+        metadata: &mut TransmitMetadata,
         timeout: f64,
-    ) -> Result<(), Error> {
-        let mut metadata = TransmitMetadata::default();
-        let mut samples_received = 0usize;
-
-        // Initialize buffer_pointers
-        if self.buffer_pointers.is_empty() {
-            self.buffer_pointers
-                .resize(self.num_channels(), ptr::null_mut());
+    ) -> Result<usize, Error> {
+        let buffer_length = check_buffers(buffers, self.buffer_pointers.len())?;
+
+        // Copy buffer pointers into C-compatible form
+        for (entry, buffer) in self.buffer_pointers.iter_mut().zip(buffers.iter()) {
+            *entry = buffer.as_ptr() as *const c_void;
         }
-        // Now buffer_pointers.len() is equal to self.num_channels().
+        self.send_from_pointers(buffer_length, metadata, timeout)
+    }
+
+    /// Sends samples to the USRP from one contiguous buffer that is split into equal-length
+    /// planes, one per channel, using the provided metadata to control bursts and timing
+    ///
+    /// buffer: The samples to send. Samples for channel 0 start at index 0, samples for channel 1
+    /// start at index `buffer.len() / channels`, and so on.
+    ///
+    /// channels: The number of channels. This function returns an error if this is not equal to
+    /// self.num_channels(), or if buffer.len() is not a multiple of channels.
+    ///
+    /// metadata and timeout have the same meaning as in
+    /// [`send_with_metadata`](#method.send_with_metadata).
+    ///
+    /// On success, this function returns the number of samples (per channel) that were sent.
+    pub fn send_planar(
+        &mut self,
+        buffer: &[I],
+        channels: usize,
+        metadata: &mut TransmitMetadata,
+        timeout: f64,
+    ) -> Result<usize, Error> {
+        let plane_length = plane_length(buffer.len(), channels, self.buffer_pointers.len())?;
+        self.send_planes(buffer, plane_length, 0, plane_length, metadata, timeout)
+    }
+
+    /// Sends samples from one contiguous buffer that holds a plane of `plane_length` samples
+    /// for each channel, starting at `offset` within each plane
+    ///
+    /// This function does not allocate, which makes it suitable for real-time send loops.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if buffer.len() is not equal to plane_length * self.num_channels(),
+    /// or if offset + samples is greater than plane_length.
+    pub(crate) fn send_planes(
+        &mut self,
+        buffer: &[I],
+        plane_length: usize,
+        offset: usize,
+        samples: usize,
+        metadata: &mut TransmitMetadata,
+        timeout: f64,
+    ) -> Result<usize, Error> {
         assert_eq!(
-            buffers[0].len(),
-            self.buffer_pointers.len(),
-            "Number of buffers is not equal to this streamer's number of channels"
+            buffer.len(),
+            plane_length * self.buffer_pointers.len(),
+            "Buffer length is not equal to plane length times number of channels"
         );
-        // Check that all buffers have the same length
-        let buffer_length = check_equal_buffer_lengths(buffers);
-
-        // Copy buffer pointers into C-compatible form
-        for (entry, buffer) in self.buffer_pointers.iter_mut().zip(buffers.iter_mut()) {
-            *entry = buffer.as_mut_ptr() as *mut c_void;
+        assert!(offset + samples <= plane_length, "Samples extend past end of plane");
+        let base = buffer.as_ptr();
+        for (i, entry) in self.buffer_pointers.iter_mut().enumerate() {
+            // In bounds because of the length checks above
+            *entry = unsafe { base.add(i * plane_length + offset) } as *const c_void;
         }
+        self.send_from_pointers(samples, metadata, timeout)
+    }
 
+    /// Sends samples from the buffers that buffer_pointers currently points to
+    ///
+    /// Each buffer must contain at least buffer_length samples.
+    fn send_from_pointers(
+        &mut self,
+        buffer_length: usize,
+        metadata: &mut TransmitMetadata,
+        timeout: f64,
+    ) -> Result<usize, Error> {
+        let mut samples_sent = 0usize;
         check_status(unsafe {
             uhd_sys::uhd_tx_streamer_send(
                 self.handle,
-                self.buffer_pointers.as_mut_ptr() as *mut *const _,
+                self.buffer_pointers.as_mut_ptr(),
                 buffer_length as _,
                 metadata.handle_mut(),
                 timeout,
-                &mut samples_received as *mut usize as *mut _,
+                &mut samples_sent as *mut usize as *mut _,
             )
         })?;
-        metadata.set_samples(samples_received);
-
-        Ok(())
+        metadata.set_samples(samples_sent);
+        Ok(samples_sent)
     }
 
-}
+    /// Waits for an asynchronous message from the USRP about this stream
+    ///
+    /// timeout: The maximum time to wait, in seconds
+    ///
+    /// This function returns None if no message was received before the timeout expired.
+    pub fn receive_async_message(&mut self, timeout: f64) -> Result<Option<AsyncMetadata>, Error> {
+        let mut metadata = AsyncMetadata::try_new()?;
+        if self.receive_async_message_into(&mut metadata, timeout)? {
+            Ok(Some(metadata))
+        } else {
+            Ok(None)
+        }
+    }
 
-/// Checks that all provided buffers have the same length. Returns the length of the buffers,
-/// or 0 if there are no buffers. Panics if the buffer lengths are not equal.
-fn check_equal_buffer_lengths<I>(buffers: &mut [&mut [I]]) -> usize {
-    buffers
-        .iter()
-        .fold(None, |prev_size, buffer| {
-            match prev_size {
-                None => {
-                    // Store the size of the first buffer
-                    Some(buffer.len())
-                }
-                Some(prev_size) => {
-                    assert_eq!(prev_size, buffer.len(), "Unequal buffer sizes");
-                    Some(prev_size)
-                }
-            }
-        })
-        .unwrap_or(0)
+    /// Waits for an asynchronous message, reusing an existing metadata object
+    ///
+    /// Returns true if a message was received and copied into metadata.
+    pub(crate) fn receive_async_message_into(
+        &mut self,
+        metadata: &mut AsyncMetadata,
+        timeout: f64,
+    ) -> Result<bool, Error> {
+        let mut valid = false;
+        check_status(unsafe {
+            uhd_sys::uhd_tx_streamer_recv_async_msg(
+                self.handle,
+                metadata.handle_mut(),
+                timeout,
+                &mut valid,
+            )
+        })?;
+        Ok(valid)
+    }
 }
 
-impl<I> Drop for TransmitStreamer< I> {
+impl<I> Drop for TransmitStreamer<'_, I> {
     fn drop(&mut self) {
         let _ = unsafe { uhd_sys::uhd_tx_streamer_free(&mut self.handle) };
     }
@@ -162,5 +302,54 @@ impl<I> Drop for TransmitStreamer< I> {
 // All functions are thread-safe, except that the uhd_tx_streamer send(), uhd_tx_streamer recv(), and
 // uhd_tx_streamer recv_async_msg() functions. The corresponding Rust wrapper functions take &mut
 // self, which enforces single-thread access.
-unsafe impl<I> Send for TransmitStreamer< I> {}
-unsafe impl<I> Sync for TransmitStreamer< I> {}
+unsafe impl<I> Send for TransmitStreamer<'_, I> {}
+unsafe impl<I> Sync for TransmitStreamer<'_, I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a streamer with a null handle and two channels
+    fn two_channel_streamer() -> TransmitStreamer<'static, i16> {
+        let mut streamer = TransmitStreamer::new();
+        streamer.buffer_pointers.resize(2, ptr::null());
+        streamer
+    }
+
+    #[test]
+    fn buffer_mismatch() {
+        let mut streamer = two_channel_streamer();
+        let mut metadata = TransmitMetadata::try_new().unwrap();
+        let a = [0i16; 8];
+        let b = [0i16; 6];
+
+        let error = streamer
+            .send_with_metadata(&[&a], &mut metadata, 0.1)
+            .unwrap_err();
+        assert_eq!(ErrorKind::ChannelCount, error.kind());
+        let error = streamer
+            .send_with_metadata(&[&a, &b], &mut metadata, 0.1)
+            .unwrap_err();
+        assert_eq!(ErrorKind::BufferLength, error.kind());
+        let error = streamer.send(&[&a, &a, &a], 0.1).unwrap_err();
+        assert_eq!(ErrorKind::ChannelCount, error.kind());
+        let error = streamer.send(&[&a, &b], 0.1).unwrap_err();
+        assert_eq!(ErrorKind::BufferLength, error.kind());
+    }
+
+    #[test]
+    fn planar_mismatch() {
+        let mut streamer = two_channel_streamer();
+        let mut metadata = TransmitMetadata::try_new().unwrap();
+        let buffer = [0i16; 9];
+
+        let error = streamer
+            .send_planar(&buffer[..8], 1, &mut metadata, 0.1)
+            .unwrap_err();
+        assert_eq!(ErrorKind::ChannelCount, error.kind());
+        let error = streamer
+            .send_planar(&buffer, 2, &mut metadata, 0.1)
+            .unwrap_err();
+        assert_eq!(ErrorKind::BufferLength, error.kind());
+    }
+}
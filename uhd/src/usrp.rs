@@ -1,26 +1,39 @@
 use std::convert::TryInto;
 use std::ffi::CString;
 use std::ptr;
+use std::sync::Arc;
 
+use crate::device_args::{DeviceAddr, DeviceArgs};
 use crate::error::{check_status, Error};
 use crate::motherboard_eeprom::MotherboardEeprom;
 use crate::range::MetaRange;
 use crate::receive_info::ReceiveInfo;
-use crate::receive_streamer::ReceiveStreamer;
+use crate::receive_streamer::{OwnedReceiveStreamer, ReceiveStreamer};
+use crate::sensor_value::SensorValue;
 use crate::stream::{Item, StreamArgs, StreamArgsC};
 use crate::string_vector::StringVector;
+use crate::transmit_info::TransmitInfo;
+use crate::transmit_streamer::OwnedTransmitStreamer;
 use crate::utils::copy_string;
 use crate::{DaughterBoardEeprom, TimeSpec, TuneRequest, TuneResult, TransmitStreamer};
 
 /// A connection to a USRP device
+#[derive(Debug)]
 pub struct Usrp(uhd_sys::uhd_usrp_handle);
 
 impl Usrp {
-    pub fn find(args: &str) -> Result<Vec<String>, Error> {
+    /// Finds USRPs that match the provided arguments
+    ///
+    /// args: Arguments in the same form that `open` accepts, used to limit the search (for
+    /// example, `type=b200`). An empty string finds all USRPs.
+    pub fn find(args: &str) -> Result<Vec<DeviceAddr>, Error> {
         let args = CString::new(args)?;
         let mut addresses = StringVector::new()?;
         check_status(unsafe { uhd_sys::uhd_usrp_find(args.as_ptr(), addresses.handle_mut()) })?;
-        Ok(addresses.into())
+        Vec::<String>::from(addresses)
+            .iter()
+            .map(|address| address.parse())
+            .collect()
     }
 
     /// Opens a connection to a USRP
@@ -40,6 +53,13 @@ impl Usrp {
         Ok(Usrp(handle))
     }
 
+    /// Opens a connection to a USRP using structured arguments
+    ///
+    /// A `DeviceAddr` returned by `find` can be opened with `open_with_args(address.args())`.
+    pub fn open_with_args(args: &DeviceArgs) -> Result<Self, Error> {
+        Usrp::open(&args.to_string())
+    }
+
     /// Returns a list of registers on this USRP that can be read and written
     ///
     /// mboard: The board number (normally 0 if only one USRP is in use)
@@ -124,6 +144,15 @@ impl Usrp {
         Ok(range)
     }
 
+    /// Returns the supported range of transmit frequencies
+    pub fn get_tx_frequency_range(&self, channel: usize) -> Result<MetaRange, Error> {
+        let mut range = MetaRange::default();
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_freq_range(self.0, channel as _, range.handle())
+        })?;
+        Ok(range)
+    }
+
     /// Returns the current gain of the gain element with the specified name
     pub fn get_rx_gain(&self, channel: usize, name: &str) -> Result<f64, Error> {
         let name = CString::new(name)?;
@@ -161,6 +190,16 @@ impl Usrp {
         Ok(range)
     }
 
+    /// Sets the device time when later commands (such as frequency and gain changes) will take
+    /// effect
+    ///
+    /// Commands sent after this function are timed until `clear_command_time` is called.
+    pub fn set_command_time(&self, time: &TimeSpec, mboard: usize) -> Result<(), Error> {
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_command_time(self.0, time.seconds, time.fraction, mboard as _)
+        })
+    }
+
     /// Clears the command time (?), causing stream commands to be sent immediately
     pub fn clear_command_time(&self, mboard: usize) -> Result<(), Error> {
         check_status(unsafe { uhd_sys::uhd_usrp_clear_command_time(self.0, mboard as _) })
@@ -270,6 +309,34 @@ impl Usrp {
         })?;
         Ok(vector.into())
     }
+    /// Sets the clock source (for example, `internal`, `external`, or `gpsdo`)
+    pub fn set_clock_source(&self, source: &str, mboard: usize) -> Result<(), Error> {
+        let source = CString::new(source)?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_clock_source(self.0, source.as_ptr(), mboard as _)
+        })
+    }
+    /// Returns the current time source
+    pub fn get_time_source(&self, mboard: usize) -> Result<String, Error> {
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_usrp_get_time_source(self.0, mboard as _, buffer, length as _)
+        })
+    }
+    /// Returns the available time sources
+    pub fn get_time_sources(&self, mboard: usize) -> Result<Vec<String>, Error> {
+        let mut vector = StringVector::new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_time_sources(self.0, mboard as _, vector.handle_mut())
+        })?;
+        Ok(vector.into())
+    }
+    /// Sets the time source (for example, `internal`, `external`, or `gpsdo`)
+    pub fn set_time_source(&self, source: &str, mboard: usize) -> Result<(), Error> {
+        let source = CString::new(source)?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_set_time_source(self.0, source.as_ptr(), mboard as _)
+        })
+    }
     /// Returns the available sensors on the motherboard
     pub fn get_mboard_sensor_names(&self, mboard: usize) -> Result<Vec<String>, Error> {
         let mut vector = StringVector::new()?;
@@ -341,6 +408,30 @@ impl Usrp {
         }
     }
 
+    /// Gets information about the transmit configuration of a channel
+    pub fn get_tx_info(&self, channel: usize) -> Result<TransmitInfo, Error> {
+        let mut info_c = uhd_sys::uhd_usrp_tx_info_t {
+            mboard_id: ptr::null_mut(),
+            mboard_name: ptr::null_mut(),
+            mboard_serial: ptr::null_mut(),
+            tx_id: ptr::null_mut(),
+            tx_subdev_name: ptr::null_mut(),
+            tx_subdev_spec: ptr::null_mut(),
+            tx_serial: ptr::null_mut(),
+            tx_antenna: ptr::null_mut(),
+        };
+        unsafe {
+            check_status(uhd_sys::uhd_usrp_get_tx_info(
+                self.0,
+                channel as _,
+                &mut info_c,
+            ))?;
+            let info = TransmitInfo::from_c(&info_c)?;
+            uhd_sys::uhd_usrp_tx_info_free(&mut info_c);
+            Ok(info)
+        }
+    }
+
     /// Returns true if the provided local oscillator is exported
     pub fn get_rx_lo_export_enabled(&self, channel: usize, name: &str) -> Result<bool, Error> {
         let name = CString::new(name)?;
@@ -375,6 +466,45 @@ impl Usrp {
         Ok(vector.into())
     }
 
+    /// Returns the source of a local oscillator (for example, `internal` or `external`)
+    pub fn get_rx_lo_source(&self, channel: usize, name: &str) -> Result<String, Error> {
+        let name = CString::new(name)?;
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_usrp_get_rx_lo_source(
+                self.0,
+                name.as_ptr(),
+                channel as _,
+                buffer,
+                length as _,
+            )
+        })
+    }
+
+    /// Reads a sensor that relates to receiving (for example, `lo_locked`)
+    pub fn get_rx_sensor(&self, name: &str, channel: usize) -> Result<SensorValue, Error> {
+        let name = CString::new(name)?;
+        let mut value = SensorValue::try_new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_rx_sensor(self.0, name.as_ptr(), channel as _, value.handle_mut())
+        })?;
+        Ok(value)
+    }
+
+    /// Reads a sensor on the motherboard (for example, `ref_locked`)
+    pub fn get_mboard_sensor(&self, name: &str, mboard: usize) -> Result<SensorValue, Error> {
+        let name = CString::new(name)?;
+        let mut value = SensorValue::try_new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_mboard_sensor(
+                self.0,
+                name.as_ptr(),
+                mboard as _,
+                value.handle_mut(),
+            )
+        })?;
+        Ok(value)
+    }
+
     /// Returns the names of sensors that relate to receiving
     pub fn get_rx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error> {
         let mut vector = StringVector::new()?;
@@ -384,8 +514,105 @@ impl Usrp {
         Ok(vector.into())
     }
 
+    /// Returns true if the provided transmit local oscillator is exported
+    pub fn get_tx_lo_export_enabled(&self, channel: usize, name: &str) -> Result<bool, Error> {
+        let name = CString::new(name)?;
+        let mut enabled = false;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_lo_export_enabled(
+                self.0,
+                name.as_ptr(),
+                channel as _,
+                &mut enabled,
+            )
+        })?;
+        Ok(enabled)
+    }
+
+    /// Returns the frequency of a transmit local oscillator
+    pub fn get_tx_lo_frequency(&self, channel: usize, name: &str) -> Result<f64, Error> {
+        let name = CString::new(name)?;
+        let mut value = 0.0;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_lo_freq(self.0, name.as_ptr(), channel as _, &mut value)
+        })?;
+        Ok(value)
+    }
+
+    /// Returns the names of transmit local oscillators
+    pub fn get_tx_lo_names(&self, channel: usize) -> Result<Vec<String>, Error> {
+        let mut vector = StringVector::new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_lo_names(self.0, channel as _, vector.handle_mut())
+        })?;
+        Ok(vector.into())
+    }
+
+    /// Returns the source of a transmit local oscillator (for example, `internal` or `external`)
+    pub fn get_tx_lo_source(&self, channel: usize, name: &str) -> Result<String, Error> {
+        let name = CString::new(name)?;
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_usrp_get_tx_lo_source(
+                self.0,
+                name.as_ptr(),
+                channel as _,
+                buffer,
+                length as _,
+            )
+        })
+    }
+
+    /// Reads a sensor that relates to transmitting (for example, `lo_locked`)
+    pub fn get_tx_sensor(&self, name: &str, channel: usize) -> Result<SensorValue, Error> {
+        let name = CString::new(name)?;
+        let mut value = SensorValue::try_new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_sensor(self.0, name.as_ptr(), channel as _, value.handle_mut())
+        })?;
+        Ok(value)
+    }
+
+    /// Returns the names of sensors that relate to transmitting
+    pub fn get_tx_sensor_names(&self, channel: usize) -> Result<Vec<String>, Error> {
+        let mut vector = StringVector::new()?;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_sensor_names(self.0, channel as _, vector.handle_mut())
+        })?;
+        Ok(vector.into())
+    }
+
     /// Opens a stream that can be used to receive samples
     pub fn get_rx_stream<I>(&self, args: &StreamArgs<I>) -> Result<ReceiveStreamer<'_, I>, Error>
+    where
+        I: Item,
+    {
+        self.make_rx_stream(args)
+    }
+
+    /// Opens a stream that can be used to receive samples, and keeps this USRP alive for as long
+    /// as the streamer exists
+    ///
+    /// The returned streamer does not borrow the USRP, so it can be moved into another thread.
+    pub fn get_rx_stream_owned<I>(
+        self: &Arc<Self>,
+        args: &StreamArgs<I>,
+    ) -> Result<OwnedReceiveStreamer<I>, Error>
+    where
+        I: Item,
+    {
+        let mut streamer = self.make_rx_stream(args)?;
+        streamer.set_owner(Arc::clone(self));
+        Ok(streamer)
+    }
+
+    /// Creates a receive streamer
+    ///
+    /// The caller must ensure that this USRP outlives the streamer, either by borrowing it for
+    /// 'usrp or by making the streamer own an Arc that points to it.
+    fn make_rx_stream<'usrp, I>(
+        &self,
+        args: &StreamArgs<I>,
+    ) -> Result<ReceiveStreamer<'usrp, I>, Error>
     where
         I: Item,
     {
@@ -416,7 +643,43 @@ impl Usrp {
         Ok(streamer)
     }
 
-    pub fn get_tx_stream<I>(&self, args: &StreamArgs<I>, cap: usize) -> Result<TransmitStreamer< I>, Error> where I: Item,
+    /// Opens a stream that can be used to transmit samples
+    pub fn get_tx_stream<I>(
+        &self,
+        args: &StreamArgs<I>,
+    ) -> Result<TransmitStreamer<'_, I>, Error>
+    where
+        I: Item,
+    {
+        self.make_tx_stream(args)
+    }
+
+    /// Opens a stream that can be used to transmit samples, and keeps this USRP alive for as long
+    /// as the streamer exists
+    ///
+    /// The returned streamer does not borrow the USRP, so it can be moved into another thread.
+    pub fn get_tx_stream_owned<I>(
+        self: &Arc<Self>,
+        args: &StreamArgs<I>,
+    ) -> Result<OwnedTransmitStreamer<I>, Error>
+    where
+        I: Item,
+    {
+        let mut streamer = self.make_tx_stream(args)?;
+        streamer.set_owner(Arc::clone(self));
+        Ok(streamer)
+    }
+
+    /// Creates a transmit streamer
+    ///
+    /// The caller must ensure that this USRP outlives the streamer, either by borrowing it for
+    /// 'usrp or by making the streamer own an Arc that points to it.
+    fn make_tx_stream<'usrp, I>(
+        &self,
+        args: &StreamArgs<I>,
+    ) -> Result<TransmitStreamer<'usrp, I>, Error>
+    where
+        I: Item,
     {
         let args: StreamArgsC = args.try_into()?;
         let mut args_c = uhd_sys::uhd_stream_args_t {
@@ -430,21 +693,12 @@ impl Usrp {
                 .try_into()
                 .expect("Number of channels too large"),
         };
-        let mut streamer = TransmitStreamer::new(cap);
-        check_status(unsafe{
-            uhd_sys::uhd_tx_streamer_make(streamer.handle_mut() as *mut *mut _)
-        })?;
+        let mut streamer = TransmitStreamer::new();
+        check_status(unsafe { uhd_sys::uhd_tx_streamer_make(streamer.handle_mut()) })?;
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_tx_stream(self.0, &mut args_c, streamer.handle())
         })?;
-        let mut buff_size: usize = streamer.buff_size();
-        check_status(unsafe {
-            uhd_sys::uhd_tx_streamer_max_num_samps(streamer.handle(), &mut buff_size as *mut _)
-        })?;
-        if buff_size!= streamer.buff_size(){
-            println!("Buffer size change: {}", buff_size);
-            streamer.set_len(buff_size);
-        }
+        streamer.init_buffer_pointers()?;
 
         Ok(streamer)
     }
@@ -468,18 +722,14 @@ impl Usrp {
     /// Returns the USRP's current time. Commands can be scheduled relative to this time.
     pub fn get_current_time(&self, mboard: usize) -> Result<TimeSpec, Error> {
         let mut time = TimeSpec::default();
-        let mut seconds_time_t: libc::time_t = Default::default();
-        let mut seconds_time_t_i64: i64 = seconds_time_t as i64;
-
         check_status(unsafe {
             uhd_sys::uhd_usrp_get_time_now(
                 self.0,
                 mboard as _,
-                &mut seconds_time_t_i64 as *mut i64,
+                &mut time.seconds,
                 &mut time.fraction,
             )
         })?;
-        time.seconds = seconds_time_t.into();
         Ok(time)
     }
 
@@ -593,6 +843,65 @@ impl Usrp {
         })
     }
 
+    /// Returns the current transmit sample rate in samples/second
+    pub fn get_tx_sample_rate(&self, channel: usize) -> Result<f64, Error> {
+        let mut value = 0.0;
+        check_status(unsafe { uhd_sys::uhd_usrp_get_tx_rate(self.0, channel as _, &mut value) })?;
+        Ok(value)
+    }
+
+    /// Returns the current gain of the transmit gain element with the specified name
+    pub fn get_tx_gain(&self, channel: usize, name: &str) -> Result<f64, Error> {
+        let name = CString::new(name)?;
+        let mut value = 0.0;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_gain(self.0, channel as _, name.as_ptr(), &mut value)
+        })?;
+        Ok(value)
+    }
+
+    /// Returns the transmit bandwidth
+    pub fn get_tx_bandwidth(&self, channel: usize) -> Result<f64, Error> {
+        let mut value = 0.0;
+        check_status(unsafe {
+            uhd_sys::uhd_usrp_get_tx_bandwidth(self.0, channel as _, &mut value)
+        })?;
+        Ok(value)
+    }
+
+    /// Sets the transmit bandwidth
+    pub fn set_tx_bandwidth(&self, bandwidth: f64, channel: usize) -> Result<(), Error> {
+        check_status(unsafe { uhd_sys::uhd_usrp_set_tx_bandwidth(self.0, bandwidth, channel as _) })
+    }
+
+    /// Returns the receive subdevice specification, in markup form (for example, `A:0 B:0`)
+    pub fn get_rx_subdev_spec(&self, mboard: usize) -> Result<String, Error> {
+        let spec = SubdevSpec::new("")?;
+        check_status(unsafe { uhd_sys::uhd_usrp_get_rx_subdev_spec(self.0, mboard as _, spec.0) })?;
+        spec.to_markup()
+    }
+
+    /// Maps receive frontends to channels using a subdevice specification in markup form
+    /// (for example, `A:0 B:0`)
+    pub fn set_rx_subdev_spec(&self, spec: &str, mboard: usize) -> Result<(), Error> {
+        let spec = SubdevSpec::new(spec)?;
+        check_status(unsafe { uhd_sys::uhd_usrp_set_rx_subdev_spec(self.0, spec.0, mboard as _) })
+    }
+
+    /// Returns the transmit subdevice specification, in markup form (for example, `A:0 B:0`)
+    pub fn get_tx_subdev_spec(&self, mboard: usize) -> Result<String, Error> {
+        let spec = SubdevSpec::new("")?;
+        check_status(unsafe { uhd_sys::uhd_usrp_get_tx_subdev_spec(self.0, mboard as _, spec.0) })?;
+        spec.to_markup()
+    }
+
+    /// Maps transmit frontends to channels using a subdevice specification in markup form
+    /// (for example, `A:0 B:0`)
+    pub fn set_tx_subdev_spec(&self, spec: &str, mboard: usize) -> Result<(), Error> {
+        let spec = SubdevSpec::new(spec)?;
+        check_status(unsafe { uhd_sys::uhd_usrp_set_tx_subdev_spec(self.0, spec.0, mboard as _) })
+    }
+
     /// Returns the available GPIO banks
     pub fn get_gpio_banks(&self, mboard: usize) -> Result<Vec<String>, Error> {
         let mut banks = StringVector::new()?;
@@ -610,6 +919,30 @@ impl Drop for Usrp {
     }
 }
 
+/// A list of subdevice specifications
+struct SubdevSpec(uhd_sys::uhd_subdev_spec_handle);
+
+impl SubdevSpec {
+    fn new(markup: &str) -> Result<Self, Error> {
+        let markup = CString::new(markup)?;
+        let mut handle: uhd_sys::uhd_subdev_spec_handle = ptr::null_mut();
+        check_status(unsafe { uhd_sys::uhd_subdev_spec_make(&mut handle, markup.as_ptr()) })?;
+        Ok(SubdevSpec(handle))
+    }
+
+    fn to_markup(&self) -> Result<String, Error> {
+        copy_string(|buffer, length| unsafe {
+            uhd_sys::uhd_subdev_spec_to_string(self.0, buffer, length as _)
+        })
+    }
+}
+
+impl Drop for SubdevSpec {
+    fn drop(&mut self) {
+        let _ = unsafe { uhd_sys::uhd_subdev_spec_free(&mut self.0) };
+    }
+}
+
 // Thread safety: see https://files.ettus.com/manual/page_general.html#general_threading
 // All functions associated with the Usrp struct are thread-safe
 unsafe impl Send for Usrp {}
@@ -74,6 +74,63 @@ impl Iterator for BufferSizes {
     }
 }
 
+/// Checks that there is one buffer for each of num_channels channels and that all buffers have
+/// the same length
+///
+/// Returns the length of the buffers, or 0 if there are no buffers.
+pub(crate) fn check_buffers<B, I>(buffers: &[B], num_channels: usize) -> Result<usize, Error>
+where
+    B: AsRef<[I]>,
+{
+    if buffers.len() != num_channels {
+        return Err(Error::with_message(
+            ErrorKind::ChannelCount,
+            format!(
+                "Got {} buffers for a stream with {} channels",
+                buffers.len(),
+                num_channels
+            ),
+        ));
+    }
+    let mut lengths = buffers.iter().map(|buffer| buffer.as_ref().len());
+    let length = lengths.next().unwrap_or(0);
+    if let Some(other) = lengths.find(|&other| other != length) {
+        return Err(Error::with_message(
+            ErrorKind::BufferLength,
+            format!("Buffer lengths {} and {} are not equal", length, other),
+        ));
+    }
+    Ok(length)
+}
+
+/// Returns the length of each plane when a buffer of length buffer_length is split into channels
+/// planes, for a stream with num_channels channels
+pub(crate) fn plane_length(
+    buffer_length: usize,
+    channels: usize,
+    num_channels: usize,
+) -> Result<usize, Error> {
+    if channels != num_channels {
+        return Err(Error::with_message(
+            ErrorKind::ChannelCount,
+            format!(
+                "Got {} channel planes for a stream with {} channels",
+                channels, num_channels
+            ),
+        ));
+    }
+    if channels == 0 || buffer_length / channels * channels != buffer_length {
+        return Err(Error::with_message(
+            ErrorKind::BufferLength,
+            format!(
+                "Buffer length {} is not a multiple of the number of channels ({})",
+                buffer_length, channels
+            ),
+        ));
+    }
+    Ok(buffer_length / channels)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,4 +154,50 @@ mod tests {
         assert_eq!(Some(1048576), sizes.next());
         assert_eq!(None, sizes.next());
     }
+
+    #[test]
+    fn planes() {
+        assert_eq!(Ok(1024), plane_length(2048, 2, 2));
+        assert_eq!(Ok(0), plane_length(0, 4, 4));
+    }
+
+    #[test]
+    fn planes_mismatch() {
+        assert_eq!(
+            ErrorKind::BufferLength,
+            plane_length(1001, 2, 2).unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::ChannelCount,
+            plane_length(1000, 2, 1).unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::BufferLength,
+            plane_length(0, 0, 0).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn buffers_match() {
+        let a = [0i16; 8];
+        let b = [0i16; 8];
+        assert_eq!(Ok(8), check_buffers(&[&a[..], &b[..]], 2));
+        assert_eq!(Ok(0), check_buffers::<&[i16], i16>(&[], 0));
+    }
+
+    #[test]
+    fn buffers_channel_count() {
+        let a = [0i16; 8];
+        let error = check_buffers(&[&a[..]], 2).unwrap_err();
+        assert_eq!(ErrorKind::ChannelCount, error.kind());
+        assert_eq!(Some("Got 1 buffers for a stream with 2 channels"), error.message());
+    }
+
+    #[test]
+    fn buffers_length_mismatch() {
+        let a = [0i16; 8];
+        let b = [0i16; 7];
+        let error = check_buffers(&[&a[..], &b[..]], 2).unwrap_err();
+        assert_eq!(ErrorKind::BufferLength, error.kind());
+    }
 }
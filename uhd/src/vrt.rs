@@ -0,0 +1,808 @@
+//!
+//! VITA 49 (VRT) packets
+//!
+//! This module encodes received samples into VRT IF data packets and channel settings into VRT
+//! context packets, and decodes those packets. Each channel is a separate VRT stream whose
+//! stream ID is the base stream ID of the encoder plus the channel index.
+//!
+//! Packets use big-endian 32-bit words. Complex samples are stored with the real part first.
+//! Timestamps have an integer part of type "other" (the USRP device time, which may or may not
+//! be synchronized to UTC or GPS) and a fractional part in picoseconds.
+//!
+//! The payload format is not part of a data packet, so the decoder must be used with the same
+//! host sample type as the encoder.
+//!
+
+use std::convert::{TryFrom, TryInto};
+
+use crate::error::{Error, ErrorKind};
+use crate::io::{Datatype, Sample};
+use crate::receive_metadata::{ReceiveMetadata, ReceiveMetadataSnapshot};
+use crate::receive_pipeline::ReceiveBlock;
+use crate::transmit_metadata::TransmitMetadata;
+use crate::usrp::Usrp;
+use crate::utils::check_buffers;
+use crate::TimeSpec;
+
+/// Packet type of IF data packets with a stream ID
+const DATA_PACKET_TYPE: u32 = 0x1;
+/// Packet type of IF context packets
+const CONTEXT_PACKET_TYPE: u32 = 0x4;
+/// Integer timestamp type "other"
+const TSI_OTHER: u32 = 0x3;
+/// Fractional timestamp type "real time" (picoseconds)
+const TSF_REAL_TIME: u32 = 0x2;
+/// Maximum packet size, in words
+const MAX_PACKET_WORDS: usize = 0xffff;
+const PICOSECONDS_PER_SECOND: f64 = 1e12;
+/// Default maximum number of samples in a data packet
+const DEFAULT_PACKET_SAMPLES: usize = 1024;
+
+/// Context indicator field bits
+const CIF_CHANGE: u32 = 1 << 31;
+const CIF_BANDWIDTH: u32 = 1 << 29;
+const CIF_RF_FREQUENCY: u32 = 1 << 27;
+const CIF_GAIN: u32 = 1 << 23;
+const CIF_SAMPLE_RATE: u32 = 1 << 21;
+/// Highest and lowest context indicator bits that this module can decode
+const CIF_FIRST_BIT: u32 = 30;
+const CIF_LAST_BIT: u32 = 10;
+/// Sizes, in words, of the context fields for bits CIF_FIRST_BIT down to CIF_LAST_BIT
+const CONTEXT_FIELD_WORDS: [usize; 21] = [
+    1, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 1, 1, 2, 1, 2, 11, 11, 13, 13, 1,
+];
+/// Number of fractional bits in frequency and sample rate fields
+const FREQUENCY_RADIX: i32 = 20;
+/// Number of fractional bits in gain fields
+const GAIN_RADIX: i32 = 7;
+
+/// A VRT class identifier
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClassId {
+    /// Organizationally unique identifier (24 bits)
+    pub oui: u32,
+    /// Information class code
+    pub information_class: u16,
+    /// Packet class code
+    pub packet_class: u16,
+}
+
+/// Channel settings carried in context packets
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Context {
+    /// Center (RF reference) frequency, in hertz
+    pub frequency: Option<f64>,
+    /// Sample rate, in samples per second
+    pub sample_rate: Option<f64>,
+    /// Gain, in decibels
+    pub gain: Option<f64>,
+    /// Bandwidth, in hertz
+    pub bandwidth: Option<f64>,
+}
+
+impl Context {
+    /// Reads the frequency, sample rate, overall gain, and bandwidth of a receive channel
+    pub fn from_usrp(usrp: &Usrp, channel: usize) -> Result<Self, Error> {
+        Ok(Context {
+            frequency: Some(usrp.get_rx_frequency(channel)?),
+            sample_rate: Some(usrp.get_rx_sample_rate(channel)?),
+            gain: Some(usrp.get_rx_gain(channel, "")?),
+            bandwidth: Some(usrp.get_rx_bandwidth(channel)?),
+        })
+    }
+}
+
+/// A decoded IF data packet
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataPacket<S> {
+    pub stream_id: u32,
+    pub class_id: Option<ClassId>,
+    /// Packet count (modulo 16), which increases by one for each packet in a stream
+    pub packet_count: u8,
+    /// Device time of the first sample
+    pub time_spec: Option<TimeSpec>,
+    pub samples: Vec<S>,
+}
+
+impl<S> DataPacket<S> {
+    /// Creates transmit metadata for sending the samples in this packet
+    ///
+    /// If start_of_burst is true, the metadata includes the timestamp of this packet so that
+    /// the burst starts at that time.
+    pub fn transmit_metadata(
+        &self,
+        start_of_burst: bool,
+        end_of_burst: bool,
+    ) -> Result<TransmitMetadata, Error> {
+        let time_spec = if start_of_burst {
+            self.time_spec.as_ref()
+        } else {
+            None
+        };
+        TransmitMetadata::try_with_options(start_of_burst, end_of_burst, time_spec)
+    }
+}
+
+/// A decoded IF context packet
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextPacket {
+    pub stream_id: u32,
+    pub class_id: Option<ClassId>,
+    /// Packet count (modulo 16), which increases by one for each packet in a stream
+    pub packet_count: u8,
+    pub time_spec: Option<TimeSpec>,
+    /// True if any setting has changed since the previous context packet
+    pub changed: bool,
+    pub context: Context,
+}
+
+/// A decoded VRT packet
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet<S> {
+    Data(DataPacket<S>),
+    Context(ContextPacket),
+}
+
+/// Encodes samples and settings into VRT packets
+///
+/// Example:
+///
+/// ```no_run
+/// use std::net::UdpSocket;
+/// use num_complex::Complex;
+/// use uhd::vrt::{Context, Encoder};
+/// use uhd::{StreamArgs, StreamCommand, StreamCommandType, StreamTime, Usrp};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let usrp = Usrp::open("")?;
+/// let socket = UdpSocket::bind("0.0.0.0:0")?;
+/// socket.connect("192.168.1.20:4991")?;
+/// let mut encoder = Encoder::new(0x100, usrp.get_rx_sample_rate(0)?).max_packet_samples(360);
+/// let mut packets = Vec::new();
+/// encoder.encode_context(0, &Context::from_usrp(&usrp, 0)?, None, &mut packets)?;
+///
+/// let mut streamer = usrp.get_rx_stream(&StreamArgs::<Complex<i16>>::builder().build())?;
+/// streamer.send_command(&StreamCommand {
+///     command_type: StreamCommandType::StartContinuous,
+///     time: StreamTime::Now,
+/// })?;
+/// let mut buffer = vec![Complex::default(); 3600];
+/// loop {
+///     let metadata = streamer.receive_simple(&mut buffer)?;
+///     encoder.encode(&[&buffer], &metadata, &mut packets)?;
+///     for packet in packets.drain(..) {
+///         socket.send(&packet)?;
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    /// Stream ID of channel 0
+    stream_id: u32,
+    /// Class ID included in all packets
+    class_id: Option<ClassId>,
+    /// Sample rate, used to calculate the timestamps of packets after the first in a block
+    sample_rate: f64,
+    /// Maximum number of samples in a data packet
+    max_packet_samples: usize,
+    /// Data packet count for each channel
+    data_counts: Vec<u8>,
+    /// Context packet count for each channel
+    context_counts: Vec<u8>,
+    /// The most recent context encoded for each channel
+    contexts: Vec<Option<Context>>,
+}
+
+impl Encoder {
+    /// Creates an encoder
+    ///
+    /// stream_id: The stream ID of channel 0. Channel i uses stream ID `stream_id + i`.
+    ///
+    /// sample_rate: The sample rate of the samples that will be encoded
+    pub fn new(stream_id: u32, sample_rate: f64) -> Self {
+        Encoder {
+            stream_id,
+            class_id: None,
+            sample_rate,
+            max_packet_samples: DEFAULT_PACKET_SAMPLES,
+            data_counts: Vec::new(),
+            context_counts: Vec::new(),
+            contexts: Vec::new(),
+        }
+    }
+
+    /// Sets a class ID to include in every packet
+    ///
+    /// If the samples in a data packet do not fill a whole number of words, the packet has a
+    /// class ID that records the number of padding bits even if this is not set.
+    pub fn class_id(self, class_id: ClassId) -> Self {
+        Encoder {
+            class_id: Some(class_id),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of samples in a data packet (default 1024)
+    ///
+    /// Blocks with more samples are split into several packets.
+    pub fn max_packet_samples(self, max_packet_samples: usize) -> Self {
+        Encoder {
+            max_packet_samples: max_packet_samples.max(1),
+            ..self
+        }
+    }
+
+    /// Encodes the samples from a receive operation into data packets, and appends them to
+    /// out (one `Vec` per packet)
+    ///
+    /// buffers: The buffers passed to the receive function, one per channel. Only the number of
+    /// samples in the metadata are encoded.
+    ///
+    /// On success, this returns the number of packets added.
+    pub fn encode<S, B>(
+        &mut self,
+        buffers: &[B],
+        metadata: &ReceiveMetadata,
+        out: &mut Vec<Vec<u8>>,
+    ) -> Result<usize, Error>
+    where
+        S: Sample,
+        B: AsRef<[S]>,
+    {
+        self.encode_snapshot(buffers, &metadata.snapshot()?, out)
+    }
+
+    /// Encodes the samples from a receive operation into data packets, using a metadata
+    /// snapshot
+    pub fn encode_snapshot<S, B>(
+        &mut self,
+        buffers: &[B],
+        metadata: &ReceiveMetadataSnapshot,
+        out: &mut Vec<Vec<u8>>,
+    ) -> Result<usize, Error>
+    where
+        S: Sample,
+        B: AsRef<[S]>,
+    {
+        let length = check_buffers(buffers, buffers.len())?;
+        if metadata.samples > length {
+            return Err(Error::with_message(
+                ErrorKind::BufferLength,
+                format!(
+                    "Metadata reports {} samples, but the buffers have only {}",
+                    metadata.samples, length
+                ),
+            ));
+        }
+        let mut packets = 0;
+        for start in (0..metadata.samples).step_by(self.max_packet_samples) {
+            let end = (start + self.max_packet_samples).min(metadata.samples);
+            let time = metadata
+                .time_spec
+                .map(|time| time + TimeSpec::from_ticks(start as i64, self.sample_rate));
+            for (channel, buffer) in buffers.iter().enumerate() {
+                out.push(self.data_packet(channel, &buffer.as_ref()[start..end], time)?);
+                packets += 1;
+            }
+        }
+        Ok(packets)
+    }
+
+    /// Encodes the samples in a block from a `ReceivePipeline` into data packets
+    pub fn encode_block<S: Sample>(
+        &mut self,
+        block: &ReceiveBlock<S>,
+        out: &mut Vec<Vec<u8>>,
+    ) -> Result<usize, Error> {
+        let buffers: Vec<&[S]> = (0..block.num_channels())
+            .map(|channel| block.channel(channel))
+            .collect();
+        self.encode_snapshot(&buffers, block.metadata(), out)
+    }
+
+    /// Encodes the settings of a channel into a context packet, and appends it to out
+    ///
+    /// The packet has the change indicator set if this is the first context packet for the
+    /// channel or the context is different from the previous one.
+    pub fn encode_context(
+        &mut self,
+        channel: usize,
+        context: &Context,
+        time: Option<TimeSpec>,
+        out: &mut Vec<Vec<u8>>,
+    ) -> Result<(), Error> {
+        let mut indicators = 0;
+        let mut fields = Vec::new();
+        if let Some(bandwidth) = context.bandwidth {
+            indicators |= CIF_BANDWIDTH;
+            push_fixed_64(&mut fields, bandwidth);
+        }
+        if let Some(frequency) = context.frequency {
+            indicators |= CIF_RF_FREQUENCY;
+            push_fixed_64(&mut fields, frequency);
+        }
+        if let Some(gain) = context.gain {
+            indicators |= CIF_GAIN;
+            // Stage 1 gain is in the lower 16 bits, and stage 2 gain is 0
+            fields.push(u32::from(to_fixed(gain, GAIN_RADIX) as i16 as u16));
+        }
+        if let Some(sample_rate) = context.sample_rate {
+            indicators |= CIF_SAMPLE_RATE;
+            push_fixed_64(&mut fields, sample_rate);
+        }
+        if entry(&mut self.contexts, channel) != &Some(*context) {
+            indicators |= CIF_CHANGE;
+            self.contexts[channel] = Some(*context);
+        }
+
+        let count = next_count(&mut self.context_counts, channel);
+        let mut words = self.prologue(
+            CONTEXT_PACKET_TYPE,
+            channel,
+            count,
+            self.class_id,
+            time.as_ref(),
+        )?;
+        words.push(indicators);
+        words.extend(fields);
+        out.push(finish_packet(words, &[])?);
+        Ok(())
+    }
+
+    /// Encodes one data packet
+    fn data_packet<S: Sample>(
+        &mut self,
+        channel: usize,
+        samples: &[S],
+        time: Option<TimeSpec>,
+    ) -> Result<Vec<u8>, Error> {
+        let component_size = if Datatype::of::<S>().is_complex() {
+            S::SIZE / 2
+        } else {
+            S::SIZE
+        };
+        let mut payload = vec![0u8; samples.len() * S::SIZE];
+        for (sample, bytes) in samples.iter().zip(payload.chunks_exact_mut(S::SIZE)) {
+            sample.encode(bytes);
+            // Convert each component from little-endian to big-endian
+            for component in bytes.chunks_exact_mut(component_size) {
+                component.reverse();
+            }
+        }
+        let padding = (4 - payload.len() % 4) % 4;
+        payload.resize(payload.len() + padding, 0);
+        let class_id = if padding != 0 {
+            Some(self.class_id.unwrap_or_default())
+        } else {
+            self.class_id
+        };
+
+        let count = next_count(&mut self.data_counts, channel);
+        let mut words = self.prologue(DATA_PACKET_TYPE, channel, count, class_id, time.as_ref())?;
+        if padding != 0 {
+            // Pad bit count, in the upper 5 bits of the first class ID word
+            words[2] |= ((padding * 8) as u32) << 27;
+        }
+        finish_packet(words, &payload)
+    }
+
+    /// Returns the words of a packet before its payload or context fields, with a packet size
+    /// of 0
+    fn prologue(
+        &self,
+        packet_type: u32,
+        channel: usize,
+        count: u8,
+        class_id: Option<ClassId>,
+        time: Option<&TimeSpec>,
+    ) -> Result<Vec<u32>, Error> {
+        let mut header = packet_type << 28 | u32::from(count) << 16;
+        if class_id.is_some() {
+            header |= 1 << 27;
+        }
+        if time.is_some() {
+            header |= TSI_OTHER << 22 | TSF_REAL_TIME << 20;
+        }
+        let mut words = vec![header, self.stream_id.wrapping_add(channel as u32)];
+        if let Some(class_id) = class_id {
+            words.push(class_id.oui & 0x00ff_ffff);
+            words.push(
+                u32::from(class_id.information_class) << 16 | u32::from(class_id.packet_class),
+            );
+        }
+        if let Some(time) = time {
+            let (seconds, picoseconds) = to_timestamp(time)?;
+            words.push(seconds);
+            words.push((picoseconds >> 32) as u32);
+            words.push(picoseconds as u32);
+        }
+        Ok(words)
+    }
+}
+
+/// Returns the entry for a channel, adding default entries if the vector is too short
+fn entry<T: Default>(entries: &mut Vec<T>, channel: usize) -> &mut T {
+    if entries.len() <= channel {
+        entries.resize_with(channel + 1, T::default);
+    }
+    &mut entries[channel]
+}
+
+/// Returns the packet count for a channel and increments it
+fn next_count(counts: &mut Vec<u8>, channel: usize) -> u8 {
+    let count = entry(counts, channel);
+    let current = *count;
+    *count = (current + 1) % 16;
+    current
+}
+
+/// Sets the packet size in the header, and returns the bytes of the packet
+fn finish_packet(mut words: Vec<u32>, payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let size = words.len() + payload.len() / 4;
+    if size > MAX_PACKET_WORDS {
+        return Err(Error::with_message(
+            ErrorKind::Value,
+            format!("VRT packet size {} words is too large", size),
+        ));
+    }
+    words[0] |= size as u32;
+    let mut bytes = Vec::with_capacity(size * 4);
+    for word in words {
+        bytes.extend_from_slice(&word.to_be_bytes());
+    }
+    bytes.extend_from_slice(payload);
+    Ok(bytes)
+}
+
+/// Converts a time into integer seconds and picoseconds
+fn to_timestamp(time: &TimeSpec) -> Result<(u32, u64), Error> {
+    let time = time.normalized();
+    let mut seconds = time.seconds;
+    let mut picoseconds = (time.fraction * PICOSECONDS_PER_SECOND).round() as u64;
+    if picoseconds >= PICOSECONDS_PER_SECOND as u64 {
+        seconds += 1;
+        picoseconds -= PICOSECONDS_PER_SECOND as u64;
+    }
+    let seconds = u32::try_from(seconds).map_err(|_| {
+        Error::with_message(
+            ErrorKind::Value,
+            format!("Time {} cannot be stored in a VRT timestamp", time),
+        )
+    })?;
+    Ok((seconds, picoseconds))
+}
+
+/// Converts a number into a fixed-point number with the provided number of fractional bits
+fn to_fixed(value: f64, radix: i32) -> i64 {
+    (value * 2f64.powi(radix)).round() as i64
+}
+
+/// Converts a fixed-point number with the provided number of fractional bits into a number
+fn from_fixed(value: i64, radix: i32) -> f64 {
+    value as f64 / 2f64.powi(radix)
+}
+
+/// Appends a 64-bit fixed-point frequency or rate
+fn push_fixed_64(words: &mut Vec<u32>, value: f64) {
+    let fixed = to_fixed(value, FREQUENCY_RADIX) as u64;
+    words.push((fixed >> 32) as u32);
+    words.push(fixed as u32);
+}
+
+/// Decodes one packet from the beginning of bytes
+///
+/// On success, this returns the packet and its length in bytes, so that a buffer with several
+/// packets can be decoded in a loop.
+///
+/// This returns an error of kind `ErrorKind::Value` if the packet is truncated or malformed, is
+/// not an IF data or IF context packet, or has context fields that this module cannot decode.
+pub fn decode<S: Sample>(bytes: &[u8]) -> Result<(Packet<S>, usize), Error> {
+    let mut reader = WordReader { bytes, position: 0 };
+    let header = reader.next()?;
+    let size = (header & 0xffff) as usize;
+    if bytes.len() < size * 4 {
+        return Err(decode_error(format!(
+            "Packet size is {} bytes, but only {} bytes are available",
+            size * 4,
+            bytes.len()
+        )));
+    }
+    reader.bytes = &bytes[..size * 4];
+
+    let packet_type = header >> 28;
+    let packet_count = ((header >> 16) & 0xf) as u8;
+    let stream_id = reader.next()?;
+    let mut pad_bits = 0;
+    let class_id = if header & (1 << 27) != 0 {
+        let first = reader.next()?;
+        let second = reader.next()?;
+        pad_bits = (first >> 27) as usize;
+        Some(ClassId {
+            oui: first & 0x00ff_ffff,
+            information_class: (second >> 16) as u16,
+            packet_class: second as u16,
+        })
+    } else {
+        None
+    };
+    let integer_type = (header >> 22) & 0x3;
+    let fractional_type = (header >> 20) & 0x3;
+    let seconds = if integer_type != 0 {
+        Some(reader.next()?)
+    } else {
+        None
+    };
+    let fractional = if fractional_type != 0 {
+        Some(u64::from(reader.next()?) << 32 | u64::from(reader.next()?))
+    } else {
+        None
+    };
+    let time_spec = seconds.map(|seconds| {
+        let fraction = match fractional {
+            Some(picoseconds) if fractional_type == TSF_REAL_TIME => {
+                picoseconds as f64 / PICOSECONDS_PER_SECOND
+            }
+            _ => 0.0,
+        };
+        TimeSpec::new(i64::from(seconds), fraction)
+    });
+
+    let packet = match packet_type {
+        DATA_PACKET_TYPE => {
+            let has_trailer = header & (1 << 26) != 0;
+            let payload_end = if has_trailer { size - 1 } else { size } * 4;
+            let payload = bytes
+                .get(reader.position..payload_end)
+                .ok_or_else(|| decode_error("Packet is too short".to_string()))?;
+            let payload = &payload[..payload.len().saturating_sub(pad_bits / 8)];
+            Packet::Data(DataPacket {
+                stream_id,
+                class_id,
+                packet_count,
+                time_spec,
+                samples: decode_samples(payload),
+            })
+        }
+        CONTEXT_PACKET_TYPE => {
+            let indicators = reader.next()?;
+            Packet::Context(ContextPacket {
+                stream_id,
+                class_id,
+                packet_count,
+                time_spec,
+                changed: indicators & CIF_CHANGE != 0,
+                context: decode_context(&mut reader, indicators)?,
+            })
+        }
+        _ => {
+            return Err(decode_error(format!(
+                "Unsupported packet type {}",
+                packet_type
+            )))
+        }
+    };
+    Ok((packet, size * 4))
+}
+
+/// Decodes big-endian samples
+fn decode_samples<S: Sample>(payload: &[u8]) -> Vec<S> {
+    let component_size = if Datatype::of::<S>().is_complex() {
+        S::SIZE / 2
+    } else {
+        S::SIZE
+    };
+    let mut bytes = vec![0u8; S::SIZE];
+    payload
+        .chunks_exact(S::SIZE)
+        .map(|sample| {
+            bytes.copy_from_slice(sample);
+            for component in bytes.chunks_exact_mut(component_size) {
+                component.reverse();
+            }
+            S::decode(&bytes)
+        })
+        .collect()
+}
+
+/// Decodes the context fields that follow the context indicator field
+fn decode_context(reader: &mut WordReader<'_>, indicators: u32) -> Result<Context, Error> {
+    let supported = (CIF_LAST_BIT..=CIF_FIRST_BIT).fold(0, |mask, bit| mask | 1 << bit);
+    let unsupported = indicators & !(supported | CIF_CHANGE);
+    if unsupported != 0 {
+        return Err(decode_error(format!(
+            "Unsupported context indicator bits {:#010x}",
+            unsupported
+        )));
+    }
+    let mut context = Context::default();
+    for (bit, &words) in (CIF_LAST_BIT..=CIF_FIRST_BIT)
+        .rev()
+        .zip(CONTEXT_FIELD_WORDS.iter())
+    {
+        let flag = 1 << bit;
+        if indicators & flag == 0 {
+            continue;
+        }
+        match flag {
+            CIF_BANDWIDTH => context.bandwidth = Some(reader.next_fixed_64()?),
+            CIF_RF_FREQUENCY => context.frequency = Some(reader.next_fixed_64()?),
+            CIF_SAMPLE_RATE => context.sample_rate = Some(reader.next_fixed_64()?),
+            CIF_GAIN => {
+                let stage_1 = i64::from(reader.next()? as u16 as i16);
+                context.gain = Some(from_fixed(stage_1, GAIN_RADIX));
+            }
+            _ => {
+                for _ in 0..words {
+                    reader.next()?;
+                }
+            }
+        }
+    }
+    Ok(context)
+}
+
+/// Returns an error for a packet that cannot be decoded
+fn decode_error(message: String) -> Error {
+    Error::with_message(ErrorKind::Value, format!("Invalid VRT packet: {}", message))
+}
+
+/// Reads big-endian words from a packet
+struct WordReader<'b> {
+    bytes: &'b [u8],
+    /// Offset of the next word, in bytes
+    position: usize,
+}
+
+impl WordReader<'_> {
+    fn next(&mut self) -> Result<u32, Error> {
+        let word = self
+            .bytes
+            .get(self.position..self.position + 4)
+            .ok_or_else(|| decode_error("Packet is too short".to_string()))?;
+        self.position += 4;
+        Ok(u32::from_be_bytes(word.try_into().unwrap()))
+    }
+
+    fn next_fixed_64(&mut self) -> Result<f64, Error> {
+        let value = u64::from(self.next()?) << 32 | u64::from(self.next()?);
+        Ok(from_fixed(value as i64, FREQUENCY_RADIX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_complex::Complex;
+
+    #[test]
+    fn data_round_trip() {
+        let a: Vec<_> = (0..5)
+            .map(|i| Complex::new(i as i16, -(i as i16)))
+            .collect();
+        let b: Vec<_> = (0..5).map(|i| Complex::new(1000 + i as i16, 7)).collect();
+        let metadata = ReceiveMetadataSnapshot {
+            samples: 5,
+            time_spec: Some(TimeSpec::new(10, 0.25)),
+            ..ReceiveMetadataSnapshot::default()
+        };
+        let mut encoder = Encoder::new(0x100, 1000.0).max_packet_samples(3);
+        let mut packets = Vec::new();
+        assert_eq!(
+            4,
+            encoder
+                .encode_snapshot(&[&a, &b], &metadata, &mut packets)
+                .unwrap()
+        );
+        // Header, stream ID, 3 timestamp words, and 3 samples
+        assert_eq!(
+            [0x10, 0xe0, 0x00, 0x08, 0x00, 0x00, 0x01, 0x01],
+            packets[1][..8]
+        );
+        assert_eq!([0x03, 0xe8, 0x00, 0x07], packets[1][20..24]);
+
+        let decoded: Vec<DataPacket<Complex<i16>>> = packets
+            .iter()
+            .map(|bytes| match decode(bytes).unwrap() {
+                (Packet::Data(packet), length) => {
+                    assert_eq!(bytes.len(), length);
+                    packet
+                }
+                (other, _) => panic!("Unexpected packet {:?}", other),
+            })
+            .collect();
+        assert_eq!(a[..3], decoded[0].samples[..]);
+        assert_eq!(b[3..], decoded[3].samples[..]);
+        assert_eq!(0x101, decoded[3].stream_id);
+        assert_eq!(1, decoded[3].packet_count);
+        assert_eq!(Some(TimeSpec::new(10, 0.25)), decoded[0].time_spec);
+        assert_eq!(Some(TimeSpec::new(10, 0.253)), decoded[3].time_spec);
+        assert_eq!(None, decoded[0].class_id);
+    }
+
+    #[test]
+    fn padding() {
+        let samples = [Complex::new(1i8, 2), Complex::new(3, 4), Complex::new(5, 6)];
+        let metadata = ReceiveMetadataSnapshot {
+            samples: 3,
+            ..ReceiveMetadataSnapshot::default()
+        };
+        let mut packets = Vec::new();
+        Encoder::new(7, 1.0)
+            .encode_snapshot(&[&samples], &metadata, &mut packets)
+            .unwrap();
+        // Header, stream ID, class ID, and 6 bytes of samples padded to 8
+        assert_eq!(4 * 6, packets[0].len());
+        match decode::<Complex<i8>>(&packets[0]).unwrap().0 {
+            Packet::Data(packet) => {
+                assert_eq!(samples[..], packet.samples[..]);
+                assert_eq!(Some(ClassId::default()), packet.class_id);
+                assert_eq!(None, packet.time_spec);
+            }
+            other => panic!("Unexpected packet {:?}", other),
+        }
+    }
+
+    #[test]
+    fn context_round_trip() {
+        let context = Context {
+            frequency: Some(2.4e9 + 0.5),
+            sample_rate: Some(12.5e6),
+            gain: Some(-10.5),
+            bandwidth: None,
+        };
+        let class_id = ClassId {
+            oui: 0x12_3456,
+            information_class: 1,
+            packet_class: 2,
+        };
+        let mut encoder = Encoder::new(0, 1.0).class_id(class_id);
+        let mut packets = Vec::new();
+        let time = Some(TimeSpec::new(5, 0.0));
+        encoder
+            .encode_context(1, &context, time, &mut packets)
+            .unwrap();
+        encoder
+            .encode_context(1, &context, time, &mut packets)
+            .unwrap();
+        let decode_context = |bytes: &[u8]| match decode::<Complex<f32>>(bytes).unwrap().0 {
+            Packet::Context(packet) => packet,
+            other => panic!("Unexpected packet {:?}", other),
+        };
+        let first = decode_context(&packets[0]);
+        assert_eq!(
+            ContextPacket {
+                stream_id: 1,
+                class_id: Some(class_id),
+                packet_count: 0,
+                time_spec: time,
+                changed: true,
+                context,
+            },
+            first
+        );
+        let second = decode_context(&packets[1]);
+        assert!(!second.changed);
+        assert_eq!(1, second.packet_count);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut packets = Vec::new();
+        Encoder::new(0, 1.0)
+            .encode_context(0, &Context::default(), None, &mut packets)
+            .unwrap();
+        let packet = &packets[0];
+        assert!(decode::<Complex<f32>>(&packet[..packet.len() - 1]).is_err());
+
+        // GPS ASCII field
+        let mut unsupported = packet.clone();
+        unsupported[10] |= 0x02;
+        assert!(decode::<Complex<f32>>(&unsupported).is_err());
+
+        // Extension data packet
+        let mut unknown_type = packet.clone();
+        unknown_type[0] = 0x30 | (unknown_type[0] & 0x0f);
+        assert!(decode::<Complex<f32>>(&unknown_type).is_err());
+    }
+}
@@ -0,0 +1,766 @@
+//!
+//! ZeroMQ streaming compatible with GNU Radio
+//!
+//! `Publisher` sends received samples in the format of the GNU Radio ZMQ PUB Sink block, and
+//! `Subscriber` receives messages in that format (for example, from a GNU Radio ZMQ PUB Sink)
+//! and sends them through a `TransmitStreamer`.
+//!
+//! Each message contains samples from one channel, encoded in little-endian form (see
+//! `io::Sample`). GNU Radio `complex` streams correspond to `Complex<f32>` samples.
+//!
+//! When tags are enabled (the "Pass Tags" option in GNU Radio), each message starts with a
+//! header that contains the offset of its first sample and its stream tags. The publisher adds
+//! the same `rx_time`, `rx_rate`, and `rx_freq` tags as the GNU Radio UHD USRP Source block.
+//! The subscriber uses the `tx_sob`, `tx_eob`, and `tx_time` tags like the UHD USRP Sink block.
+//!
+//! This module is available when the `zmq` feature is enabled.
+//!
+
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use crate::error::{Error, ErrorKind};
+use crate::io::Sample;
+use crate::receive_metadata::{ReceiveMetadata, ReceiveMetadataSnapshot};
+use crate::sample_clock::SampleClock;
+use crate::transmit_metadata::TransmitMetadata;
+use crate::transmit_streamer::TransmitStreamer;
+use crate::TimeSpec;
+
+/// The first two bytes of a GNU Radio tag header
+const HEADER_MAGIC: u16 = 0x5ff0;
+const HEADER_VERSION: u8 = 0x01;
+
+/// Type codes of serialized PMT values
+const PST_TRUE: u8 = 0x00;
+const PST_FALSE: u8 = 0x01;
+const PST_SYMBOL: u8 = 0x02;
+const PST_INT32: u8 = 0x03;
+const PST_DOUBLE: u8 = 0x04;
+const PST_NULL: u8 = 0x06;
+const PST_PAIR: u8 = 0x07;
+const PST_VECTOR: u8 = 0x08;
+const PST_TUPLE: u8 = 0x0a;
+const PST_UINT64: u8 = 0x0b;
+
+/// Maximum number of pairs, vectors, and tuples that a deserialized value can be nested in
+///
+/// Messages come from the network, so this keeps deeply nested values from overflowing the stack.
+const MAX_PMT_DEPTH: usize = 32;
+
+/// Tag keys used by the GNU Radio UHD blocks
+pub const RX_TIME: &str = "rx_time";
+pub const RX_RATE: &str = "rx_rate";
+pub const RX_FREQ: &str = "rx_freq";
+pub const TX_TIME: &str = "tx_time";
+pub const TX_SOB: &str = "tx_sob";
+pub const TX_EOB: &str = "tx_eob";
+
+/// A GNU Radio polymorphic type (PMT) value
+///
+/// Only the types that are commonly used in stream tags are supported.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pmt {
+    Bool(bool),
+    Null,
+    Symbol(String),
+    Int32(i32),
+    Double(f64),
+    Uint64(u64),
+    Pair(Box<Pmt>, Box<Pmt>),
+    Vector(Vec<Pmt>),
+    Tuple(Vec<Pmt>),
+}
+
+impl Pmt {
+    /// Returns a time in the format of the `rx_time` and `tx_time` tags (a tuple of integer
+    /// seconds and fractional seconds)
+    ///
+    /// Times before 0 are not supported, and are replaced with 0.
+    pub fn from_time(time: &TimeSpec) -> Self {
+        let time = time.normalized();
+        Pmt::Tuple(vec![
+            Pmt::Uint64(time.seconds.max(0) as u64),
+            Pmt::Double(if time.seconds < 0 { 0.0 } else { time.fraction }),
+        ])
+    }
+
+    /// Converts a value in the format of the `rx_time` and `tx_time` tags into a time
+    pub fn to_time(&self) -> Option<TimeSpec> {
+        match self {
+            Pmt::Tuple(values) => match values.as_slice() {
+                [Pmt::Uint64(seconds), Pmt::Double(fraction)] => {
+                    Some(TimeSpec::new(*seconds as i64, *fraction))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Appends the serialized form of this value
+    pub fn serialize(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Pmt::Bool(true) => bytes.push(PST_TRUE),
+            Pmt::Bool(false) => bytes.push(PST_FALSE),
+            Pmt::Null => bytes.push(PST_NULL),
+            Pmt::Symbol(name) => {
+                bytes.push(PST_SYMBOL);
+                bytes.extend_from_slice(&(name.len() as u16).to_be_bytes());
+                bytes.extend_from_slice(name.as_bytes());
+            }
+            Pmt::Int32(value) => {
+                bytes.push(PST_INT32);
+                bytes.extend_from_slice(&value.to_be_bytes());
+            }
+            Pmt::Double(value) => {
+                bytes.push(PST_DOUBLE);
+                bytes.extend_from_slice(&value.to_bits().to_be_bytes());
+            }
+            Pmt::Uint64(value) => {
+                bytes.push(PST_UINT64);
+                bytes.extend_from_slice(&value.to_be_bytes());
+            }
+            Pmt::Pair(first, second) => {
+                bytes.push(PST_PAIR);
+                first.serialize(bytes);
+                second.serialize(bytes);
+            }
+            Pmt::Vector(values) | Pmt::Tuple(values) => {
+                let code = if let Pmt::Vector(_) = self {
+                    PST_VECTOR
+                } else {
+                    PST_TUPLE
+                };
+                bytes.push(code);
+                bytes.extend_from_slice(&(values.len() as u32).to_be_bytes());
+                for value in values {
+                    value.serialize(bytes);
+                }
+            }
+        }
+    }
+
+    /// Reads a serialized value from the beginning of bytes, and returns the value and the
+    /// number of bytes that it used
+    ///
+    /// This returns an error of kind `ErrorKind::Value` if the value is truncated, has an
+    /// unsupported type, or is nested more than 32 levels deep.
+    pub fn deserialize(bytes: &[u8]) -> Result<(Self, usize), Error> {
+        let mut reader = Reader { bytes, position: 0 };
+        let value = reader.pmt()?;
+        Ok((value, reader.position))
+    }
+}
+
+/// A stream tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    /// The absolute offset of the sample that the tag is attached to
+    pub offset: u64,
+    pub key: String,
+    pub value: Pmt,
+    /// The block that created the tag (often `Pmt::Bool(false)`, meaning no source)
+    pub source: Pmt,
+}
+
+impl Tag {
+    /// Creates a tag with no source
+    pub fn new(offset: u64, key: &str, value: Pmt) -> Self {
+        Tag {
+            offset,
+            key: key.to_string(),
+            value,
+            source: Pmt::Bool(false),
+        }
+    }
+}
+
+/// Appends a GNU Radio tag header
+///
+/// offset: The absolute offset of the first sample in the message
+pub fn encode_header(offset: u64, tags: &[Tag], bytes: &mut Vec<u8>) {
+    // Fixed-size fields use the byte order of the host, which is little-endian on the platforms
+    // that GNU Radio supports
+    bytes.extend_from_slice(&HEADER_MAGIC.to_le_bytes());
+    bytes.push(HEADER_VERSION);
+    bytes.extend_from_slice(&offset.to_le_bytes());
+    bytes.extend_from_slice(&(tags.len() as u64).to_le_bytes());
+    for tag in tags {
+        bytes.extend_from_slice(&tag.offset.to_le_bytes());
+        Pmt::Symbol(tag.key.clone()).serialize(bytes);
+        tag.value.serialize(bytes);
+        tag.source.serialize(bytes);
+    }
+}
+
+/// Reads a GNU Radio tag header from the beginning of a message, and returns the offset of the
+/// first sample, the tags, and the length of the header in bytes
+pub fn decode_header(bytes: &[u8]) -> Result<(u64, Vec<Tag>, usize), Error> {
+    let mut reader = Reader { bytes, position: 0 };
+    let magic = u16::from_le_bytes(reader.take(2)?.try_into().unwrap());
+    let version = reader.u8()?;
+    if magic != HEADER_MAGIC || version != HEADER_VERSION {
+        return Err(decode_error("Message does not start with a tag header"));
+    }
+    let offset = reader.u64_le()?;
+    let num_tags = reader.u64_le()?;
+    let mut tags = Vec::new();
+    for _ in 0..num_tags {
+        let tag_offset = reader.u64_le()?;
+        let key = match reader.pmt()? {
+            Pmt::Symbol(key) => key,
+            _ => return Err(decode_error("Tag key is not a symbol")),
+        };
+        let value = reader.pmt()?;
+        let source = reader.pmt()?;
+        tags.push(Tag {
+            offset: tag_offset,
+            key,
+            value,
+            source,
+        });
+    }
+    Ok((offset, tags, reader.position))
+}
+
+/// Returns an error for a message that cannot be decoded
+fn decode_error(message: &str) -> Error {
+    Error::with_message(
+        ErrorKind::Value,
+        format!("Invalid ZMQ message: {}", message),
+    )
+}
+
+/// Converts a ZeroMQ error into an Error
+fn zmq_error(error: zmq::Error) -> Error {
+    Error::with_message(ErrorKind::Io, error.to_string())
+}
+
+/// Reads values from a message
+struct Reader<'b> {
+    bytes: &'b [u8],
+    position: usize,
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, length: usize) -> Result<&'b [u8], Error> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + length)
+            .ok_or_else(|| decode_error("Message is too short"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64_le(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn u64_be(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn pmt(&mut self) -> Result<Pmt, Error> {
+        self.nested_pmt(0)
+    }
+
+    /// Reads a value that is inside depth pairs, vectors, or tuples
+    fn nested_pmt(&mut self, depth: usize) -> Result<Pmt, Error> {
+        Ok(match self.u8()? {
+            PST_TRUE => Pmt::Bool(true),
+            PST_FALSE => Pmt::Bool(false),
+            PST_NULL => Pmt::Null,
+            PST_SYMBOL => {
+                let length = u16::from_be_bytes(self.take(2)?.try_into().unwrap());
+                let name = self.take(usize::from(length))?;
+                Pmt::Symbol(String::from_utf8_lossy(name).into_owned())
+            }
+            PST_INT32 => Pmt::Int32(i32::from_be_bytes(self.take(4)?.try_into().unwrap())),
+            PST_DOUBLE => Pmt::Double(f64::from_bits(self.u64_be()?)),
+            PST_UINT64 => Pmt::Uint64(self.u64_be()?),
+            PST_PAIR | PST_VECTOR | PST_TUPLE if depth >= MAX_PMT_DEPTH => {
+                return Err(decode_error("PMT nesting too deep"))
+            }
+            PST_PAIR => Pmt::Pair(
+                Box::new(self.nested_pmt(depth + 1)?),
+                Box::new(self.nested_pmt(depth + 1)?),
+            ),
+            code @ PST_VECTOR | code @ PST_TUPLE => {
+                let length = u32::from_be_bytes(self.take(4)?.try_into().unwrap());
+                let values = (0..length)
+                    .map(|_| self.nested_pmt(depth + 1))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if code == PST_VECTOR {
+                    Pmt::Vector(values)
+                } else {
+                    Pmt::Tuple(values)
+                }
+            }
+            code => return Err(decode_error(&format!("Unsupported PMT type {:#04x}", code))),
+        })
+    }
+}
+
+/// Publishes received samples from one channel, like the GNU Radio ZMQ PUB Sink block
+///
+/// Example:
+///
+/// ```no_run
+/// use num_complex::Complex32;
+/// use uhd::zmq::Publisher;
+/// use uhd::{StreamArgs, StreamCommand, StreamCommandType, StreamTime, Usrp};
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let usrp = Usrp::open("")?;
+/// let mut publisher = Publisher::<Complex32>::bind("tcp://*:5555", usrp.get_rx_sample_rate(0)?)?
+///     .pass_tags(true)
+///     .frequency(usrp.get_rx_frequency(0)?);
+/// let mut streamer = usrp.get_rx_stream(&StreamArgs::<Complex32>::builder().build())?;
+/// streamer.send_command(&StreamCommand {
+///     command_type: StreamCommandType::StartContinuous,
+///     time: StreamTime::Now,
+/// })?;
+/// let mut buffer = vec![Complex32::default(); 4096];
+/// loop {
+///     let metadata = streamer.receive_simple(&mut buffer)?;
+///     publisher.publish(&buffer, &metadata)?;
+/// }
+/// # }
+/// ```
+pub struct Publisher<S> {
+    socket: zmq::Socket,
+    /// True to add a tag header to each message
+    pass_tags: bool,
+    /// Center frequency for rx_freq tags
+    frequency: Option<f64>,
+    /// True if the next message needs an rx_freq tag
+    frequency_changed: bool,
+    /// Tracks sample times to find discontinuities
+    clock: SampleClock,
+    /// Number of samples published
+    samples_published: u64,
+    sample_phantom: PhantomData<S>,
+}
+
+impl<S: Sample> Publisher<S> {
+    /// Creates a publisher and binds it to an endpoint (for example, `tcp://*:5555`)
+    ///
+    /// sample_rate: The sample rate of the samples, used for tags and to find discontinuities
+    pub fn bind(endpoint: &str, sample_rate: f64) -> Result<Self, Error> {
+        let socket = zmq::Context::new().socket(zmq::PUB).map_err(zmq_error)?;
+        socket.bind(endpoint).map_err(zmq_error)?;
+        Ok(Publisher {
+            socket,
+            pass_tags: false,
+            frequency: None,
+            frequency_changed: false,
+            clock: SampleClock::new(sample_rate),
+            samples_published: 0,
+            sample_phantom: PhantomData,
+        })
+    }
+
+    /// Enables or disables tag headers (disabled by default)
+    pub fn pass_tags(self, pass_tags: bool) -> Self {
+        Publisher { pass_tags, ..self }
+    }
+
+    /// Sets the center frequency reported in rx_freq tags
+    pub fn frequency(mut self, frequency: f64) -> Self {
+        self.set_frequency(frequency);
+        self
+    }
+
+    /// Changes the center frequency, and adds an rx_freq tag to the next message
+    pub fn set_frequency(&mut self, frequency: f64) {
+        self.frequency = Some(frequency);
+        self.frequency_changed = true;
+    }
+
+    /// Returns the endpoint that the publisher is bound to
+    ///
+    /// If the endpoint passed to `bind` had a wildcard port (for example, `tcp://127.0.0.1:*`),
+    /// this has the actual port.
+    pub fn endpoint(&self) -> Result<String, Error> {
+        self.socket
+            .get_last_endpoint()
+            .map_err(zmq_error)?
+            .map_err(|_| decode_error("Endpoint is not valid UTF-8"))
+    }
+
+    /// Returns the number of samples published
+    pub fn samples_published(&self) -> u64 {
+        self.samples_published
+    }
+
+    /// Publishes the samples from a receive operation
+    ///
+    /// samples: The buffer passed to the receive function. Only the number of samples in the
+    /// metadata are published.
+    pub fn publish(&mut self, samples: &[S], metadata: &ReceiveMetadata) -> Result<(), Error> {
+        self.publish_snapshot(samples, &metadata.snapshot()?)
+    }
+
+    /// Publishes the samples from a receive operation, using a metadata snapshot
+    ///
+    /// With tags enabled, the first message and every message after a discontinuity have
+    /// rx_time and rx_rate tags, and the first message and every message after the frequency
+    /// changes have an rx_freq tag.
+    pub fn publish_snapshot(
+        &mut self,
+        samples: &[S],
+        metadata: &ReceiveMetadataSnapshot,
+    ) -> Result<(), Error> {
+        if metadata.samples > samples.len() {
+            return Err(Error::with_message(
+                ErrorKind::BufferLength,
+                format!(
+                    "Metadata reports {} samples, but the buffer has only {}",
+                    metadata.samples,
+                    samples.len()
+                ),
+            ));
+        }
+        if metadata.samples == 0 {
+            return Ok(());
+        }
+        let first = self.samples_published == 0;
+        let jump = self.clock.update_snapshot(metadata);
+
+        let mut message = Vec::with_capacity(metadata.samples * S::SIZE);
+        if self.pass_tags {
+            let offset = self.samples_published;
+            let mut tags = Vec::new();
+            if first || jump.is_some() {
+                if let Some(time) = metadata.time_spec {
+                    tags.push(Tag::new(offset, RX_TIME, Pmt::from_time(&time)));
+                }
+                tags.push(Tag::new(
+                    offset,
+                    RX_RATE,
+                    Pmt::Double(self.clock.sample_rate()),
+                ));
+            }
+            if first || self.frequency_changed {
+                if let Some(frequency) = self.frequency {
+                    tags.push(Tag::new(offset, RX_FREQ, Pmt::Double(frequency)));
+                }
+            }
+            encode_header(offset, &tags, &mut message);
+        }
+        self.frequency_changed = false;
+
+        let start = message.len();
+        message.resize(start + metadata.samples * S::SIZE, 0);
+        for (sample, bytes) in samples[..metadata.samples]
+            .iter()
+            .zip(message[start..].chunks_exact_mut(S::SIZE))
+        {
+            sample.encode(bytes);
+        }
+        self.socket.send(message, 0).map_err(zmq_error)?;
+        self.samples_published += metadata.samples as u64;
+        Ok(())
+    }
+}
+
+/// A message received by a `Subscriber`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message<S> {
+    /// The absolute offset of the first sample, if the message had a tag header
+    pub offset: Option<u64>,
+    pub tags: Vec<Tag>,
+    pub samples: Vec<S>,
+}
+
+impl<S> Message<S> {
+    /// Returns the first tag with the provided key
+    pub fn tag(&self, key: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.key == key)
+    }
+
+    /// Creates transmit metadata from the tx_sob, tx_eob, and tx_time tags of this message
+    ///
+    /// A tx_sob or tx_eob tag anywhere in the message sets the corresponding flag for the whole
+    /// message.
+    pub fn transmit_metadata(&self) -> Result<TransmitMetadata, Error> {
+        let time = self.tag(TX_TIME).and_then(|tag| tag.value.to_time());
+        TransmitMetadata::try_with_options(
+            self.tag(TX_SOB).is_some(),
+            self.tag(TX_EOB).is_some(),
+            time.as_ref(),
+        )
+    }
+}
+
+/// Receives samples published in the format of the GNU Radio ZMQ PUB Sink block
+///
+/// Example:
+///
+/// ```no_run
+/// use num_complex::Complex32;
+/// use uhd::zmq::Subscriber;
+/// use uhd::{StreamArgs, Usrp};
+///
+/// # fn main() -> Result<(), uhd::Error> {
+/// let usrp = Usrp::open("")?;
+/// let mut streamer = usrp.get_tx_stream(&StreamArgs::<Complex32>::builder().build())?;
+/// let mut subscriber = Subscriber::<Complex32>::connect("tcp://192.168.1.20:5555")?;
+/// loop {
+///     subscriber.forward(&mut streamer, 1.0)?;
+/// }
+/// # }
+/// ```
+pub struct Subscriber<S> {
+    socket: zmq::Socket,
+    /// True if each message starts with a tag header
+    pass_tags: bool,
+    sample_phantom: PhantomData<S>,
+}
+
+impl<S: Sample> Subscriber<S> {
+    /// Creates a subscriber and connects it to an endpoint (for example,
+    /// `tcp://192.168.1.20:5555`)
+    pub fn connect(endpoint: &str) -> Result<Self, Error> {
+        let socket = zmq::Context::new().socket(zmq::SUB).map_err(zmq_error)?;
+        socket.connect(endpoint).map_err(zmq_error)?;
+        socket.set_subscribe(b"").map_err(zmq_error)?;
+        Ok(Subscriber {
+            socket,
+            pass_tags: false,
+            sample_phantom: PhantomData,
+        })
+    }
+
+    /// Sets whether messages start with tag headers (disabled by default)
+    ///
+    /// This must match the setting of the publisher.
+    pub fn pass_tags(self, pass_tags: bool) -> Self {
+        Subscriber { pass_tags, ..self }
+    }
+
+    /// Waits for a message and decodes it
+    ///
+    /// timeout: The maximum time to wait, in seconds
+    ///
+    /// This returns `Ok(None)` if no message arrived before the timeout, or an error of kind
+    /// `ErrorKind::Value` if the message is not valid.
+    pub fn receive(&mut self, timeout: f64) -> Result<Option<Message<S>>, Error> {
+        let timeout_ms = (timeout * 1000.0).ceil() as i64;
+        if self
+            .socket
+            .poll(zmq::POLLIN, timeout_ms)
+            .map_err(zmq_error)?
+            == 0
+        {
+            return Ok(None);
+        }
+        let bytes = self.socket.recv_bytes(0).map_err(zmq_error)?;
+        let (offset, tags, header_length) = if self.pass_tags {
+            let (offset, tags, length) = decode_header(&bytes)?;
+            (Some(offset), tags, length)
+        } else {
+            (None, Vec::new(), 0)
+        };
+        let payload = &bytes[header_length..];
+        if payload.len() % S::SIZE != 0 {
+            return Err(decode_error(&format!(
+                "Payload length {} is not a multiple of the sample size {}",
+                payload.len(),
+                S::SIZE
+            )));
+        }
+        Ok(Some(Message {
+            offset,
+            tags,
+            samples: payload.chunks_exact(S::SIZE).map(S::decode).collect(),
+        }))
+    }
+
+    /// Waits for a message and sends its samples through a single-channel transmit streamer,
+    /// and returns the number of samples sent
+    ///
+    /// The metadata for the samples comes from the message tags (see
+    /// `Message::transmit_metadata`). If no message arrives before the timeout, this returns 0.
+    pub fn forward(
+        &mut self,
+        streamer: &mut TransmitStreamer<'_, S>,
+        timeout: f64,
+    ) -> Result<usize, Error> {
+        let message = match self.receive(timeout)? {
+            Some(message) => message,
+            None => return Ok(0),
+        };
+        let mut metadata = message.transmit_metadata()?;
+        let end_of_burst = metadata.try_end_of_burst()?;
+        let mut sent = 0;
+        loop {
+            let samples =
+                streamer.send_with_metadata(&[&message.samples[sent..]], &mut metadata, timeout)?;
+            sent += samples;
+            if sent == message.samples.len() {
+                return Ok(sent);
+            }
+            if samples == 0 {
+                return Err(Error::with_message(
+                    ErrorKind::Timeout,
+                    format!("Sent {} of {} samples", sent, message.samples.len()),
+                ));
+            }
+            // The rest of the message continues the burst
+            metadata = TransmitMetadata::try_with_options(false, end_of_burst, None)?;
+        }
+    }
+}
+
+mod fmt {
+    use super::{Publisher, Subscriber};
+    use std::fmt::{Debug, Formatter, Result};
+
+    impl<S> Debug for Publisher<S> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            f.debug_struct("Publisher")
+                .field("pass_tags", &self.pass_tags)
+                .field("frequency", &self.frequency)
+                .field("samples_published", &self.samples_published)
+                .finish()
+        }
+    }
+
+    impl<S> Debug for Subscriber<S> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            f.debug_struct("Subscriber")
+                .field("pass_tags", &self.pass_tags)
+                .finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_complex::Complex;
+
+    #[test]
+    fn nesting_limit() {
+        let mut value = Pmt::Null;
+        for _ in 0..MAX_PMT_DEPTH {
+            value = Pmt::Pair(Box::new(Pmt::Bool(true)), Box::new(value));
+        }
+        let mut bytes = Vec::new();
+        value.serialize(&mut bytes);
+        assert_eq!(value, Pmt::deserialize(&bytes).unwrap().0);
+
+        // A message from the network that would otherwise overflow the stack
+        let bytes = vec![PST_PAIR; 500_000];
+        let error = Pmt::deserialize(&bytes).unwrap_err();
+        assert_eq!(ErrorKind::Value, error.kind());
+        assert!(error.to_string().contains("too deep"));
+        let mut bytes = vec![PST_VECTOR, 0, 0, 0, 1];
+        bytes.extend_from_slice(&[PST_PAIR; 100]);
+        assert!(Pmt::deserialize(&bytes).is_err());
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let tags = vec![
+            Tag::new(10, RX_TIME, Pmt::from_time(&TimeSpec::new(3, 0.25))),
+            Tag {
+                source: Pmt::Symbol("usrp_source0".to_string()),
+                ..Tag::new(10, RX_RATE, Pmt::Double(1e6))
+            },
+            Tag::new(
+                12,
+                "other",
+                Pmt::Pair(
+                    Box::new(Pmt::Int32(-2)),
+                    Box::new(Pmt::Vector(vec![Pmt::Null, Pmt::Bool(true)])),
+                ),
+            ),
+        ];
+        let mut bytes = Vec::new();
+        encode_header(10, &tags, &mut bytes);
+        assert_eq!([0xf0, 0x5f, 0x01, 10, 0, 0, 0, 0, 0, 0, 0, 3], bytes[..12]);
+        // The first tag key is a symbol with a big-endian length
+        assert_eq!([0x02, 0x00, 0x07, b'r'], bytes[27..31]);
+        bytes.extend_from_slice(&[1, 2, 3]);
+
+        let (offset, decoded, length) = decode_header(&bytes).unwrap();
+        assert_eq!(10, offset);
+        assert_eq!(tags, decoded);
+        assert_eq!(bytes.len() - 3, length);
+        assert_eq!(Some(TimeSpec::new(3, 0.25)), decoded[0].value.to_time());
+
+        assert!(decode_header(&bytes[..length - 1]).is_err());
+        assert!(Pmt::deserialize(&[0x0c, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn publish_subscribe_localhost() {
+        let mut publisher = Publisher::<Complex<i16>>::bind("tcp://127.0.0.1:*", 1000.0)
+            .unwrap()
+            .pass_tags(true)
+            .frequency(915e6);
+        let mut subscriber = Subscriber::<Complex<i16>>::connect(&publisher.endpoint().unwrap())
+            .unwrap()
+            .pass_tags(true);
+
+        let samples: Vec<_> = (0..8).map(|i| Complex::new(i, -i)).collect();
+        let metadata = |seconds| ReceiveMetadataSnapshot {
+            samples: 4,
+            time_spec: Some(TimeSpec::new(seconds, 0.0)),
+            ..ReceiveMetadataSnapshot::default()
+        };
+        // A subscriber does not receive messages published before it connects, so publish
+        // until one arrives
+        let first = loop {
+            publisher.publish_snapshot(&samples, &metadata(1)).unwrap();
+            if let Some(message) = subscriber.receive(0.1).unwrap() {
+                break message;
+            }
+        };
+        assert_eq!(samples[..4], first.samples[..]);
+        let first_offset = first.offset.unwrap();
+        if first_offset == 0 {
+            assert_eq!(3, first.tags.len());
+            assert_eq!(
+                Some(&Pmt::Double(915e6)),
+                first.tag(RX_FREQ).map(|tag| &tag.value)
+            );
+        }
+
+        // Samples that continue the last published block have no tags
+        let time = TimeSpec::new(1, 0.0) + TimeSpec::from_ticks(4, 1000.0);
+        let continuous = ReceiveMetadataSnapshot {
+            time_spec: Some(time),
+            ..metadata(0)
+        };
+        publisher.publish_snapshot(&samples, &continuous).unwrap();
+        let second = subscriber.receive(1.0).unwrap().unwrap();
+        assert_eq!(Some(first_offset + 4), second.offset);
+        assert!(second.tags.is_empty());
+
+        // A discontinuity and a frequency change are tagged
+        publisher.set_frequency(920e6);
+        publisher
+            .publish_snapshot(&samples, &metadata(100))
+            .unwrap();
+        let third = subscriber.receive(1.0).unwrap().unwrap();
+        assert_eq!(
+            Some(TimeSpec::new(100, 0.0)),
+            third.tag(RX_TIME).and_then(|tag| tag.value.to_time())
+        );
+        assert_eq!(
+            Some(&Pmt::Double(920e6)),
+            third.tag(RX_FREQ).map(|tag| &tag.value)
+        );
+        assert_eq!(first_offset + 8, third.tags[0].offset);
+
+        assert!(subscriber.receive(0.01).unwrap().is_none());
+    }
+}